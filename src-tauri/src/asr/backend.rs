@@ -0,0 +1,180 @@
+//! What the pipeline needs from a speech recognizer, and which one to load.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::decoding::DecodingOptions;
+use super::engine::AsrEngine;
+use super::language::LanguageMode;
+use super::onnx::OnnxAsr;
+use super::prompt::AsrContext;
+use super::transcript::Transcript;
+use crate::db::models::{ModelEntry, ModelFormat};
+
+/// Whisper models in order of preference.
+const WHISPER_MODELS: &[&str] = &["ggml-base.bin", "ggml-small.bin"];
+
+/// How to decode a segment.
+#[derive(Debug, Clone, Default)]
+pub struct TranscribeOptions {
+    pub language: LanguageMode,
+    /// Use Whisper's translate task: whatever is spoken comes out as English.
+    pub translate: bool,
+    pub decoding: DecodingOptions,
+}
+
+/// The model can't do what the language settings ask; the user has to change one of them.
+#[derive(Debug)]
+pub struct Unsupported(pub String);
+
+impl std::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// For English-only models: fails unless the language is fixed to English. Translating
+/// English speech into English is then a no-op, so the translate task needs no check.
+pub fn english_only(model: &str, language: &LanguageMode) -> Result<(), Unsupported> {
+    match language {
+        LanguageMode::Fixed { language } if language == "en" => Ok(()),
+        _ => Err(Unsupported(format!("{} only transcribes English; set the language to English or use a Whisper model", model))),
+    }
+}
+
+pub trait AsrBackend: Send + Sync {
+    /// Short name for logs ("whisper", "moonshine-base").
+    fn name(&self) -> &str;
+
+    /// Transcribe 16kHz mono audio. Backends ignore what they don't support (a prompt,
+    /// beam search) rather than fail, but a language they can't transcribe is an `Unsupported`
+    /// error rather than text in the wrong language.
+    fn transcribe_with(&self, audio: &[f32], context: Option<&AsrContext>, options: &TranscribeOptions) -> Result<Transcript>;
+
+    /// Plain English text, for callers that need nothing else.
+    fn transcribe(&self, audio: &[f32], context: Option<&AsrContext>) -> Result<String> {
+        Ok(self.transcribe_with(audio, context, &TranscribeOptions::default())?.text)
+    }
+
+    /// Run one short inference on silence so buffers, caches and lazy initialization are
+    /// paid for at load time rather than on the first dictation.
+    fn warm_up(&self) -> Result<()> {
+        let silence = vec![0.0f32; 16000];
+        self.transcribe_with(&silence, None, &TranscribeOptions::default()).map(|_| ())
+    }
+}
+
+/// An ASR engine and model, as the settings UI picks it. What is actually loaded is the
+/// registry's active ASR model; a choice is a way of naming one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BackendChoice {
+    /// whisper.cpp with the best installed ggml model.
+    #[default]
+    Whisper,
+    /// An ONNX export in `models/onnx/<model>/`.
+    Onnx { model: String },
+}
+
+impl BackendChoice {
+    /// The choice an installed ASR model belongs to.
+    pub fn of(entry: &ModelEntry) -> Self {
+        match entry.format {
+            ModelFormat::Onnx => BackendChoice::Onnx {
+                model: Path::new(&entry.model_path).file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            },
+            ModelFormat::Ggml | ModelFormat::Gguf => BackendChoice::Whisper,
+        }
+    }
+
+    /// Model file or directory this choice loads, if installed.
+    pub fn model_path(&self, models_dir: &Path) -> Option<PathBuf> {
+        match self {
+            BackendChoice::Whisper => WHISPER_MODELS.iter()
+                .map(|m| models_dir.join(m))
+                .find(|p| p.exists()),
+            BackendChoice::Onnx { model } => Some(models_dir.join("onnx").join(model)).filter(|p| p.is_dir()),
+        }
+    }
+}
+
+/// Load an installed model with the engine its format needs. Slow: run it off the async runtime.
+pub fn load_model(entry: &ModelEntry) -> Result<Arc<dyn AsrBackend>> {
+    let path = Path::new(&entry.model_path);
+    if !path.exists() {
+        anyhow::bail!("ASR model not found: {}", path.display());
+    }
+    tracing::info!("Loading ASR model {}...", path.display());
+    Ok(match entry.format {
+        ModelFormat::Ggml => Arc::new(AsrEngine::new(path)?),
+        ModelFormat::Onnx => Arc::new(OnnxAsr::new(path)?),
+        ModelFormat::Gguf => anyhow::bail!("{} is not a speech recognition model", path.display()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::ModelType;
+
+    fn entry(format: ModelFormat, path: &str) -> ModelEntry {
+        ModelEntry {
+            id: 1,
+            model_type: ModelType::Asr,
+            format,
+            model_name: String::new(),
+            model_path: path.into(),
+            quantization: None,
+            is_active: true,
+        }
+    }
+
+    #[test]
+    fn choice_of_installed_model() {
+        assert_eq!(BackendChoice::of(&entry(ModelFormat::Ggml, "/m/ggml-small-q5_1.bin")), BackendChoice::Whisper);
+        let onnx = BackendChoice::Onnx { model: "moonshine-base".into() };
+        assert_eq!(BackendChoice::of(&entry(ModelFormat::Onnx, "/m/onnx/moonshine-base")), onnx);
+    }
+
+    #[test]
+    fn english_only_needs_fixed_english() {
+        assert!(english_only("moonshine-base", &LanguageMode::default()).is_ok());
+        let err = english_only("moonshine-base", &LanguageMode::Fixed { language: "hi".into() }).unwrap_err();
+        assert!(err.to_string().starts_with("moonshine-base only transcribes English"));
+        assert!(english_only("moonshine-base", &LanguageMode::Auto).is_err());
+        assert!(english_only("moonshine-base", &LanguageMode::Allowed { languages: vec!["en".into(), "hi".into()] }).is_err());
+    }
+
+    #[test]
+    fn model_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(BackendChoice::Whisper.model_path(dir.path()), None);
+        std::fs::write(dir.path().join("ggml-small.bin"), b"").unwrap();
+        assert_eq!(BackendChoice::Whisper.model_path(dir.path()), Some(dir.path().join("ggml-small.bin")));
+        std::fs::write(dir.path().join("ggml-base.bin"), b"").unwrap();
+        assert_eq!(BackendChoice::Whisper.model_path(dir.path()), Some(dir.path().join("ggml-base.bin")));
+
+        let onnx = BackendChoice::Onnx { model: "moonshine-tiny".into() };
+        assert_eq!(onnx.model_path(dir.path()), None);
+        std::fs::create_dir_all(dir.path().join("onnx/moonshine-tiny")).unwrap();
+        assert_eq!(onnx.model_path(dir.path()), Some(dir.path().join("onnx/moonshine-tiny")));
+    }
+
+    #[test]
+    fn load_reports_missing_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("onnx/nope");
+        let err = load_model(&entry(ModelFormat::Onnx, &missing.to_string_lossy())).err().unwrap();
+        assert!(err.to_string().contains("not found"));
+        let llm = dir.path().join("qwen.gguf");
+        std::fs::write(&llm, b"").unwrap();
+        let err = load_model(&entry(ModelFormat::Gguf, &llm.to_string_lossy())).err().unwrap();
+        assert!(err.to_string().contains("not a speech recognition model"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Decoder settings: search strategy, temperature fallback, threads and context, with
+//! "fast" and "quality" presets.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::db::settings;
+
+const SETTING_KEY: &str = "asr_decoding";
+const MAX_BEAM_SIZE: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    Greedy,
+    Beam,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecodingOptions {
+    pub strategy: Strategy,
+    /// Beams kept with `Strategy::Beam`.
+    pub beam_size: u32,
+    /// Re-decode at rising temperatures when the output loops (compresses too well) or the
+    /// decoder is unsure.
+    pub temperature_fallback: bool,
+    /// Compression ratio (Whisper's entropy threshold) that triggers the fallback.
+    pub compression_ratio_threshold: f32,
+    /// Decoder threads; 0 lets the backend pick (up to 4).
+    pub n_threads: u32,
+    /// Don't condition on text decoded earlier in the same audio.
+    pub no_context: bool,
+}
+
+impl DecodingOptions {
+    /// Cheapest decode that is still accurate on short dictation.
+    pub fn fast() -> Self {
+        Self {
+            strategy: Strategy::Greedy,
+            beam_size: 1,
+            temperature_fallback: false,
+            compression_ratio_threshold: 2.4,
+            n_threads: 0,
+            no_context: true,
+        }
+    }
+
+    /// Beam search with fallback on every core; worth it on big machines.
+    pub fn quality() -> Self {
+        Self {
+            strategy: Strategy::Beam,
+            beam_size: 5,
+            temperature_fallback: true,
+            compression_ratio_threshold: 2.4,
+            n_threads: std::thread::available_parallelism().map(|n| n.get() as u32).unwrap_or(4),
+            no_context: false,
+        }
+    }
+
+    /// Threads to decode with.
+    pub fn threads(&self) -> usize {
+        match self.n_threads {
+            0 => std::thread::available_parallelism().map(|n| n.get().min(4)).unwrap_or(1),
+            n => n as usize,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.strategy == Strategy::Beam && !(1..=MAX_BEAM_SIZE).contains(&self.beam_size) {
+            anyhow::bail!("Beam size must be between 1 and {}", MAX_BEAM_SIZE);
+        }
+        if self.compression_ratio_threshold <= 1.0 {
+            anyhow::bail!("Compression ratio threshold must be above 1");
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecodingOptions {
+    fn default() -> Self {
+        Self::fast()
+    }
+}
+
+/// What the user picked: a preset, resolved when used so "quality" follows the machine, or
+/// explicit options.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "preset", rename_all = "lowercase")]
+pub enum DecodingSetting {
+    #[default]
+    Fast,
+    Quality,
+    Custom(DecodingOptions),
+}
+
+impl DecodingSetting {
+    pub fn options(&self) -> DecodingOptions {
+        match self {
+            DecodingSetting::Fast => DecodingOptions::fast(),
+            DecodingSetting::Quality => DecodingOptions::quality(),
+            DecodingSetting::Custom(options) => options.clone(),
+        }
+    }
+}
+
+pub fn load(conn: &Connection) -> DecodingSetting {
+    settings::get(conn, SETTING_KEY).ok().flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(conn: &Connection, setting: &DecodingSetting) -> Result<()> {
+    setting.options().validate()?;
+    settings::set(conn, SETTING_KEY, &serde_json::to_string(setting)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn test_db() -> Connection {
+        schema::init_db(std::path::Path::new(":memory:")).unwrap()
+    }
+
+    #[test]
+    fn defaults_to_fast() {
+        let conn = test_db();
+        assert_eq!(load(&conn), DecodingSetting::Fast);
+        assert_eq!(load(&conn).options(), DecodingOptions::fast());
+    }
+
+    #[test]
+    fn presets_differ() {
+        let quality = DecodingSetting::Quality.options();
+        assert_eq!(quality.strategy, Strategy::Beam);
+        assert!(quality.temperature_fallback);
+        assert!(quality.threads() >= 1);
+        assert_eq!(DecodingSetting::Fast.options().strategy, Strategy::Greedy);
+        assert!((1..=4).contains(&DecodingOptions::fast().threads()));
+    }
+
+    #[test]
+    fn custom_roundtrip() {
+        let conn = test_db();
+        let custom = DecodingSetting::Custom(DecodingOptions { beam_size: 8, n_threads: 12, ..DecodingOptions::quality() });
+        save(&conn, &custom).unwrap();
+        assert_eq!(load(&conn), custom);
+        assert_eq!(load(&conn).options().threads(), 12);
+    }
+
+    #[test]
+    fn serialized_shape() {
+        assert_eq!(serde_json::to_string(&DecodingSetting::Quality).unwrap(), r#"{"preset":"quality"}"#);
+        let custom: DecodingSetting = serde_json::from_str(
+            r#"{"preset":"custom","strategy":"beam","beam_size":3,"temperature_fallback":true,
+                "compression_ratio_threshold":2.4,"n_threads":16,"no_context":false}"#,
+        ).unwrap();
+        assert_eq!(custom.options().beam_size, 3);
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        let conn = test_db();
+        let huge_beam = DecodingOptions { strategy: Strategy::Beam, beam_size: 64, ..DecodingOptions::fast() };
+        assert!(save(&conn, &DecodingSetting::Custom(huge_beam)).is_err());
+        let bad_ratio = DecodingOptions { compression_ratio_threshold: 0.5, ..DecodingOptions::fast() };
+        assert!(save(&conn, &DecodingSetting::Custom(bad_ratio)).is_err());
+        assert_eq!(load(&conn), DecodingSetting::Fast);
+    }
+}
@@ -1,9 +1,25 @@
 use anyhow::Result;
 use std::path::Path;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState};
+
+use super::backend::{AsrBackend, TranscribeOptions};
+use super::decoding::{DecodingOptions, Strategy};
+use super::language::{self, LanguageMode};
+use super::pool::Pool;
+use super::prompt::AsrContext;
+use super::transcript::{Segment, Token, Transcript};
+
+/// Temperature added on each fallback retry, as in Whisper.
+const TEMPERATURE_STEP: f32 = 0.2;
+/// Idle states kept for reuse. Dictation decodes one segment at a time; the spare covers a
+/// preview or a second caller overlapping it.
+const MAX_IDLE_STATES: usize = 2;
+/// Samples in one 30 s Whisper window at 16 kHz.
+const WINDOW_SAMPLES: usize = 30 * 16000;
 
 pub struct AsrEngine {
     ctx: WhisperContext,
+    states: Pool<WhisperState>,
 }
 
 impl AsrEngine {
@@ -16,34 +32,162 @@ impl AsrEngine {
             params,
         )
         .map_err(|e| anyhow::anyhow!("Failed to load whisper model: {}", e))?;
-        Ok(Self { ctx })
+        Ok(Self { ctx, states: Pool::new(MAX_IDLE_STATES) })
+    }
+
+    fn read_segments(&self, state: &WhisperState) -> Result<Vec<Segment>> {
+        let n = state.full_n_segments()
+            .map_err(|e| anyhow::anyhow!("Failed to get segments: {}", e))?;
+        let eot = self.ctx.token_eot();
+        let mut segments = Vec::with_capacity(n.max(0) as usize);
+        for i in 0..n {
+            let Ok(text) = state.full_get_segment_text(i) else { continue };
+            let mut tokens = Vec::new();
+            for j in 0..state.full_n_tokens(i).unwrap_or(0) {
+                let Ok(id) = state.full_get_token_id(i, j) else { continue };
+                // Everything from end-of-text up is a special or timestamp token
+                if id >= eot {
+                    continue;
+                }
+                tokens.push(Token {
+                    id,
+                    text: state.full_get_token_text(i, j).unwrap_or_default(),
+                    p: state.full_get_token_prob(i, j).unwrap_or(0.0),
+                });
+            }
+            segments.push(Segment {
+                // whisper.cpp counts in 10ms steps
+                start_ms: state.full_get_segment_t0(i).unwrap_or(0) * 10,
+                end_ms: state.full_get_segment_t1(i).unwrap_or(0) * 10,
+                text,
+                tokens,
+                no_speech_prob: None,
+            });
+        }
+        Ok(segments)
     }
 
-    pub fn transcribe(&self, audio: &[f32]) -> Result<String> {
-        let mut state = self.ctx.create_state()
-            .map_err(|e| anyhow::anyhow!("Failed to create state: {}", e))?;
+    /// Probability that the audio `full` just decoded holds no speech: the `<|nospeech|>`
+    /// token's share of the prediction right after `<|startoftranscript|>`, which is where
+    /// Whisper reads it (the language and task tokens come later, so they can't affect it).
+    /// whisper-rs 0.13 doesn't report it, so this decodes that one token again on the
+    /// encoder output of the last window.
+    fn no_speech_prob(&self, state: &mut WhisperState, threads: usize) -> Option<f32> {
+        state.decode(&[self.ctx.token_sot()], 0, threads).ok()?;
+        softmax_at(state.get_logits().ok()?, self.ctx.token_nosp() as usize)
+    }
+
+    /// The language to decode as, or `None` to let Whisper detect it while decoding.
+    fn choose_language(&self, state: &mut WhisperState, audio: &[f32], mode: &LanguageMode, threads: usize) -> Result<Option<String>> {
+        if !self.ctx.is_multilingual() {
+            // English-only (.en) models can't detect or decode anything else
+            return Ok(Some("en".into()));
+        }
+        match mode {
+            LanguageMode::Fixed { language } => Ok(Some(language.clone())),
+            LanguageMode::Auto => Ok(None),
+            LanguageMode::Allowed { languages } if languages.len() <= 1 => Ok(languages.first().cloned()),
+            LanguageMode::Allowed { languages } => {
+                state.pcm_to_mel(audio, threads)
+                    .map_err(|e| anyhow::anyhow!("Failed to compute mel: {}", e))?;
+                let (_, probs) = state.lang_detect(0, threads)
+                    .map_err(|e| anyhow::anyhow!("Language detection failed: {}", e))?;
+                let picked = language::pick_allowed(&probs, languages, whisper_rs::get_lang_id)
+                    .unwrap_or(&languages[0]);
+                tracing::debug!("Detected language {} among {:?}", picked, languages);
+                Ok(Some(picked.to_string()))
+            }
+        }
+    }
+
+    /// Whisper keeps at most half its text context for the prompt; anything longer is cut
+    /// from the front, which would lose the vocabulary rather than the oldest text.
+    fn initial_prompt(&self, context: &AsrContext) -> String {
+        let budget = (self.ctx.n_text_ctx() / 2 - 1).max(0) as usize;
+        context.initial_prompt(budget, |s| match self.ctx.tokenize(s, budget * 4) {
+            Ok(tokens) => tokens.len(),
+            // Over the limit or untokenizable: rough English estimate
+            Err(_) => s.len() / 3 + 1,
+        })
+    }
+}
 
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-        params.set_language(Some("en"));
+/// Softmax probability of `logits[i]`.
+fn softmax_at(logits: &[f32], i: usize) -> Option<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let sum: f32 = logits.iter().map(|l| (l - max).exp()).sum();
+    logits.get(i).map(|l| (l - max).exp() / sum)
+}
+
+fn sampling_strategy(decoding: &DecodingOptions) -> SamplingStrategy {
+    match decoding.strategy {
+        Strategy::Greedy => SamplingStrategy::Greedy { best_of: 1 },
+        // Negative patience is whisper.cpp's "stop when beam_size candidates finished"
+        Strategy::Beam => SamplingStrategy::BeamSearch { beam_size: decoding.beam_size.max(1) as i32, patience: -1.0 },
+    }
+}
+
+impl AsrBackend for AsrEngine {
+    fn name(&self) -> &str {
+        "whisper"
+    }
+
+    /// Transcribe (or translate) in the language `options` selects, with segment timings,
+    /// token probabilities and the language used.
+    fn transcribe_with(&self, audio: &[f32], context: Option<&AsrContext>, options: &TranscribeOptions) -> Result<Transcript> {
+        let mut state = self.states.take(|| {
+            self.ctx.create_state().map_err(|e| anyhow::anyhow!("Failed to create state: {}", e))
+        })?;
+        let decoding = &options.decoding;
+        let chosen = self.choose_language(&mut state, audio, &options.language, decoding.threads())?;
+
+        let mut params = FullParams::new(sampling_strategy(decoding));
+        params.set_n_threads(decoding.threads() as i32);
+        params.set_no_context(decoding.no_context);
+        params.set_temperature(0.0);
+        if decoding.temperature_fallback {
+            params.set_temperature_inc(TEMPERATURE_STEP);
+            params.set_entropy_thold(decoding.compression_ratio_threshold);
+        } else {
+            params.set_temperature_inc(0.0);
+        }
+        params.set_language(Some(chosen.as_deref().unwrap_or("auto")));
+        params.set_translate(options.translate);
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
         params.set_suppress_blank(true);
-        params.set_no_timestamps(true);
+        // Timestamp tokens give real segment boundaries instead of one per 30s window
+        params.set_no_timestamps(false);
+        let prompt = context.map(|c| self.initial_prompt(c)).unwrap_or_default();
+        if !prompt.is_empty() {
+            tracing::debug!("ASR prompt: {}", prompt);
+            params.set_initial_prompt(&prompt);
+        }
 
-        state.full(params, audio)
-            .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
+        if let Err(e) = state.full(params, audio) {
+            // Don't hand a half-run state to the next segment
+            state.discard();
+            anyhow::bail!("Transcription failed: {}", e);
+        }
 
-        let n = state.full_n_segments()
-            .map_err(|e| anyhow::anyhow!("Failed to get segments: {}", e))?;
-        let mut text = String::new();
-        for i in 0..n {
-            if let Ok(seg) = state.full_get_segment_text(i) {
-                text.push_str(&seg);
+        let mut segments = self.read_segments(&state)?;
+        let language = match chosen {
+            Some(code) => code,
+            None => state.full_lang_id_from_state().ok()
+                .and_then(whisper_rs::get_lang_str)
+                .unwrap_or("en")
+                .to_string(),
+        };
+        // Only the last window's encoding is left, so longer audio goes without
+        if audio.len() <= WINDOW_SAMPLES {
+            let no_speech = self.no_speech_prob(&mut state, decoding.threads());
+            for segment in &mut segments {
+                segment.no_speech_prob = no_speech;
             }
         }
-        Ok(text.trim().to_string())
+        Ok(Transcript::from_segments(segments, language))
     }
 }
 
@@ -58,12 +202,34 @@ mod tests {
         if base.exists() { base } else { cfg.models_dir.join("ggml-small.bin") }
     }
 
+    #[test]
+    fn softmax_of_logits() {
+        let p = softmax_at(&[1.0, 1.0, 1.0, 1.0], 2).unwrap();
+        assert!((p - 0.25).abs() < 1e-6);
+        assert!(softmax_at(&[0.0, 50.0], 1).unwrap() > 0.99);
+        assert_eq!(softmax_at(&[0.0], 3), None);
+    }
+
+    #[test]
+    #[ignore] // requires whisper model
+    fn silence_reports_no_speech() {
+        let engine = AsrEngine::new(&asr_model_path()).unwrap();
+        let t = engine.transcribe_with(&vec![0.0f32; 16000], None, &TranscribeOptions::default()).unwrap();
+        assert!(t.segments.iter().all(|s| s.no_speech_prob.is_some()));
+        if let Some(prob) = t.no_speech_prob() {
+            assert!((0.0..=1.0).contains(&prob));
+        }
+        // Fed through the filter, whatever Whisper made of the silence is gone
+        let (filtered, _) = crate::asr::hallucination::filter(t, &Default::default());
+        assert!(filtered.text.is_empty() || filtered.text.starts_with('['));
+    }
+
     #[test]
     #[ignore] // requires whisper model
     fn transcribe_silence_returns_empty() {
         let engine = AsrEngine::new(&asr_model_path()).unwrap();
         let silence = vec![0.0f32; 16000]; // 1 second of silence
-        let text = engine.transcribe(&silence).unwrap();
+        let text = engine.transcribe(&silence, None).unwrap();
         // Whisper on silence typically returns empty or bracketed noise markers
         assert!(text.is_empty() || text.starts_with('[') || text.starts_with('('));
     }
@@ -74,8 +240,74 @@ mod tests {
         let engine = AsrEngine::new(&asr_model_path()).unwrap();
         // Generate a 2-second 440Hz tone — won't produce real words but tests the pipeline
         let audio: Vec<f32> = (0..32000).map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin() * 0.5).collect();
-        let text = engine.transcribe(&audio).unwrap();
+        let text = engine.transcribe(&audio, None).unwrap();
         // Just verify it doesn't crash and returns a string
         assert!(text.len() < 10000);
     }
+
+    #[test]
+    #[ignore] // requires whisper model
+    fn transcribe_with_context() {
+        let engine = AsrEngine::new(&asr_model_path()).unwrap();
+        let ctx = AsrContext {
+            vocabulary: vec!["Kubernetes".into(), "kubectl".into()],
+            window_title: "Terminal".into(),
+            previous_text: "Let me check the cluster.".into(),
+        };
+        assert!(!engine.initial_prompt(&ctx).is_empty());
+        let silence = vec![0.0f32; 16000];
+        assert!(engine.transcribe(&silence, Some(&ctx)).is_ok());
+    }
+
+    #[test]
+    #[ignore] // requires whisper model
+    fn transcribe_reports_language() {
+        let engine = AsrEngine::new(&asr_model_path()).unwrap();
+        let silence = vec![0.0f32; 16000];
+        let fixed = TranscribeOptions { language: LanguageMode::Fixed { language: "de".into() }, translate: false, ..Default::default() };
+        let t = engine.transcribe_with(&silence, None, &fixed).unwrap();
+        // English-only models always decode as English
+        assert!(t.language == "de" || t.language == "en");
+        let allowed = TranscribeOptions { language: LanguageMode::Allowed { languages: vec!["en".into(), "hi".into()] }, translate: true, ..Default::default() };
+        let t = engine.transcribe_with(&silence, None, &allowed).unwrap();
+        // Translating still reports the spoken language
+        assert!(t.language == "en" || t.language == "hi");
+    }
+
+    #[test]
+    #[ignore] // requires whisper model
+    fn transcribe_with_beam_search() {
+        let engine = AsrEngine::new(&asr_model_path()).unwrap();
+        let options = TranscribeOptions { decoding: DecodingOptions::quality(), ..Default::default() };
+        let silence = vec![0.0f32; 16000];
+        assert!(engine.transcribe_with(&silence, None, &options).is_ok());
+    }
+
+    #[test]
+    #[ignore] // requires whisper model
+    fn transcribe_reuses_state() {
+        let engine = AsrEngine::new(&asr_model_path()).unwrap();
+        engine.warm_up().unwrap();
+        assert_eq!(engine.states.idle(), 1);
+        let silence = vec![0.0f32; 16000];
+        std::thread::scope(|s| {
+            for _ in 0..3 {
+                s.spawn(|| engine.transcribe(&silence, None).unwrap());
+            }
+        });
+        assert!((1..=MAX_IDLE_STATES).contains(&engine.states.idle()));
+    }
+
+    #[test]
+    #[ignore] // requires whisper model
+    fn transcribe_returns_segments() {
+        let engine = AsrEngine::new(&asr_model_path()).unwrap();
+        let audio: Vec<f32> = (0..32000).map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin() * 0.5).collect();
+        let t = engine.transcribe_with(&audio, None, &TranscribeOptions::default()).unwrap();
+        for seg in &t.segments {
+            assert!(seg.start_ms <= seg.end_ms);
+            assert!(seg.tokens.iter().all(|tok| (0.0..=1.0).contains(&tok.p)));
+        }
+        assert_eq!(t.text, t.segments.iter().map(|s| s.text.as_str()).collect::<String>().trim());
+    }
 }
@@ -0,0 +1,372 @@
+//! Drops or trims segments Whisper invents on silence, breathing and keyboard noise:
+//! "Thank you for watching.", noise markers, and phrases looping on themselves.
+
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use super::transcript::{Segment, Transcript};
+use crate::db::settings;
+
+const SETTING_KEY: &str = "hallucination_filter";
+
+/// Phrases from video subtitles in Whisper's training data. A segment that is nothing but
+/// these is dropped; inside real dictation ("the spec was translated by Ana") they are only
+/// cut when the decoder was unsure of the segment.
+const KNOWN_PHRASES: &[&str] = &[
+    "thank you for watching",
+    "thanks for watching",
+    "thank you so much for watching",
+    "please subscribe",
+    "like and subscribe",
+    "subscribe to my channel",
+    "see you in the next video",
+    "subtitles by",
+    "amara org",
+    "transcribed by",
+    "translated by",
+];
+
+/// Short phrases Whisper also produces on noise, but people do say. Only dropped when the
+/// decoder wasn't confident.
+const SUSPECT_PHRASES: &[&str] = &["thank you", "thanks", "bye", "you", "okay", "so", "the end"];
+
+/// Words a sentence may have besides known phrases and still count as only the phrase:
+/// "Subtitles by *the* Amara.org *community*".
+const PHRASE_EXTRA_WORDS: usize = 3;
+
+/// A run has to repeat this often before it counts as a loop.
+const MIN_REPEATS: usize = 3;
+const MAX_LOOP_WORDS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterConfig {
+    /// A segment at least this likely to be silence is dropped if its tokens are also unsure.
+    pub no_speech_threshold: f32,
+    /// Average token log-probability below which a segment counts as unsure.
+    pub logprob_threshold: f32,
+    /// Text compressing better than this (zlib, bytes in / bytes out) is looping on itself.
+    pub compression_ratio_threshold: f32,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        // Whisper's own decoding fallback thresholds; no_speech_prob is read the same way
+        // Whisper reads it (see AsrEngine::no_speech_prob), so its 0.6 carries over
+        Self { no_speech_threshold: 0.6, logprob_threshold: -1.0, compression_ratio_threshold: 2.4 }
+    }
+}
+
+/// Thresholds from settings, defaults for anything unset.
+pub fn load(conn: &Connection) -> FilterConfig {
+    settings::get(conn, SETTING_KEY).ok().flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason {
+    /// "[BLANK_AUDIO]", "(keyboard clicking)".
+    NoiseMarker,
+    NoSpeech { prob: f32, avg_logprob: f32 },
+    KnownPhrase(String),
+    SuspectPhrase { avg_logprob: f32 },
+    Repetitive { ratio: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    /// Index of the segment in the unfiltered transcript.
+    pub segment: usize,
+    pub text: String,
+    pub reason: Reason,
+}
+
+/// Drop hallucinated segments and trim hallucinated sentences out of the rest. Every
+/// rejection and trim is logged with the numbers behind it.
+pub fn filter(transcript: Transcript, config: &FilterConfig) -> (Transcript, Vec<Rejection>) {
+    let mut kept = Vec::with_capacity(transcript.segments.len());
+    let mut rejections = Vec::new();
+    for (i, segment) in transcript.segments.into_iter().enumerate() {
+        match check(&segment, config) {
+            Ok(text) => {
+                if text != segment.text {
+                    tracing::info!("Hallucination filter trimmed segment {}: {:?} → {:?}", i, segment.text.trim(), text.trim());
+                }
+                kept.push(Segment { text, ..segment });
+            }
+            Err(reason) => {
+                tracing::info!("Hallucination filter dropped segment {} ({:?}): {:?}", i, reason, segment.text.trim());
+                rejections.push(Rejection { segment: i, text: segment.text, reason });
+            }
+        }
+    }
+    (Transcript::from_segments(kept, transcript.language), rejections)
+}
+
+/// The segment's text to keep, possibly trimmed, or why it was dropped.
+fn check(segment: &Segment, config: &FilterConfig) -> Result<String, Reason> {
+    let text = segment.text.trim();
+    if text.is_empty() {
+        return Ok(String::new());
+    }
+    if is_noise_marker(text) {
+        return Err(Reason::NoiseMarker);
+    }
+    let avg_logprob = segment.avg_logprob().unwrap_or(0.0);
+    let unsure = avg_logprob < config.logprob_threshold;
+    if let Some(prob) = segment.no_speech_prob {
+        if prob >= config.no_speech_threshold && unsure {
+            return Err(Reason::NoSpeech { prob, avg_logprob });
+        }
+    }
+
+    let sentences = sentences(text);
+    let phrase = sentences.iter().find_map(|s| known_phrase(&normalize(s)));
+    if phrase.is_some() && sentences.iter().all(|s| is_only_phrases(&normalize(s))) {
+        return Err(Reason::KnownPhrase(phrase.unwrap_or_default().to_string()));
+    }
+    // Inside real dictation a phrase is only taken for a hallucination in a doubtful segment
+    let doubtful = unsure || segment.no_speech_prob.is_some_and(|prob| prob >= config.no_speech_threshold);
+    let kept: Vec<&str> = sentences.iter()
+        .filter(|s| !doubtful || known_phrase(&normalize(s)).is_none())
+        .copied()
+        .collect();
+    if kept.is_empty() {
+        return Err(Reason::KnownPhrase(phrase.unwrap_or_default().to_string()));
+    }
+    let mut out = kept.join(" ");
+
+    if SUSPECT_PHRASES.contains(&normalize(&out).as_str()) && avg_logprob < config.logprob_threshold {
+        return Err(Reason::SuspectPhrase { avg_logprob });
+    }
+
+    let ratio = compression_ratio(&out);
+    if ratio > config.compression_ratio_threshold {
+        let collapsed = collapse_repeats(&out);
+        if compression_ratio(&collapsed) > config.compression_ratio_threshold {
+            return Err(Reason::Repetitive { ratio });
+        }
+        out = collapsed;
+    }
+
+    // Keep Whisper's leading space so joined segments stay separate words
+    if out == text { Ok(segment.text.clone()) } else { Ok(format!(" {}", out)) }
+}
+
+fn is_noise_marker(text: &str) -> bool {
+    let wrapped = |open: char, close: char| text.starts_with(open) && text.ends_with(close);
+    wrapped('[', ']') || wrapped('(', ')') || wrapped('*', '*') || text.chars().all(|c| c == '♪' || c.is_whitespace())
+}
+
+/// Lowercase words with punctuation removed, single-spaced.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() || c == '\'' { c.to_lowercase().next().unwrap_or(c) } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn contains_words(haystack: &str, phrase: &str) -> bool {
+    format!(" {} ", haystack).contains(&format!(" {} ", phrase))
+}
+
+/// The first known phrase in a normalized sentence.
+fn known_phrase(norm: &str) -> Option<&'static str> {
+    KNOWN_PHRASES.iter().find(|p| contains_words(norm, p)).copied()
+}
+
+/// A normalized sentence holding known phrases and next to nothing else.
+fn is_only_phrases(norm: &str) -> bool {
+    if known_phrase(norm).is_none() {
+        return false;
+    }
+    let mut rest = format!(" {} ", norm);
+    for phrase in KNOWN_PHRASES {
+        rest = rest.replace(&format!(" {} ", phrase), " ");
+    }
+    rest.split_whitespace().count() <= PHRASE_EXTRA_WORDS
+}
+
+/// Sentences ending in . ! or ? (the last may have no terminator), trimmed.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at_break = chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        if matches!(c, '.' | '!' | '?') && at_break {
+            let end = i + c.len_utf8();
+            out.push(text[start..end].trim());
+            start = end;
+        }
+    }
+    out.push(text[start..].trim());
+    out.retain(|s| !s.is_empty());
+    out
+}
+
+/// zlib compression ratio, as Whisper uses to spot decoding loops.
+pub fn compression_ratio(text: &str) -> f32 {
+    if text.is_empty() {
+        return 0.0;
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder.write_all(text.as_bytes()).and_then(|_| encoder.finish());
+    match compressed {
+        Ok(bytes) if !bytes.is_empty() => text.len() as f32 / bytes.len() as f32,
+        _ => 0.0,
+    }
+}
+
+/// Collapse word runs repeated `MIN_REPEATS` or more times in a row down to one.
+fn collapse_repeats(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let norm: Vec<String> = words.iter().map(|w| normalize(w)).collect();
+    let mut out: Vec<&str> = Vec::with_capacity(words.len());
+    let mut i = 0;
+    'outer: while i < words.len() {
+        for n in 1..=MAX_LOOP_WORDS.min((words.len() - i) / MIN_REPEATS) {
+            let mut repeats = 1;
+            while i + (repeats + 1) * n <= words.len()
+                && norm[i + repeats * n..i + (repeats + 1) * n] == norm[i..i + n]
+            {
+                repeats += 1;
+            }
+            if repeats >= MIN_REPEATS {
+                // Keep the last copy, which carries the run's final punctuation
+                out.extend(&words[i + (repeats - 1) * n..i + repeats * n]);
+                i += repeats * n;
+                continue 'outer;
+            }
+        }
+        out.push(words[i]);
+        i += 1;
+    }
+    out.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asr::transcript::Token;
+
+    fn segment(text: &str) -> Segment {
+        Segment { start_ms: 0, end_ms: 1000, text: text.into(), tokens: vec![], no_speech_prob: None }
+    }
+
+    fn with_confidence(text: &str, p: f32) -> Segment {
+        Segment { tokens: vec![Token { id: 1, text: text.into(), p }], ..segment(text) }
+    }
+
+    fn run(segments: Vec<Segment>) -> (Transcript, Vec<Rejection>) {
+        filter(Transcript::from_segments(segments, "en".into()), &FilterConfig::default())
+    }
+
+    #[test]
+    fn keeps_normal_dictation() {
+        let (t, rejected) = run(vec![segment(" Deploy the new version."), segment(" Then tell the team.")]);
+        assert_eq!(t.text, "Deploy the new version. Then tell the team.");
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn drops_noise_markers() {
+        let (t, rejected) = run(vec![segment(" [BLANK_AUDIO]"), segment(" (keyboard clicking)"), segment(" ♪ ♪")]);
+        assert_eq!(t.text, "");
+        assert_eq!(rejected.len(), 3);
+        assert!(rejected.iter().all(|r| r.reason == Reason::NoiseMarker));
+    }
+
+    #[test]
+    fn drops_and_trims_known_phrases() {
+        let (t, rejected) = run(vec![
+            with_confidence(" Let's ship it. Thanks for watching!", 0.1),
+            segment(" Thank you for watching."),
+            segment(" Subtitles by the Amara.org community"),
+        ]);
+        assert_eq!(t.text, "Let's ship it.");
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0], Rejection {
+            segment: 1,
+            text: " Thank you for watching.".into(),
+            reason: Reason::KnownPhrase("thank you for watching".into()),
+        });
+    }
+
+    #[test]
+    fn known_phrases_in_confident_dictation_are_kept() {
+        let (t, rejected) = run(vec![
+            with_confidence(" The spec was translated by Ana. Please subscribe to the newsletter.", 0.9),
+            segment(" Let's ship it. Thanks for watching!"),
+        ]);
+        assert_eq!(t.text, "The spec was translated by Ana. Please subscribe to the newsletter. Let's ship it. Thanks for watching!");
+        assert!(rejected.is_empty());
+
+        let mut silent = segment(" The spec was translated by Ana.");
+        silent.no_speech_prob = Some(0.9);
+        let (t, rejected) = run(vec![silent]);
+        assert_eq!(t.text, "");
+        assert_eq!(rejected[0].reason, Reason::KnownPhrase("translated by".into()));
+    }
+
+    #[test]
+    fn suspect_phrases_need_low_confidence() {
+        let (t, rejected) = run(vec![with_confidence(" Thank you.", 0.1)]);
+        assert_eq!(t.text, "");
+        assert!(matches!(rejected[0].reason, Reason::SuspectPhrase { .. }));
+
+        let (t, rejected) = run(vec![with_confidence(" Thank you.", 0.9)]);
+        assert_eq!(t.text, "Thank you.");
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn no_speech_needs_low_confidence() {
+        let mut unsure = with_confidence(" the", 0.2);
+        unsure.no_speech_prob = Some(0.8);
+        let mut sure = with_confidence(" Yes, do it.", 0.95);
+        sure.no_speech_prob = Some(0.8);
+        let (t, rejected) = run(vec![unsure, sure]);
+        assert_eq!(t.text, "Yes, do it.");
+        assert!(matches!(rejected[0].reason, Reason::NoSpeech { .. }));
+    }
+
+    #[test]
+    fn collapses_loops() {
+        let looped = format!(" Okay.{}", " I think so.".repeat(10));
+        let (t, rejected) = run(vec![segment(&looped)]);
+        assert_eq!(t.text, "Okay. I think so.");
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn drops_incompressible_loops() {
+        let (t, rejected) = run(vec![segment(&format!(" {}", "a".repeat(80)))]);
+        assert_eq!(t.text, "");
+        assert!(matches!(rejected[0].reason, Reason::Repetitive { .. }));
+    }
+
+    #[test]
+    fn compression_ratio_of_repetition() {
+        assert!(compression_ratio(&"the cat sat. ".repeat(20)) > 2.4);
+        assert!(compression_ratio("Deploy the new version to staging first.") < 2.4);
+        assert_eq!(compression_ratio(""), 0.0);
+    }
+
+    #[test]
+    fn config_from_settings() {
+        let conn = crate::db::schema::init_db(std::path::Path::new(":memory:")).unwrap();
+        assert_eq!(load(&conn), FilterConfig::default());
+        settings::set(&conn, SETTING_KEY, r#"{"logprob_threshold": -0.5}"#).unwrap();
+        let config = load(&conn);
+        assert_eq!(config.logprob_threshold, -0.5);
+        assert_eq!(config.compression_ratio_threshold, 2.4);
+    }
+}
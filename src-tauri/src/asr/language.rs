@@ -0,0 +1,144 @@
+//! Transcription language setting: one fixed language, auto-detect, or detect within a set.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::db::settings;
+
+const SETTING_KEY: &str = "asr_language";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum LanguageMode {
+    /// Always transcribe as this Whisper language code ("en", "hi", …).
+    Fixed { language: String },
+    /// Let Whisper detect the language of every segment.
+    Auto,
+    /// Detect per segment, but only ever pick one of these codes.
+    Allowed { languages: Vec<String> },
+}
+
+impl Default for LanguageMode {
+    fn default() -> Self {
+        LanguageMode::Fixed { language: "en".into() }
+    }
+}
+
+impl LanguageMode {
+    /// Every language code the mode names must be one Whisper knows.
+    pub fn validate(&self, is_known: impl Fn(&str) -> bool) -> Result<()> {
+        let codes: &[String] = match self {
+            LanguageMode::Fixed { language } => std::slice::from_ref(language),
+            LanguageMode::Auto => &[],
+            LanguageMode::Allowed { languages } => {
+                if languages.is_empty() {
+                    anyhow::bail!("Allowed languages must not be empty");
+                }
+                languages
+            }
+        };
+        if let Some(bad) = codes.iter().find(|c| !is_known(c)) {
+            anyhow::bail!("Unknown language code: {}", bad);
+        }
+        Ok(())
+    }
+}
+
+/// The saved language mode, or the default (English) if unset or unreadable.
+pub fn load(conn: &Connection) -> LanguageMode {
+    settings::get(conn, SETTING_KEY).ok().flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(conn: &Connection, mode: &LanguageMode) -> Result<()> {
+    settings::set(conn, SETTING_KEY, &serde_json::to_string(mode)?)
+}
+
+/// The allowed language Whisper thinks most likely, given its per-language probabilities
+/// (indexed by Whisper language id).
+pub fn pick_allowed<'a>(probs: &[f32], allowed: &'a [String], lang_id: impl Fn(&str) -> Option<i32>) -> Option<&'a str> {
+    allowed.iter()
+        .filter_map(|code| {
+            let p = probs.get(usize::try_from(lang_id(code)?).ok()?)?;
+            Some((code.as_str(), *p))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(code, _)| code)
+}
+
+/// English name of a Whisper language code, for prompts ("hi" → "Hindi").
+pub fn display_name(code: &str) -> String {
+    let full = whisper_rs::get_lang_id(code).and_then(whisper_rs::get_lang_str_full).unwrap_or(code);
+    let mut chars = full.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn test_db() -> Connection {
+        schema::init_db(std::path::Path::new(":memory:")).unwrap()
+    }
+
+    fn ids(code: &str) -> Option<i32> {
+        ["en", "zh", "de", "es", "hi"].iter().position(|c| *c == code).map(|i| i as i32)
+    }
+
+    #[test]
+    fn defaults_to_english() {
+        let conn = test_db();
+        assert_eq!(load(&conn), LanguageMode::Fixed { language: "en".into() });
+    }
+
+    #[test]
+    fn save_and_load_roundtrip() {
+        let conn = test_db();
+        let mode = LanguageMode::Allowed { languages: vec!["en".into(), "hi".into()] };
+        save(&conn, &mode).unwrap();
+        assert_eq!(load(&conn), mode);
+        save(&conn, &LanguageMode::Auto).unwrap();
+        assert_eq!(load(&conn), LanguageMode::Auto);
+    }
+
+    #[test]
+    fn serialized_shape() {
+        let json = serde_json::to_string(&LanguageMode::Fixed { language: "de".into() }).unwrap();
+        assert_eq!(json, r#"{"mode":"fixed","language":"de"}"#);
+        let mode: LanguageMode = serde_json::from_str(r#"{"mode":"auto"}"#).unwrap();
+        assert_eq!(mode, LanguageMode::Auto);
+    }
+
+    #[test]
+    fn unreadable_setting_falls_back() {
+        let conn = test_db();
+        settings::set(&conn, SETTING_KEY, "not json").unwrap();
+        assert_eq!(load(&conn), LanguageMode::default());
+    }
+
+    #[test]
+    fn validate_codes() {
+        let known = |c: &str| ids(c).is_some();
+        assert!(LanguageMode::Auto.validate(known).is_ok());
+        assert!(LanguageMode::Fixed { language: "hi".into() }.validate(known).is_ok());
+        assert!(LanguageMode::Fixed { language: "xx".into() }.validate(known).is_err());
+        assert!(LanguageMode::Allowed { languages: vec![] }.validate(known).is_err());
+        assert!(LanguageMode::Allowed { languages: vec!["en".into(), "klingon".into()] }.validate(known).is_err());
+    }
+
+    #[test]
+    fn picks_most_likely_allowed_language() {
+        // German is the most likely overall, but only English and Hindi are allowed
+        let probs = [0.2, 0.05, 0.5, 0.05, 0.2 + 0.01];
+        let allowed = vec!["en".to_string(), "hi".to_string()];
+        assert_eq!(pick_allowed(&probs, &allowed, ids), Some("hi"));
+        assert_eq!(pick_allowed(&probs, &["xx".to_string()], ids), None);
+        assert_eq!(pick_allowed(&[], &allowed, ids), None);
+    }
+}
@@ -1 +1,10 @@
+pub mod backend;
+pub mod decoding;
 pub mod engine;
+pub mod hallucination;
+pub mod language;
+pub mod onnx;
+pub mod pool;
+pub mod prompt;
+pub mod streaming;
+pub mod transcript;
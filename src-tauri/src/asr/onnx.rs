@@ -0,0 +1,274 @@
+//! ONNX Runtime speech recognition for Moonshine exports: `preprocess`, `encode`,
+//! `uncached_decode` and `cached_decode` graphs plus the model's `tokenizer.json`.
+//! Several times cheaper than Whisper on CPU, English only.
+
+use anyhow::{Context, Result};
+use ort::session::{Session, SessionInputValue};
+use ort::tensor::TensorElementType;
+use ort::value::{DynValue, Value, ValueType};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::backend::{self, AsrBackend, TranscribeOptions};
+use super::prompt::AsrContext;
+use super::transcript::{Segment, Token, Transcript};
+
+const GRAPHS: [&str; 4] = ["preprocess.onnx", "encode.onnx", "uncached_decode.onnx", "cached_decode.onnx"];
+const TOKENIZER: &str = "tokenizer.json";
+const BOS: i64 = 1;
+const EOS: i64 = 2;
+/// Moonshine's own generation cap; nobody speaks faster than this.
+const MAX_TOKENS_PER_SECOND: f32 = 6.0;
+
+pub struct OnnxAsr {
+    name: String,
+    // `Session::run` needs `&mut`; one decode at a time
+    sessions: Mutex<Sessions>,
+    tokenizer: Tokenizer,
+}
+
+struct Sessions {
+    preprocess: Session,
+    encode: Session,
+    uncached_decode: Session,
+    cached_decode: Session,
+}
+
+impl OnnxAsr {
+    pub fn new(model_dir: &Path) -> Result<Self> {
+        let missing: Vec<&str> = GRAPHS.iter().chain([&TOKENIZER])
+            .filter(|f| !model_dir.join(f).exists())
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!("{} is not a Moonshine ONNX export (missing {})", model_dir.display(), missing.join(", "));
+        }
+        let load = |file: &str| -> Result<Session> {
+            Ok(Session::builder()?.commit_from_file(model_dir.join(file))?)
+        };
+        let sessions = Sessions {
+            preprocess: load(GRAPHS[0])?,
+            encode: load(GRAPHS[1])?,
+            uncached_decode: load(GRAPHS[2])?,
+            cached_decode: load(GRAPHS[3])?,
+        };
+        let tokenizer = Tokenizer::from_json(&std::fs::read_to_string(model_dir.join(TOKENIZER))?)
+            .context("Failed to read tokenizer.json")?;
+        let name = model_dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "onnx".into());
+        Ok(Self { name, sessions: Mutex::new(sessions), tokenizer })
+    }
+
+    /// Greedy decode; token ids with the probability each was picked with.
+    fn decode(&self, audio: &[f32]) -> Result<Vec<(i64, f32)>> {
+        let mut s = self.sessions.lock().map_err(|_| anyhow::anyhow!("ONNX ASR session poisoned"))?;
+        let max_tokens = ((audio.len() as f32 / 16000.0) * MAX_TOKENS_PER_SECOND).ceil() as usize;
+
+        let samples = Value::from_array(([1i64, audio.len() as i64], audio.to_vec()))?;
+        let features = first_output(&mut s.preprocess, &[samples.into()])?;
+        let frames = match features.dtype() {
+            ValueType::Tensor { shape, .. } if shape.len() >= 2 => shape[shape.len() - 2],
+            other => anyhow::bail!("Unexpected preprocess output {:?}", other),
+        };
+        let frames = int_input(&s.encode, 1, vec![1], vec![frames])?;
+        let context = first_output(&mut s.encode, &[features.into(), frames.into()])?;
+
+        let mut tokens = Vec::new();
+        let mut seq_len = 1;
+        let mut inputs: Vec<SessionInputValue> = vec![
+            int_input(&s.uncached_decode, 0, vec![1, 1], vec![BOS])?.into(),
+            (&context).into(),
+            int_input(&s.uncached_decode, 2, vec![1], vec![seq_len])?.into(),
+        ];
+        let mut step = run(&mut s.uncached_decode, &inputs)?;
+        while tokens.len() < max_tokens {
+            let (id, p) = argmax_softmax(&step.0)?;
+            if id == EOS {
+                break;
+            }
+            tokens.push((id, p));
+            seq_len += 1;
+            inputs = vec![
+                int_input(&s.cached_decode, 0, vec![1, 1], vec![id])?.into(),
+                (&context).into(),
+                int_input(&s.cached_decode, 2, vec![1], vec![seq_len])?.into(),
+            ];
+            inputs.extend(step.1.into_iter().map(SessionInputValue::from));
+            step = run(&mut s.cached_decode, &inputs)?;
+        }
+        Ok(tokens)
+    }
+}
+
+impl AsrBackend for OnnxAsr {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn transcribe_with(&self, audio: &[f32], context: Option<&AsrContext>, options: &TranscribeOptions) -> Result<Transcript> {
+        if context.is_some() {
+            tracing::debug!("{} has no prompt input; ignoring ASR context", self.name);
+        }
+        backend::english_only(&self.name, &options.language)?;
+        let decoded = self.decode(audio)?;
+        let ids: Vec<i64> = decoded.iter().map(|(id, _)| *id).collect();
+        let segment = Segment {
+            start_ms: 0,
+            end_ms: audio.len() as i64 * 1000 / 16000,
+            text: self.tokenizer.decode(&ids),
+            tokens: decoded.iter()
+                .filter(|(id, _)| !self.tokenizer.special.contains(id))
+                .map(|&(id, p)| Token { id: id as i32, text: self.tokenizer.decode(&[id]), p })
+                .collect(),
+            no_speech_prob: None,
+        };
+        Ok(Transcript::from_segments(vec![segment], "en".into()))
+    }
+}
+
+/// Run a graph and take its first output.
+fn first_output(session: &mut Session, inputs: &[SessionInputValue]) -> Result<DynValue> {
+    let name = session.outputs()[0].name().to_string();
+    let mut outputs = session.run(inputs)?;
+    outputs.remove(&name).ok_or_else(|| anyhow::anyhow!("Missing output {}", name))
+}
+
+/// Run a decoder step: logits, then the key/value cache to feed the next step.
+fn run(session: &mut Session, inputs: &[SessionInputValue]) -> Result<(Vec<f32>, Vec<DynValue>)> {
+    let names: Vec<String> = session.outputs().iter().map(|o| o.name().to_string()).collect();
+    let mut outputs = session.run(inputs)?;
+    let (shape, logits) = outputs[names[0].as_str()].try_extract_tensor::<f32>()?;
+    // Logits for the last position only
+    let vocab = *shape.last().unwrap_or(&0) as usize;
+    let logits = logits[logits.len().saturating_sub(vocab)..].to_vec();
+    let cache = names[1..].iter().filter_map(|n| outputs.remove(n)).collect();
+    Ok((logits, cache))
+}
+
+/// An integer tensor in whichever width the graph declares for input `index`.
+fn int_input(session: &Session, index: usize, shape: Vec<i64>, data: Vec<i64>) -> Result<DynValue> {
+    let declared = session.inputs().get(index).map(|i| i.dtype());
+    Ok(match declared {
+        Some(ValueType::Tensor { ty: TensorElementType::Int32, .. }) => {
+            Value::from_array((shape, data.into_iter().map(|v| v as i32).collect::<Vec<_>>()))?.into_dyn()
+        }
+        _ => Value::from_array((shape, data))?.into_dyn(),
+    })
+}
+
+/// Most likely token and its softmax probability.
+fn argmax_softmax(logits: &[f32]) -> Result<(i64, f32)> {
+    let (id, max) = logits.iter().copied().enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .ok_or_else(|| anyhow::anyhow!("Decoder returned no logits"))?;
+    let sum: f32 = logits.iter().map(|l| (l - max).exp()).sum();
+    Ok((id as i64, 1.0 / sum))
+}
+
+/// Decoding half of a SentencePiece-style BPE tokenizer, from Hugging Face `tokenizer.json`.
+pub(crate) struct Tokenizer {
+    pieces: HashMap<i64, String>,
+    special: HashSet<i64>,
+}
+
+impl Tokenizer {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let doc: serde_json::Value = serde_json::from_str(json)?;
+        let vocab = doc["model"]["vocab"].as_object()
+            .ok_or_else(|| anyhow::anyhow!("tokenizer.json has no model.vocab"))?;
+        let mut pieces: HashMap<i64, String> = vocab.iter()
+            .filter_map(|(piece, id)| Some((id.as_i64()?, piece.clone())))
+            .collect();
+        let mut special = HashSet::new();
+        for added in doc["added_tokens"].as_array().into_iter().flatten() {
+            let (Some(id), Some(content)) = (added["id"].as_i64(), added["content"].as_str()) else { continue };
+            pieces.insert(id, content.to_string());
+            if added["special"].as_bool().unwrap_or(false) {
+                special.insert(id);
+            }
+        }
+        Ok(Self { pieces, special })
+    }
+
+    pub fn decode(&self, ids: &[i64]) -> String {
+        let mut bytes = Vec::new();
+        for id in ids {
+            if self.special.contains(id) {
+                continue;
+            }
+            let Some(piece) = self.pieces.get(id) else { continue };
+            // Byte fallback pieces spell raw UTF-8 bytes: "<0x0A>"
+            let byte = piece.strip_prefix("<0x").and_then(|h| h.strip_suffix('>'))
+                .and_then(|h| u8::from_str_radix(h, 16).ok());
+            match byte {
+                Some(b) => bytes.push(b),
+                None => bytes.extend(piece.replace('▁', " ").as_bytes()),
+            }
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKENIZER_JSON: &str = r#"{
+        "added_tokens": [
+            {"id": 0, "content": "<unk>", "special": true},
+            {"id": 1, "content": "<s>", "special": true},
+            {"id": 2, "content": "</s>", "special": true}
+        ],
+        "model": {"type": "BPE", "vocab": {
+            "<unk>": 0, "<s>": 1, "</s>": 2, "▁Deploy": 3, "▁the": 4, "▁new": 5, "▁ver": 6, "sion": 7,
+            ".": 8, "<0xC3>": 9, "<0xA9>": 10, "▁caf": 11
+        }}
+    }"#;
+
+    #[test]
+    fn decodes_pieces() {
+        let tok = Tokenizer::from_json(TOKENIZER_JSON).unwrap();
+        assert_eq!(tok.decode(&[1, 3, 4, 5, 6, 7, 8, 2]), " Deploy the new version.");
+        assert_eq!(tok.decode(&[6]), " ver");
+    }
+
+    #[test]
+    fn decodes_byte_fallback() {
+        let tok = Tokenizer::from_json(TOKENIZER_JSON).unwrap();
+        assert_eq!(tok.decode(&[11, 9, 10]), " café");
+        // A lone continuation byte doesn't panic
+        assert_eq!(tok.decode(&[10]), "\u{FFFD}");
+    }
+
+    #[test]
+    fn rejects_tokenizer_without_vocab() {
+        assert!(Tokenizer::from_json(r#"{"model": {}}"#).is_err());
+    }
+
+    #[test]
+    fn softmax_of_argmax() {
+        let (id, p) = argmax_softmax(&[0.0, 2.0, 0.0]).unwrap();
+        assert_eq!(id, 1);
+        let expected = 2f32.exp() / (2.0 + 2f32.exp());
+        assert!((p - expected).abs() < 1e-6);
+        assert!(argmax_softmax(&[]).is_err());
+    }
+
+    #[test]
+    fn rejects_incomplete_export() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("encode.onnx"), b"").unwrap();
+        let err = OnnxAsr::new(dir.path()).err().unwrap().to_string();
+        assert!(err.ends_with("(missing preprocess.onnx, uncached_decode.onnx, cached_decode.onnx, tokenizer.json)"), "{}", err);
+    }
+
+    #[test]
+    #[ignore] // requires Moonshine ONNX model in models/onnx/moonshine-base
+    fn transcribes_silence() {
+        let dir = crate::config::AppConfig::default().models_dir.join("onnx/moonshine-base");
+        let asr = OnnxAsr::new(&dir).unwrap();
+        let t = asr.transcribe_with(&vec![0.0f32; 16000], None, &TranscribeOptions::default()).unwrap();
+        assert_eq!(t.language, "en");
+        assert!(t.text.len() < 100);
+    }
+}
@@ -0,0 +1,128 @@
+//! Reusable decoder states. Creating one allocates the KV caches, so keep a few idle ones
+//! around instead of building a fresh state per segment.
+
+use anyhow::Result;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+pub struct Pool<T> {
+    idle: Mutex<Vec<T>>,
+    max_idle: usize,
+}
+
+impl<T> Pool<T> {
+    /// A pool that keeps at most `max_idle` states once they're returned.
+    pub fn new(max_idle: usize) -> Self {
+        Self { idle: Mutex::new(Vec::new()), max_idle }
+    }
+
+    /// An idle state, or a new one from `create` if all are in use. It goes back to the
+    /// pool when the guard drops.
+    pub fn take(&self, create: impl FnOnce() -> Result<T>) -> Result<Pooled<'_, T>> {
+        let idle = self.idle.lock().unwrap().pop();
+        let item = match idle {
+            Some(item) => item,
+            None => create()?,
+        };
+        Ok(Pooled { pool: self, item: Some(item) })
+    }
+
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    fn put(&self, item: T) {
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < self.max_idle {
+            idle.push(item);
+        }
+    }
+}
+
+pub struct Pooled<'a, T> {
+    pool: &'a Pool<T>,
+    item: Option<T>,
+}
+
+impl<T> Pooled<'_, T> {
+    /// Drop the state instead of returning it, e.g. after a failure left it unusable.
+    pub fn discard(mut self) {
+        self.item = None;
+    }
+}
+
+impl<T> Deref for Pooled<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.item.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for Pooled<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.item.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for Pooled<'_, T> {
+    fn drop(&mut self) {
+        if let Some(item) = self.item.take() {
+            self.pool.put(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn reuses_returned_state() {
+        let pool = Pool::new(2);
+        let created = AtomicUsize::new(0);
+        let create = || { created.fetch_add(1, Ordering::SeqCst); Ok(vec![0u8; 4]) };
+        {
+            let mut state = pool.take(create).unwrap();
+            state[0] = 7;
+        }
+        assert_eq!(pool.idle(), 1);
+        let state = pool.take(create).unwrap();
+        assert_eq!(state[0], 7);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn concurrent_callers_get_separate_states() {
+        let pool = Arc::new(Pool::new(2));
+        let created = Arc::new(AtomicUsize::new(0));
+        let a = pool.take(|| { created.fetch_add(1, Ordering::SeqCst); Ok(1) }).unwrap();
+        let b = pool.take(|| { created.fetch_add(1, Ordering::SeqCst); Ok(2) }).unwrap();
+        assert_ne!(*a, *b);
+        drop((a, b));
+        assert_eq!(pool.idle(), 2);
+
+        let handles: Vec<_> = (0..8).map(|_| {
+            let pool = pool.clone();
+            let created = created.clone();
+            std::thread::spawn(move || {
+                let mut state = pool.take(|| { created.fetch_add(1, Ordering::SeqCst); Ok(0) }).unwrap();
+                *state += 1;
+            })
+        }).collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert!(pool.idle() <= 2);
+    }
+
+    #[test]
+    fn discard_and_failed_create_leave_pool_empty() {
+        let pool: Pool<u32> = Pool::new(2);
+        assert!(pool.take(|| anyhow::bail!("no memory")).is_err());
+        pool.take(|| Ok(1)).unwrap().discard();
+        assert_eq!(pool.idle(), 0);
+    }
+}
@@ -0,0 +1,134 @@
+/// What Whisper is told before the audio: spellings to prefer and the text it continues.
+#[derive(Debug, Default, Clone)]
+pub struct AsrContext {
+    /// Terms to spell correctly, most important first (dictionary written forms, snippet triggers).
+    pub vocabulary: Vec<String>,
+    pub window_title: String,
+    /// Text of the previous segment, so sentences split across segments stay consistent.
+    pub previous_text: String,
+}
+
+impl AsrContext {
+    /// Build the initial prompt within `budget` tokens. Whisper conditions most on the end of
+    /// the prompt, so the previous text goes last; it and the title get a bounded share and the
+    /// vocabulary fills the rest, dropping the least important terms first.
+    pub fn initial_prompt(&self, budget: usize, count_tokens: impl Fn(&str) -> usize) -> String {
+        let clean = |s: &str| s.replace('\0', "").split_whitespace().collect::<Vec<_>>().join(" ");
+
+        let previous = tail_within(&clean(&self.previous_text), budget / 3, &count_tokens);
+        let mut used = count_tokens(&previous);
+
+        let title = clean(&self.window_title);
+        let title = if !title.is_empty() && used + count_tokens(&title) <= budget / 2 {
+            used += count_tokens(&title);
+            title
+        } else {
+            String::new()
+        };
+
+        let mut terms: Vec<String> = Vec::new();
+        for term in self.vocabulary.iter().map(|t| clean(t)) {
+            if term.is_empty() || terms.contains(&term) {
+                continue;
+            }
+            // +1 for the separating comma
+            let cost = count_tokens(&term) + 1;
+            if used + cost > budget {
+                break;
+            }
+            used += cost;
+            terms.push(term);
+        }
+
+        let mut parts = Vec::new();
+        if !terms.is_empty() {
+            parts.push(format!("{}.", terms.join(", ")));
+        }
+        if !title.is_empty() {
+            parts.push(format!("{}.", title.trim_end_matches('.')));
+        }
+        if !previous.is_empty() {
+            parts.push(previous);
+        }
+        parts.join(" ")
+    }
+}
+
+/// Longest whole-word suffix of `text` within `budget` tokens. Each word is counted once,
+/// with its leading space: BPE doesn't merge across spaces, so the counts add up.
+fn tail_within(text: &str, budget: usize, count_tokens: &impl Fn(&str) -> usize) -> String {
+    let words: Vec<&str> = text.split(' ').filter(|w| !w.is_empty()).collect();
+    let mut start = words.len();
+    let mut used = 0;
+    while start > 0 {
+        let cost = count_tokens(&format!(" {}", words[start - 1]));
+        if used + cost > budget {
+            break;
+        }
+        used += cost;
+        start -= 1;
+    }
+    words[start..].join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One token per word is close enough to Whisper's BPE for budgeting tests.
+    fn words(s: &str) -> usize {
+        s.split_whitespace().count()
+    }
+
+    #[test]
+    fn empty_context_is_empty_prompt() {
+        assert_eq!(AsrContext::default().initial_prompt(224, words), "");
+    }
+
+    #[test]
+    fn orders_vocabulary_title_then_previous() {
+        let ctx = AsrContext {
+            vocabulary: vec!["Kubernetes".into(), "gRPC".into()],
+            window_title: "#infra - Slack".into(),
+            previous_text: "We moved the cluster.".into(),
+        };
+        assert_eq!(ctx.initial_prompt(224, words), "Kubernetes, gRPC. #infra - Slack. We moved the cluster.");
+    }
+
+    #[test]
+    fn trims_to_budget() {
+        let ctx = AsrContext {
+            vocabulary: (0..100).map(|i| format!("term{}", i)).collect(),
+            window_title: "a very long window title that keeps going".into(),
+            previous_text: "one two three four five six seven eight nine ten eleven twelve".into(),
+        };
+        let prompt = ctx.initial_prompt(30, words);
+        assert!(words(&prompt) <= 30 + 2, "{}", prompt);
+        // Previous text keeps its end, capped at a third of the budget
+        assert!(prompt.ends_with("three four five six seven eight nine ten eleven twelve"));
+        assert!(!prompt.contains("two three"));
+        // Most important terms survive
+        assert!(prompt.starts_with("term0, term1"));
+        assert!(!prompt.contains("term99"));
+    }
+
+    #[test]
+    fn drops_duplicates_and_nul_bytes() {
+        let ctx = AsrContext {
+            vocabulary: vec!["gRPC".into(), "gRPC".into(), "  ".into(), "Ru\0st".into()],
+            ..Default::default()
+        };
+        assert_eq!(ctx.initial_prompt(224, words), "gRPC, Rust.");
+    }
+
+    #[test]
+    fn tail_counts_each_word_once() {
+        let text: Vec<String> = (0..200).map(|i| format!("w{}", i)).collect();
+        let calls = std::cell::Cell::new(0);
+        let count = |s: &str| { calls.set(calls.get() + 1); words(s) };
+        let tail = tail_within(&text.join(" "), 50, &count);
+        assert_eq!(words(&tail), 50);
+        assert!(tail.starts_with("w150 ") && tail.ends_with(" w199"));
+        assert_eq!(calls.get(), 51);
+    }
+}
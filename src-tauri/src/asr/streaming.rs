@@ -0,0 +1,302 @@
+//! Live captions: re-transcribe the growing utterance every so often and commit the words
+//! two consecutive hypotheses agree on (local agreement), sliding the audio window forward
+//! past committed segments so each pass stays short.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::backend::{AsrBackend, TranscribeOptions};
+use super::hallucination::{self, FilterConfig};
+use super::prompt::AsrContext;
+use super::transcript::Segment;
+
+const SAMPLE_RATE: usize = 16000;
+/// Longest run of committed words matched against the start of a hypothesis when the
+/// window slid without a clean segment boundary.
+const MAX_OVERLAP_WORDS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct StreamConfig {
+    /// How much new audio triggers another pass.
+    pub interval_ms: usize,
+    /// Audio kept per pass; older audio is dropped once its words are committed.
+    pub window_ms: usize,
+    /// Don't bother before there's this much speech.
+    pub min_audio_ms: usize,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self { interval_ms: 500, window_ms: 15_000, min_audio_ms: 500 }
+    }
+}
+
+/// What the overlay shows: words that won't change, then the latest guess at the rest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Partial {
+    pub committed: String,
+    pub tentative: String,
+}
+
+fn same_word(a: &str, b: &str) -> bool {
+    let norm = |w: &str| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+    norm(a) == norm(b)
+}
+
+/// LocalAgreement-2: a word is committed once two consecutive hypotheses agree on it and
+/// everything before it.
+#[derive(Debug, Default)]
+pub struct LocalAgreement {
+    committed: Vec<String>,
+    /// How many of the committed words are spoken in the current audio window.
+    in_window: usize,
+    /// Uncommitted tail of the last hypothesis.
+    previous: Vec<String>,
+}
+
+impl LocalAgreement {
+    /// Feed the hypothesis for the current window; returns the newly committed words.
+    pub fn insert(&mut self, hypothesis: &[String]) -> &[String] {
+        let mut fresh = &hypothesis[self.in_window.min(hypothesis.len())..];
+        if self.in_window == 0 {
+            // Those words are still in the window's audio
+            self.in_window = self.overlap(fresh);
+            fresh = &fresh[self.in_window..];
+        }
+        let agreed = self.previous.iter().zip(fresh)
+            .take_while(|(a, b)| same_word(a, b))
+            .count();
+        let start = self.committed.len();
+        self.committed.extend_from_slice(&fresh[..agreed]);
+        self.in_window += agreed;
+        self.previous = fresh[agreed..].to_vec();
+        &self.committed[start..]
+    }
+
+    /// Words at the start of `fresh` that repeat the end of what's already committed.
+    fn overlap(&self, fresh: &[String]) -> usize {
+        (1..=MAX_OVERLAP_WORDS.min(self.committed.len()).min(fresh.len())).rev()
+            .find(|&n| {
+                self.committed[self.committed.len() - n..].iter().zip(&fresh[..n])
+                    .all(|(a, b)| same_word(a, b))
+            })
+            .unwrap_or(0)
+    }
+
+    /// The first `words` committed words of the window were cut from the audio.
+    pub fn slide(&mut self, words: usize) {
+        self.in_window = self.in_window.saturating_sub(words);
+    }
+
+    pub fn committed(&self) -> &[String] {
+        &self.committed
+    }
+
+    /// Committed words no longer in the audio window.
+    pub fn spoken_before_window(&self) -> &[String] {
+        &self.committed[..self.committed.len() - self.in_window]
+    }
+
+    pub fn tentative(&self) -> &[String] {
+        &self.previous
+    }
+}
+
+fn words(segment: &Segment) -> impl Iterator<Item = String> + '_ {
+    segment.text.split_whitespace().map(String::from)
+}
+
+/// One utterance's worth of live transcription.
+pub struct Streamer {
+    config: StreamConfig,
+    options: TranscribeOptions,
+    filter: FilterConfig,
+    audio: Vec<f32>,
+    since_update: usize,
+    agreement: LocalAgreement,
+}
+
+impl Streamer {
+    pub fn new(config: StreamConfig, options: TranscribeOptions, filter: FilterConfig) -> Self {
+        Self { config, options, filter, audio: Vec::new(), since_update: 0, agreement: LocalAgreement::default() }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.audio.extend_from_slice(samples);
+        self.since_update += samples.len();
+    }
+
+    /// Enough new audio since the last pass to run another.
+    pub fn due(&self) -> bool {
+        self.audio.len() >= self.config.min_audio_ms * SAMPLE_RATE / 1000
+            && self.since_update >= self.config.interval_ms * SAMPLE_RATE / 1000
+    }
+
+    /// Start over for the next utterance.
+    pub fn reset(&mut self) {
+        self.audio.clear();
+        self.since_update = 0;
+        self.agreement = LocalAgreement::default();
+    }
+
+    pub fn partial(&self) -> Partial {
+        Partial {
+            committed: self.agreement.committed().join(" "),
+            tentative: self.agreement.tentative().join(" "),
+        }
+    }
+
+    /// Transcribe the window, commit what's stable and slide the window if it's full.
+    pub fn update(&mut self, asr: &dyn AsrBackend) -> Result<Partial> {
+        self.since_update = 0;
+        let context = AsrContext {
+            previous_text: self.agreement.spoken_before_window().join(" "),
+            ..Default::default()
+        };
+        let transcript = asr.transcribe_with(&self.audio, Some(&context), &self.options)?;
+        let (transcript, _) = hallucination::filter(transcript, &self.filter);
+        let hypothesis: Vec<String> = transcript.segments.iter().flat_map(words).collect();
+        self.agreement.insert(&hypothesis);
+        self.slide(&transcript.segments);
+        Ok(self.partial())
+    }
+
+    fn slide(&mut self, segments: &[Segment]) {
+        let window = self.config.window_ms * SAMPLE_RATE / 1000;
+        if self.audio.len() <= window {
+            return;
+        }
+        // Cut after the last segment whose words are all committed
+        let mut seen = 0;
+        let mut cut = None;
+        for segment in segments {
+            seen += words(segment).count();
+            if seen > self.agreement.in_window {
+                break;
+            }
+            cut = Some((segment.end_ms.max(0) as usize * SAMPLE_RATE / 1000, seen));
+        }
+        match cut {
+            Some((at, words)) if at > 0 => {
+                self.audio.drain(..at.min(self.audio.len()));
+                self.agreement.slide(words);
+            }
+            _ => {
+                // No clean boundary: drop the oldest audio and rely on overlap matching
+                let excess = self.audio.len() - window;
+                self.audio.drain(..excess);
+                self.agreement.slide(self.agreement.in_window);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asr::transcript::Transcript;
+    use std::sync::Mutex;
+
+    fn w(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn commits_agreed_prefix() {
+        let mut la = LocalAgreement::default();
+        assert!(la.insert(&w("hello")).is_empty());
+        assert_eq!(la.insert(&w("hello world")), w("hello").as_slice());
+        assert_eq!(la.tentative(), w("world").as_slice());
+        assert_eq!(la.insert(&w("hello, world how are")), w("world").as_slice());
+        assert_eq!(la.insert(&w("hello world how is it")), w("how").as_slice());
+        assert_eq!(la.committed(), w("hello world how").as_slice());
+        assert_eq!(la.tentative(), w("is it").as_slice());
+    }
+
+    #[test]
+    fn committed_words_are_never_revised() {
+        let mut la = LocalAgreement::default();
+        la.insert(&w("I scream"));
+        la.insert(&w("I scream for"));
+        // The model changed its mind about the committed part; keep it and move on
+        la.insert(&w("ice cream for you"));
+        assert_eq!(la.committed(), w("I scream for").as_slice());
+        assert_eq!(la.tentative(), w("you").as_slice());
+    }
+
+    #[test]
+    fn skips_overlap_after_window_slides() {
+        let mut la = LocalAgreement::default();
+        la.insert(&w("one two three four"));
+        la.insert(&w("one two three four five"));
+        la.slide(4);
+        assert_eq!(la.spoken_before_window(), w("one two three four").as_slice());
+        // The dropped audio still held "three four"
+        la.insert(&w("three four five six"));
+        assert_eq!(la.committed(), w("one two three four five").as_slice());
+        assert_eq!(la.spoken_before_window(), w("one two").as_slice());
+        la.insert(&w("three four five six seven"));
+        assert_eq!(la.committed(), w("one two three four five six").as_slice());
+        assert_eq!(la.tentative(), w("seven").as_slice());
+    }
+
+    /// Replays canned transcripts and records how much audio each pass saw.
+    struct Scripted {
+        hypotheses: Mutex<Vec<Vec<Segment>>>,
+        seen: Mutex<Vec<usize>>,
+    }
+
+    impl AsrBackend for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn transcribe_with(&self, audio: &[f32], _: Option<&AsrContext>, _: &TranscribeOptions) -> Result<Transcript> {
+            self.seen.lock().unwrap().push(audio.len());
+            let segments = self.hypotheses.lock().unwrap().remove(0);
+            Ok(Transcript::from_segments(segments, "en".into()))
+        }
+    }
+
+    fn seg(start_ms: i64, end_ms: i64, text: &str) -> Segment {
+        Segment { start_ms, end_ms, text: format!(" {}", text), tokens: vec![], no_speech_prob: None }
+    }
+
+    #[test]
+    fn streamer_updates_on_interval_and_slides_window() {
+        let config = StreamConfig { interval_ms: 500, window_ms: 2500, min_audio_ms: 500 };
+        let mut streamer = Streamer::new(config, TranscribeOptions::default(), FilterConfig::default());
+        let asr = Scripted {
+            hypotheses: Mutex::new(vec![
+                vec![seg(0, 1000, "Deploy the")],
+                vec![seg(0, 1000, "Deploy the build."), seg(1000, 2500, "Then")],
+                vec![seg(0, 1000, "Deploy the build."), seg(1000, 2500, "Then restart it.")],
+            ]),
+            seen: Mutex::new(vec![]),
+        };
+
+        streamer.push(&[0.0; 4000]);
+        assert!(!streamer.due());
+        streamer.push(&[0.0; 4000]);
+        assert!(streamer.due());
+        let p = streamer.update(&asr).unwrap();
+        assert_eq!(p, Partial { committed: "".into(), tentative: "Deploy the".into() });
+        assert!(!streamer.due());
+
+        streamer.push(&[0.0; 32000]);
+        let p = streamer.update(&asr).unwrap();
+        assert_eq!(p.committed, "Deploy the");
+        assert_eq!(p.tentative, "build. Then");
+
+        streamer.push(&[0.0; 8000]);
+        let p = streamer.update(&asr).unwrap();
+        assert_eq!(p.committed, "Deploy the build. Then");
+        assert_eq!(p.tentative, "restart it.");
+        // 3s buffered over a 2.5s window: the fully committed first second was cut
+        assert_eq!(streamer.audio.len(), 48000 - 16000);
+        assert_eq!(asr.seen.lock().unwrap().as_slice(), &[8000, 40000, 48000]);
+
+        streamer.reset();
+        assert_eq!(streamer.partial(), Partial::default());
+    }
+}
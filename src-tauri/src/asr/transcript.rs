@@ -0,0 +1,144 @@
+//! Structured ASR output: segments with timings, token confidences and language.
+
+use serde::Serialize;
+
+/// One decoded text token. Special tokens (timestamps, end of text) are left out.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Token {
+    pub id: i32,
+    pub text: String,
+    /// Probability the decoder gave this token, 0..1.
+    pub p: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Segment {
+    /// Start and end in milliseconds from the start of the audio.
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub tokens: Vec<Token>,
+    /// Probability the segment's audio holds no speech, if the backend reports one.
+    pub no_speech_prob: Option<f32>,
+}
+
+impl Segment {
+    /// Mean log-probability of the segment's tokens; `None` without tokens.
+    pub fn avg_logprob(&self) -> Option<f32> {
+        if self.tokens.is_empty() {
+            return None;
+        }
+        let sum: f32 = self.tokens.iter().map(|t| t.p.max(f32::MIN_POSITIVE).ln()).sum();
+        Some(sum / self.tokens.len() as f32)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Transcript {
+    /// All segment texts joined and trimmed.
+    pub text: String,
+    /// Whisper code of the spoken language, e.g. "hi" (also when translated to English).
+    pub language: String,
+    pub segments: Vec<Segment>,
+}
+
+impl Transcript {
+    pub fn from_segments(segments: Vec<Segment>, language: String) -> Self {
+        let text = segments.iter().map(|s| s.text.as_str()).collect::<String>().trim().to_string();
+        Self { text, language, segments }
+    }
+
+    /// Highest no-speech probability of any segment, if the backend reports them.
+    pub fn no_speech_prob(&self) -> Option<f32> {
+        self.segments.iter().filter_map(|s| s.no_speech_prob).reduce(f32::max)
+    }
+
+    /// Tokens the decoder was less than `min_p` sure of, trimmed, for highlighting.
+    pub fn low_confidence_words(&self, min_p: f32) -> Vec<&str> {
+        self.segments.iter()
+            .flat_map(|s| &s.tokens)
+            .filter(|t| t.p < min_p)
+            .map(|t| t.text.trim())
+            .filter(|t| !t.is_empty())
+            .collect()
+    }
+
+    /// SubRip subtitles, one cue per non-empty segment.
+    pub fn to_srt(&self) -> String {
+        let fmt = |ms: i64| {
+            let ms = ms.max(0);
+            format!("{:02}:{:02}:{:02},{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+        };
+        self.segments.iter()
+            .filter(|s| !s.text.trim().is_empty())
+            .enumerate()
+            .map(|(i, s)| format!("{}\n{} --> {}\n{}\n", i + 1, fmt(s.start_ms), fmt(s.end_ms), s.text.trim()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(text: &str, p: f32) -> Token {
+        Token { id: 0, text: text.into(), p }
+    }
+
+    fn segment(start_ms: i64, end_ms: i64, text: &str, tokens: Vec<Token>) -> Segment {
+        Segment { start_ms, end_ms, text: text.into(), tokens, no_speech_prob: None }
+    }
+
+    #[test]
+    fn text_joins_segments() {
+        let t = Transcript::from_segments(vec![
+            segment(0, 1500, " Deploy the", vec![]),
+            segment(1500, 3000, " new version.", vec![]),
+        ], "en".into());
+        assert_eq!(t.text, "Deploy the new version.");
+        assert_eq!(t.language, "en");
+        assert_eq!(Transcript::from_segments(vec![], "en".into()).text, "");
+    }
+
+    #[test]
+    fn average_logprob() {
+        let s = segment(0, 1000, " hi", vec![token(" hi", 1.0), token("!", (-2.0f32).exp())]);
+        assert!((s.avg_logprob().unwrap() + 1.0).abs() < 1e-5);
+        assert_eq!(segment(0, 0, "", vec![]).avg_logprob(), None);
+        // Zero probability stays finite
+        assert!(segment(0, 0, "", vec![token("x", 0.0)]).avg_logprob().unwrap().is_finite());
+    }
+
+    #[test]
+    fn no_speech_is_worst_segment() {
+        let mut a = segment(0, 1000, "a", vec![]);
+        let mut b = segment(1000, 2000, "b", vec![]);
+        let t = Transcript::from_segments(vec![a.clone(), b.clone()], "en".into());
+        assert_eq!(t.no_speech_prob(), None);
+        a.no_speech_prob = Some(0.1);
+        b.no_speech_prob = Some(0.7);
+        assert_eq!(Transcript::from_segments(vec![a, b], "en".into()).no_speech_prob(), Some(0.7));
+    }
+
+    #[test]
+    fn low_confidence_words() {
+        let t = Transcript::from_segments(vec![
+            segment(0, 1000, " run kubectl", vec![token(" run", 0.95), token(" kub", 0.3), token("ectl", 0.6)]),
+        ], "en".into());
+        assert_eq!(t.low_confidence_words(0.5), vec!["kub"]);
+    }
+
+    #[test]
+    fn srt_output() {
+        let t = Transcript::from_segments(vec![
+            segment(0, 2500, " Hello there.", vec![]),
+            segment(2500, 2600, " ", vec![]),
+            segment(3_723_004, 3_725_000, " Later.", vec![]),
+        ], "en".into());
+        assert_eq!(
+            t.to_srt(),
+            "1\n00:00:00,000 --> 00:00:02,500\nHello there.\n\n2\n01:02:03,004 --> 01:02:05,000\nLater.\n"
+        );
+    }
+}
@@ -46,6 +46,11 @@ impl Chunker {
         }
     }
 
+    /// Inside a speech segment (including its trailing silence).
+    pub fn is_buffering(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
     /// Force-flush the buffer (e.g., on hotkey release).
     pub fn flush(&mut self) -> Option<Vec<f32>> {
         if self.buffer.is_empty() {
@@ -111,6 +116,18 @@ mod tests {
         assert_eq!(seg.len(), 5 * 480);
     }
 
+    #[test]
+    fn is_buffering_tracks_segment() {
+        let mut c = Chunker::new(90);
+        assert!(!c.is_buffering());
+        c.feed(&silence_frame(), false);
+        assert!(!c.is_buffering());
+        c.feed(&speech_frame(), true);
+        assert!(c.is_buffering());
+        for _ in 0..3 { c.feed(&silence_frame(), false); }
+        assert!(!c.is_buffering());
+    }
+
     #[test]
     fn flush_empty_returns_none() {
         let mut c = Chunker::new(700);
@@ -5,6 +5,7 @@ use std::path::PathBuf;
 pub struct AppConfig {
     pub models_dir: PathBuf,
     pub db_path: PathBuf,
+    pub logs_dir: PathBuf,
     pub silence_threshold_ms: u64,
     pub sample_rate: u32,
 }
@@ -17,6 +18,7 @@ impl Default for AppConfig {
         Self {
             models_dir: base.join("models"),
             db_path: base.join("openflow.db"),
+            logs_dir: base.join("logs"),
             silence_threshold_ms: 700,
             sample_rate: 16000,
         }
@@ -32,6 +34,7 @@ mod tests {
         let c = AppConfig::default();
         assert!(c.models_dir.to_string_lossy().contains("openflow"));
         assert!(c.db_path.to_string_lossy().ends_with("openflow.db"));
+        assert_eq!(c.logs_dir.parent(), c.db_path.parent());
         assert_eq!(c.silence_threshold_ms, 700);
         assert_eq!(c.sample_rate, 16000);
     }
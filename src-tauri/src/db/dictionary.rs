@@ -1,5 +1,15 @@
 use anyhow::Result;
 use rusqlite::Connection;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DictEntry {
+    pub id: i64,
+    pub spoken: String,
+    pub written: String,
+    pub category: String,
+    pub usage_count: i64,
+}
 
 pub fn get_all(conn: &Connection) -> Result<Vec<String>> {
     let mut stmt = conn.prepare("SELECT spoken_form, written_form FROM personal_dict")?;
@@ -14,6 +24,29 @@ pub fn get_all(conn: &Connection) -> Result<Vec<String>> {
     Ok(entries)
 }
 
+/// All entries, most used first.
+pub fn entries(conn: &Connection) -> Result<Vec<DictEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, spoken_form, written_form, category, usage_count FROM personal_dict ORDER BY usage_count DESC, id",
+    )?;
+    let entries = stmt
+        .query_map([], |row| Ok(DictEntry {
+            id: row.get(0)?,
+            spoken: row.get(1)?,
+            written: row.get(2)?,
+            category: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            usage_count: row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+        }))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(entries)
+}
+
+pub fn increment_usage(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("UPDATE personal_dict SET usage_count = COALESCE(usage_count, 0) + 1 WHERE id = ?1", [id])?;
+    Ok(())
+}
+
 pub fn add(conn: &Connection, spoken: &str, written: &str, category: &str) -> Result<()> {
     conn.execute(
         "INSERT INTO personal_dict (spoken_form, written_form, category) VALUES (?1, ?2, ?3)",
@@ -56,4 +89,20 @@ mod tests {
         let all = get_all(&conn).unwrap();
         assert_eq!(all[0], "k8s → Kubernetes");
     }
+
+    #[test]
+    fn entries_ordered_by_usage() {
+        let conn = test_db();
+        add(&conn, "k eight s", "Kubernetes", "tech").unwrap();
+        add(&conn, "grpc", "gRPC", "tech").unwrap();
+        let grpc = entries(&conn).unwrap()[1].id;
+        increment_usage(&conn, grpc).unwrap();
+        increment_usage(&conn, grpc).unwrap();
+
+        let all = entries(&conn).unwrap();
+        assert_eq!(all[0].written, "gRPC");
+        assert_eq!(all[0].usage_count, 2);
+        assert_eq!(all[1].spoken, "k eight s");
+        assert_eq!(all[1].usage_count, 0);
+    }
 }
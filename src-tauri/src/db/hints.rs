@@ -1,10 +1,11 @@
 use anyhow::Result;
 use rusqlite::Connection;
 
-pub fn record_usage(conn: &Connection, app_name: &str) -> Result<()> {
+/// Record one dictation into `app_name`, with the language it was transcribed in if known.
+pub fn record_usage(conn: &Connection, app_name: &str, language: Option<&str>) -> Result<()> {
     conn.execute(
-        "INSERT INTO injection_history (raw_transcript, polished_text, app_name) VALUES ('', '', ?1)",
-        [app_name],
+        "INSERT INTO injection_history (raw_transcript, polished_text, app_name, language) VALUES ('', '', ?1, ?2)",
+        rusqlite::params![app_name, language],
     )?;
     Ok(())
 }
@@ -73,9 +74,9 @@ mod tests {
     #[test]
     fn record_usage_and_top_apps() {
         let conn = test_db();
-        for _ in 0..5 { record_usage(&conn, "Slack").unwrap(); }
-        for _ in 0..3 { record_usage(&conn, "Safari").unwrap(); }
-        record_usage(&conn, "Notes").unwrap();
+        for _ in 0..5 { record_usage(&conn, "Slack", None).unwrap(); }
+        for _ in 0..3 { record_usage(&conn, "Safari", None).unwrap(); }
+        record_usage(&conn, "Notes", None).unwrap();
         let top = top_apps(&conn, 2).unwrap();
         assert_eq!(top.len(), 2);
         assert_eq!(top[0], "Slack");
@@ -85,13 +86,25 @@ mod tests {
     #[test]
     fn top_apps_excludes_unknown() {
         let conn = test_db();
-        record_usage(&conn, "Unknown").unwrap();
-        record_usage(&conn, "Slack").unwrap();
+        record_usage(&conn, "Unknown", None).unwrap();
+        record_usage(&conn, "Slack", None).unwrap();
         let top = top_apps(&conn, 10).unwrap();
         assert!(!top.contains(&"Unknown".into()));
         assert!(top.contains(&"Slack".into()));
     }
 
+    #[test]
+    fn record_usage_stores_language() {
+        let conn = test_db();
+        record_usage(&conn, "Slack", Some("hi")).unwrap();
+        record_usage(&conn, "Notes", None).unwrap();
+        let langs: Vec<Option<String>> = conn.prepare("SELECT language FROM injection_history ORDER BY id").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .filter_map(|r| r.ok())
+            .collect();
+        assert_eq!(langs, vec![Some("hi".into()), None]);
+    }
+
     #[test]
     fn top_apps_empty() {
         let conn = test_db();
@@ -4,3 +4,7 @@ pub mod snippets;
 pub mod tones;
 pub mod settings;
 pub mod hints;
+pub mod rules;
+pub mod translation;
+pub mod models;
+pub mod rejections;
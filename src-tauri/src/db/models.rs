@@ -0,0 +1,215 @@
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelType {
+    Asr,
+    Vad,
+    Llm,
+}
+
+impl ModelType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelType::Asr => "asr",
+            ModelType::Vad => "vad",
+            ModelType::Llm => "llm",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "asr" => Some(ModelType::Asr),
+            "vad" => Some(ModelType::Vad),
+            "llm" => Some(ModelType::Llm),
+            _ => None,
+        }
+    }
+}
+
+/// What runs a model. Recorded when the model is found on disk, so loading never has to
+/// guess from the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelFormat {
+    /// whisper.cpp `ggml-*.bin`.
+    Ggml,
+    /// llama.cpp GGUF.
+    Gguf,
+    /// ONNX Runtime: a single file (VAD) or an export directory (ASR).
+    Onnx,
+}
+
+impl ModelFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelFormat::Ggml => "ggml",
+            ModelFormat::Gguf => "gguf",
+            ModelFormat::Onnx => "onnx",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ggml" => Some(ModelFormat::Ggml),
+            "gguf" => Some(ModelFormat::Gguf),
+            "onnx" => Some(ModelFormat::Onnx),
+            _ => None,
+        }
+    }
+}
+
+/// An installed model. At most one per type is active.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelEntry {
+    pub id: i64,
+    pub model_type: ModelType,
+    pub format: ModelFormat,
+    pub model_name: String,
+    pub model_path: String,
+    pub quantization: Option<String>,
+    pub is_active: bool,
+}
+
+fn query(conn: &Connection, filter: &str, params: impl rusqlite::Params) -> Result<Vec<ModelEntry>> {
+    let sql = format!(
+        "SELECT id, model_type, model_name, model_path, quantization, is_active, format FROM model_config {} \
+         ORDER BY model_type, model_name, quantization",
+        filter,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt
+        .query_map(params, |row| {
+            let model_type: String = row.get(1)?;
+            let format: String = row.get::<_, Option<String>>(6)?.unwrap_or_default();
+            // Rows of a type or format this build doesn't know are skipped
+            let known = ModelType::parse(&model_type).zip(ModelFormat::parse(&format));
+            Ok(known.map(|(model_type, format)| ModelEntry {
+                id: row.get(0).unwrap_or_default(),
+                model_type,
+                format,
+                model_name: row.get(2).unwrap_or_default(),
+                model_path: row.get(3).unwrap_or_default(),
+                quantization: row.get(4).unwrap_or_default(),
+                is_active: row.get::<_, i64>(5).unwrap_or(0) != 0,
+            }))
+        })?
+        .filter_map(|r| r.ok().flatten())
+        .collect();
+    Ok(rows)
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<ModelEntry>> {
+    query(conn, "", [])
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Option<ModelEntry>> {
+    Ok(query(conn, "WHERE id = ?1", [id])?.pop())
+}
+
+pub fn active(conn: &Connection, model_type: ModelType) -> Result<Option<ModelEntry>> {
+    Ok(query(conn, "WHERE model_type = ?1 AND is_active = 1", [model_type.as_str()])?.pop())
+}
+
+/// Add a model, or refresh the name, format and quantization of the one at `model_path`.
+/// Keeps whether it is active.
+pub fn upsert(conn: &Connection, model_type: ModelType, format: ModelFormat, model_name: &str, model_path: &str, quantization: Option<&str>) -> Result<i64> {
+    let existing: Option<i64> = conn
+        .query_row("SELECT id FROM model_config WHERE model_path = ?1", [model_path], |row| row.get(0))
+        .optional()?;
+    match existing {
+        Some(id) => {
+            conn.execute(
+                "UPDATE model_config SET model_type = ?1, format = ?2, model_name = ?3, quantization = ?4, updated_at = CURRENT_TIMESTAMP WHERE id = ?5",
+                rusqlite::params![model_type.as_str(), format.as_str(), model_name, quantization, id],
+            )?;
+            Ok(id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO model_config (model_type, format, model_name, model_path, quantization) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![model_type.as_str(), format.as_str(), model_name, model_path, quantization],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+/// Forget models whose path isn't in `paths` (deleted from disk). Returns how many.
+pub fn retain(conn: &Connection, paths: &[String]) -> Result<usize> {
+    let gone: Vec<i64> = list(conn)?.into_iter()
+        .filter(|m| !paths.contains(&m.model_path))
+        .map(|m| m.id)
+        .collect();
+    for id in &gone {
+        conn.execute("DELETE FROM model_config WHERE id = ?1", [id])?;
+    }
+    Ok(gone.len())
+}
+
+/// Make `id` the active model of its type, deactivating the previous one.
+pub fn set_active(conn: &Connection, id: i64) -> Result<ModelEntry> {
+    let entry = get(conn, id)?.ok_or_else(|| anyhow::anyhow!("No model with id {}", id))?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE model_config SET is_active = 0 WHERE model_type = ?1 AND is_active = 1",
+        [entry.model_type.as_str()],
+    )?;
+    tx.execute(
+        "UPDATE model_config SET is_active = 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        [id],
+    )?;
+    tx.commit()?;
+    Ok(ModelEntry { is_active: true, ..entry })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn test_db() -> Connection {
+        schema::init_db(std::path::Path::new(":memory:")).unwrap()
+    }
+
+    #[test]
+    fn upsert_is_keyed_by_path() {
+        let conn = test_db();
+        let id = upsert(&conn, ModelType::Asr, ModelFormat::Ggml, "base", "/m/ggml-base.bin", None).unwrap();
+        assert_eq!(upsert(&conn, ModelType::Asr, ModelFormat::Ggml, "base", "/m/ggml-base.bin", None).unwrap(), id);
+        upsert(&conn, ModelType::Asr, ModelFormat::Ggml, "small", "/m/ggml-small-q5_1.bin", Some("q5_1")).unwrap();
+        let all = list(&conn).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1].quantization.as_deref(), Some("q5_1"));
+        assert!(all.iter().all(|m| !m.is_active));
+    }
+
+    #[test]
+    fn one_active_per_type() {
+        let conn = test_db();
+        let base = upsert(&conn, ModelType::Asr, ModelFormat::Ggml, "base", "/m/ggml-base.bin", None).unwrap();
+        let small = upsert(&conn, ModelType::Asr, ModelFormat::Ggml, "small", "/m/ggml-small.bin", None).unwrap();
+        let vad = upsert(&conn, ModelType::Vad, ModelFormat::Onnx, "silero_vad", "/m/silero_vad.onnx", None).unwrap();
+        set_active(&conn, base).unwrap();
+        set_active(&conn, vad).unwrap();
+        set_active(&conn, small).unwrap();
+        assert_eq!(active(&conn, ModelType::Asr).unwrap().unwrap().id, small);
+        assert_eq!(active(&conn, ModelType::Vad).unwrap().unwrap().id, vad);
+        assert_eq!(active(&conn, ModelType::Llm).unwrap(), None);
+        assert!(!get(&conn, base).unwrap().unwrap().is_active);
+        assert!(set_active(&conn, 999).is_err());
+    }
+
+    #[test]
+    fn retain_drops_missing() {
+        let conn = test_db();
+        upsert(&conn, ModelType::Llm, ModelFormat::Gguf, "qwen", "/m/qwen.gguf", Some("q4_k_m")).unwrap();
+        let keep = upsert(&conn, ModelType::Vad, ModelFormat::Onnx, "silero_vad", "/m/silero_vad.onnx", None).unwrap();
+        assert_eq!(retain(&conn, &["/m/silero_vad.onnx".into()]).unwrap(), 1);
+        let all = list(&conn).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].id, keep);
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// Rejections hold whole transcripts, so only recent ones are kept, and only so many.
+const KEEP_DAYS: u32 = 30;
+const KEEP_ROWS: i64 = 500;
+
+/// Record polish output the validator refused, with the transcript it was given, and prune
+/// what is past the retention limits.
+pub fn record(conn: &Connection, reason: &str, raw: &str, polished: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO polish_rejections (reason, raw_transcript, polished_text) VALUES (?1, ?2, ?3)",
+        [reason, raw, polished],
+    )?;
+    conn.execute(
+        "DELETE FROM polish_rejections WHERE created_at <= datetime('now', ?1)
+         OR id NOT IN (SELECT id FROM polish_rejections ORDER BY id DESC LIMIT ?2)",
+        rusqlite::params![format!("-{} days", KEEP_DAYS), KEEP_ROWS],
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct RejectionStats {
+    /// Dictations over the period, to put the rejections in proportion.
+    pub dictations: i64,
+    /// (reason, count), most frequent first.
+    pub reasons: Vec<(String, i64)>,
+}
+
+/// Rejections and dictations over the last `days` days; rejections go back `KEEP_DAYS` at most.
+pub fn stats(conn: &Connection, days: u32) -> Result<RejectionStats> {
+    let since = format!("-{} days", days);
+    let dictations = conn.query_row(
+        "SELECT COUNT(*) FROM injection_history WHERE created_at > datetime('now', ?1)",
+        [&since],
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT reason, COUNT(*) as cnt FROM polish_rejections
+         WHERE created_at > datetime('now', ?1)
+         GROUP BY reason ORDER BY cnt DESC, reason"
+    )?;
+    let reasons = stmt.query_map([&since], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(RejectionStats { dictations, reasons })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{hints, schema};
+
+    #[test]
+    fn counts_by_reason() {
+        let conn = schema::init_db(std::path::Path::new(":memory:")).unwrap();
+        assert_eq!(stats(&conn, 30).unwrap(), RejectionStats::default());
+        for _ in 0..3 {
+            hints::record_usage(&conn, "Slack", None).unwrap();
+        }
+        record(&conn, "answered", "What is it?", "It is a cat.").unwrap();
+        record(&conn, "preamble", "ship it", "Sure! Ship it.").unwrap();
+        record(&conn, "answered", "Why?", "Because.").unwrap();
+        let stats = stats(&conn, 30).unwrap();
+        assert_eq!(stats.dictations, 3);
+        assert_eq!(stats.reasons, vec![("answered".to_string(), 2), ("preamble".to_string(), 1)]);
+    }
+
+    #[test]
+    fn prunes_old_and_excess_rejections() {
+        let conn = schema::init_db(std::path::Path::new(":memory:")).unwrap();
+        conn.execute(
+            "INSERT INTO polish_rejections (reason, raw_transcript, polished_text, created_at)
+             VALUES ('answered', 'old', 'old', datetime('now', '-40 days'))",
+            [],
+        ).unwrap();
+        for i in 0..KEEP_ROWS + 5 {
+            record(&conn, "preamble", &format!("raw {}", i), "Sure!").unwrap();
+        }
+        let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM polish_rejections"), KEEP_ROWS);
+        assert_eq!(count("SELECT COUNT(*) FROM polish_rejections WHERE reason = 'answered'"), 0);
+        let oldest: String = conn.query_row("SELECT raw_transcript FROM polish_rejections ORDER BY id LIMIT 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(oldest, "raw 5");
+    }
+}
@@ -0,0 +1,416 @@
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::settings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchType {
+    /// `*` and `?` wildcards, whole-string match.
+    Glob,
+    /// Unanchored regular expression.
+    Regex,
+}
+
+impl MatchType {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "glob" => Some(Self::Glob),
+            "regex" => Some(Self::Regex),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Glob => "glob",
+            Self::Regex => "regex",
+        }
+    }
+}
+
+/// Maps apps to a category. Every pattern that is set must match (case-insensitively);
+/// a `None` pattern matches anything. Higher priority wins, then the older rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppRule {
+    #[serde(default)]
+    pub id: i64,
+    pub priority: i64,
+    pub match_type: MatchType,
+    pub bundle_id: Option<String>,
+    pub app_name: Option<String>,
+    pub window_title: Option<String>,
+    pub category: String,
+}
+
+impl AppRule {
+    /// Reject rules that could never match or would silently never compile.
+    pub fn validate(&self) -> Result<()> {
+        if self.category.trim().is_empty() {
+            anyhow::bail!("Rule needs a category");
+        }
+        let patterns: Vec<&String> = [&self.bundle_id, &self.app_name, &self.window_title]
+            .into_iter().flatten().collect();
+        if patterns.is_empty() {
+            anyhow::bail!("Rule needs at least one pattern");
+        }
+        if self.match_type == MatchType::Regex {
+            for p in patterns {
+                RegexBuilder::new(p).build()
+                    .map_err(|e| anyhow::anyhow!("Invalid regex {:?}: {}", p, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Case-sensitive glob with `*` (any run) and `?` (any one char); callers lowercase first.
+pub(crate) fn glob_match(pattern: &str, value: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let v: Vec<char> = value.chars().collect();
+    let (mut pi, mut vi) = (0, 0);
+    // Position of the last `*` and the value index it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while vi < v.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == v[vi]) {
+            pi += 1;
+            vi += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, vi));
+            pi += 1;
+        } else if let Some((sp, sv)) = star {
+            // Let the last `*` swallow one more character
+            pi = sp + 1;
+            vi = sv + 1;
+            star = Some((sp, sv + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+enum Pattern {
+    /// Lowercased glob.
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn new(match_type: MatchType, pattern: &str) -> Option<Self> {
+        match match_type {
+            MatchType::Glob => Some(Self::Glob(pattern.to_lowercase())),
+            MatchType::Regex => RegexBuilder::new(pattern).case_insensitive(true).build().ok().map(Self::Regex),
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Self::Glob(glob) => glob_match(glob, &value.to_lowercase()),
+            Self::Regex(re) => re.is_match(value),
+        }
+    }
+}
+
+/// A rule with its patterns compiled; `None` patterns match anything.
+struct CompiledRule {
+    rule: AppRule,
+    bundle_id: Option<Pattern>,
+    app_name: Option<Pattern>,
+    window_title: Option<Pattern>,
+}
+
+impl CompiledRule {
+    /// None for a rule with a regex that doesn't compile, which could never match.
+    fn new(rule: AppRule) -> Option<Self> {
+        let compile = |p: &Option<String>| match p {
+            Some(p) => Pattern::new(rule.match_type, p).map(Some),
+            None => Some(None),
+        };
+        Some(Self {
+            bundle_id: compile(&rule.bundle_id)?,
+            app_name: compile(&rule.app_name)?,
+            window_title: compile(&rule.window_title)?,
+            rule,
+        })
+    }
+
+    fn matches(&self, bundle_id: &str, app_name: &str, window_title: &str) -> bool {
+        [(&self.bundle_id, bundle_id), (&self.app_name, app_name), (&self.window_title, window_title)]
+            .iter()
+            .all(|(pattern, value)| pattern.as_ref().is_none_or(|p| p.matches(value)))
+    }
+}
+
+/// Rules compiled once and kept in priority order.
+#[derive(Default)]
+pub struct RuleSet(Vec<CompiledRule>);
+
+impl RuleSet {
+    pub fn new(mut rules: Vec<AppRule>) -> Self {
+        rules.sort_by_key(|r| (std::cmp::Reverse(r.priority), r.id));
+        Self(rules.into_iter().filter_map(CompiledRule::new).collect())
+    }
+
+    /// First rule matching the app, in priority order.
+    pub fn find_match(&self, bundle_id: &str, app_name: &str, window_title: &str) -> Option<&AppRule> {
+        self.0.iter().find(|r| r.matches(bundle_id, app_name, window_title)).map(|r| &r.rule)
+    }
+}
+
+/// The rules in the database, compiled for matching.
+pub fn load(conn: &Connection) -> Result<RuleSet> {
+    Ok(RuleSet::new(list(conn)?))
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<AppRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, priority, match_type, bundle_pattern, app_name_pattern, title_pattern, category
+         FROM app_rules ORDER BY priority DESC, id",
+    )?;
+    let rules = stmt
+        .query_map([], |row| {
+            let match_type: String = row.get(2)?;
+            Ok(AppRule {
+                id: row.get(0)?,
+                priority: row.get(1)?,
+                match_type: MatchType::parse(&match_type).unwrap_or(MatchType::Glob),
+                bundle_id: row.get(3)?,
+                app_name: row.get(4)?,
+                window_title: row.get(5)?,
+                category: row.get(6)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rules)
+}
+
+pub fn add(conn: &Connection, rule: &AppRule) -> Result<i64> {
+    rule.validate()?;
+    conn.execute(
+        "INSERT INTO app_rules (priority, match_type, bundle_pattern, app_name_pattern, title_pattern, category)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![rule.priority, rule.match_type.as_str(), rule.bundle_id, rule.app_name, rule.window_title, rule.category],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update(conn: &Connection, rule: &AppRule) -> Result<()> {
+    rule.validate()?;
+    let changed = conn.execute(
+        "UPDATE app_rules SET priority = ?2, match_type = ?3, bundle_pattern = ?4, app_name_pattern = ?5,
+         title_pattern = ?6, category = ?7 WHERE id = ?1",
+        rusqlite::params![rule.id, rule.priority, rule.match_type.as_str(), rule.bundle_id, rule.app_name, rule.window_title, rule.category],
+    )?;
+    if changed == 0 {
+        anyhow::bail!("No rule with id {}", rule.id);
+    }
+    Ok(())
+}
+
+pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM app_rules WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Tone for a category from `category_tones`.
+pub fn category_tone(conn: &Connection, category: &str) -> Result<Option<String>> {
+    let tone = conn
+        .query_row("SELECT tone_directive FROM category_tones WHERE category = ?1", [category], |row| row.get(0))
+        .optional()?;
+    Ok(tone)
+}
+
+pub fn set_category_tone(conn: &Connection, category: &str, tone: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO category_tones (category, tone_directive) VALUES (?1, ?2)",
+        [category, tone],
+    )?;
+    Ok(())
+}
+
+pub fn list_category_tones(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT category, tone_directive FROM category_tones ORDER BY category")?;
+    let tones = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(tones)
+}
+
+pub const DEFAULT_TONES: &[(&str, &str)] = &[
+    ("email", "Professional, concise."),
+    ("slack", "Casual, conversational."),
+    ("code", "Technical. Use backticks for code references."),
+    ("code-review", "Technical and constructive. Use backticks for code references."),
+    ("terminal", "Command-like. Be terse."),
+    ("notes", "Structured. Use headers and lists where appropriate."),
+    ("default", "Natural, clear prose."),
+];
+
+/// Rules shipped with the app; the same set `categorize_app` falls back to without a database.
+pub fn default_rules() -> Vec<AppRule> {
+    let rule = |priority, match_type, bundle_id: Option<&str>, window_title: Option<&str>, category: &str| AppRule {
+        id: 0,
+        priority,
+        match_type,
+        bundle_id: bundle_id.map(String::from),
+        app_name: None,
+        window_title: window_title.map(String::from),
+        category: category.into(),
+    };
+    use MatchType::Regex;
+    vec![
+        rule(100, Regex, Some(r"firefox|chrom|safari|brave|\bedge\b|edgemac|msedge|microsoft-edge|thebrowser|vivaldi|opera"), Some(r"github|gitlab"), "code-review"),
+        rule(50, Regex, Some(r"mail|outlook|thunderbird|evolution|geary"), None, "email"),
+        rule(40, Regex, Some(r"slack"), None, "slack"),
+        rule(30, Regex, Some(r"vscode|xcode|^code$|code-oss|vscodium|jetbrains"), None, "code"),
+        rule(20, Regex, Some(r"notion"), None, "notes"),
+        rule(10, Regex, Some(r"terminal|iterm|konsole|alacritty|kitty|wezterm|foot|xterm|urxvt|tilix|terminator|st-256color|ghostty"), None, "terminal"),
+    ]
+}
+
+/// Insert the default rules once, so a user who deletes them doesn't get them back,
+/// and any missing category tones.
+pub fn seed_defaults(conn: &Connection) -> Result<()> {
+    if settings::get(conn, "app_rules_seeded")?.is_none() {
+        for rule in default_rules() {
+            add(conn, &rule)?;
+        }
+        settings::set(conn, "app_rules_seeded", "1")?;
+    }
+    for (category, tone) in DEFAULT_TONES {
+        conn.execute(
+            "INSERT OR IGNORE INTO category_tones (category, tone_directive) VALUES (?1, ?2)",
+            [category, tone],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn test_db() -> Connection {
+        schema::init_db(std::path::Path::new(":memory:")).unwrap()
+    }
+
+    fn glob_rule(priority: i64, title: &str, category: &str) -> AppRule {
+        AppRule {
+            id: 0,
+            priority,
+            match_type: MatchType::Glob,
+            bundle_id: None,
+            app_name: None,
+            window_title: Some(title.into()),
+            category: category.into(),
+        }
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_match("*github.com*", "pr #12 - github.com/acme"));
+        assert!(glob_match("com.?pple.*", "com.apple.mail"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("slack", "slack beta"));
+        assert!(!glob_match("*.txt", "notes.md"));
+    }
+
+    fn matches(rule: &AppRule, bundle_id: &str, app_name: &str, window_title: &str) -> bool {
+        RuleSet::new(vec![rule.clone()]).find_match(bundle_id, app_name, window_title).is_some()
+    }
+
+    #[test]
+    fn rule_requires_every_pattern() {
+        let rule = AppRule {
+            bundle_id: Some("*firefox*".into()),
+            ..glob_rule(0, "*github.com*", "code-review")
+        };
+        assert!(matches(&rule, "org.mozilla.firefox", "Firefox", "PR · github.com"));
+        assert!(matches(&rule, "org.mozilla.firefox", "Firefox", "PR · GITHUB.COM"));
+        assert!(!matches(&rule, "org.mozilla.firefox", "Firefox", "Inbox"));
+        assert!(!matches(&rule, "com.slack", "Slack", "github.com"));
+    }
+
+    #[test]
+    fn priority_then_age_decides() {
+        let mut low = glob_rule(1, "*", "notes");
+        low.id = 1;
+        let mut high = glob_rule(5, "*", "email");
+        high.id = 3;
+        let mut tie = glob_rule(5, "*", "slack");
+        tie.id = 2;
+        let rules = RuleSet::new(vec![low, high, tie]);
+        assert_eq!(rules.find_match("", "", "x").unwrap().category, "slack");
+        assert!(RuleSet::default().find_match("", "", "x").is_none());
+    }
+
+    #[test]
+    fn validate_rejects_bad_rules() {
+        assert!(glob_rule(0, "*", "").validate().is_err());
+        let mut no_pattern = glob_rule(0, "*", "notes");
+        no_pattern.window_title = None;
+        assert!(no_pattern.validate().is_err());
+        let bad_regex = AppRule { match_type: MatchType::Regex, ..glob_rule(0, "(", "notes") };
+        assert!(bad_regex.validate().is_err());
+        // One that got into the database anyway never matches
+        assert!(!matches(&bad_regex, "", "", "("));
+    }
+
+    #[test]
+    fn seeds_defaults_once() {
+        let conn = test_db();
+        let seeded = list(&conn).unwrap();
+        assert_eq!(seeded.len(), default_rules().len());
+        delete(&conn, seeded[0].id).unwrap();
+        seed_defaults(&conn).unwrap();
+        assert_eq!(list(&conn).unwrap().len(), default_rules().len() - 1);
+        assert_eq!(category_tone(&conn, "email").unwrap(), Some("Professional, concise.".into()));
+    }
+
+    #[test]
+    fn add_update_delete() {
+        let conn = test_db();
+        let id = add(&conn, &glob_rule(500, "*jira*", "notes")).unwrap();
+        let rules = list(&conn).unwrap();
+        assert_eq!(rules[0].id, id);
+
+        let mut edited = rules[0].clone();
+        edited.category = "code-review".into();
+        update(&conn, &edited).unwrap();
+        assert_eq!(list(&conn).unwrap()[0].category, "code-review");
+
+        delete(&conn, id).unwrap();
+        assert!(list(&conn).unwrap().iter().all(|r| r.id != id));
+        assert!(update(&conn, &edited).is_err());
+    }
+
+    #[test]
+    fn browser_on_github_is_code_review() {
+        let rules = RuleSet::new(default_rules());
+        let hit = rules.find_match("org.mozilla.firefox", "Firefox", "Fix parser · Pull Request #42 · acme/app · GitHub");
+        assert_eq!(hit.unwrap().category, "code-review");
+        assert!(rules.find_match("org.mozilla.firefox", "Firefox", "Weather").is_none());
+        let title = "Issues · acme/app · GitHub";
+        assert!(rules.find_match("com.microsoft.edgemac", "Microsoft Edge", title).is_some());
+        assert!(rules.find_match("microsoft-edge", "Microsoft Edge", title).is_some());
+        assert!(rules.find_match("org.example.knowledge", "Knowledge", title).is_none());
+        assert!(rules.find_match("hedgedoc", "HedgeDoc", title).is_none());
+    }
+
+    #[test]
+    fn category_tone_override() {
+        let conn = test_db();
+        set_category_tone(&conn, "slack", "Very casual, emoji welcome.").unwrap();
+        assert_eq!(category_tone(&conn, "slack").unwrap(), Some("Very casual, emoji welcome.".into()));
+        assert_eq!(category_tone(&conn, "nope").unwrap(), None);
+        let all = list_category_tones(&conn).unwrap();
+        assert_eq!(all.len(), DEFAULT_TONES.len());
+        assert!(all.contains(&("slack".into(), "Very casual, emoji welcome.".into())));
+    }
+}
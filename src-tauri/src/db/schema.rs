@@ -1,12 +1,15 @@
 use anyhow::Result;
-use rusqlite::Connection;
+use rusqlite::{Connection, TransactionBehavior};
 use std::path::Path;
 
+/// Data migrations applied so far, kept in `PRAGMA user_version`.
+const SCHEMA_VERSION: i64 = 3;
+
 pub fn init_db(path: &Path) -> Result<Connection> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
-    let conn = Connection::open(path)?;
+    let mut conn = Connection::open(path)?;
     conn.execute_batch(
         "
         CREATE TABLE IF NOT EXISTS personal_dict (
@@ -68,11 +71,81 @@ pub fn init_db(path: &Path) -> Result<Connection> {
             generated_date TEXT NOT NULL,
             PRIMARY KEY (app_name, generated_date)
         );
+
+        CREATE TABLE IF NOT EXISTS app_rules (
+            id INTEGER PRIMARY KEY,
+            priority INTEGER NOT NULL DEFAULT 0,
+            match_type TEXT NOT NULL DEFAULT 'glob',
+            bundle_pattern TEXT,
+            app_name_pattern TEXT,
+            title_pattern TEXT,
+            category TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS category_tones (
+            category TEXT PRIMARY KEY,
+            tone_directive TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS app_output_languages (
+            bundle_id TEXT PRIMARY KEY,
+            app_name TEXT NOT NULL,
+            language TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS polish_rejections (
+            id INTEGER PRIMARY KEY,
+            reason TEXT NOT NULL,
+            raw_transcript TEXT NOT NULL,
+            polished_text TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        );
         ",
     )?;
+    migrate(&mut conn)?;
     Ok(conn)
 }
 
+/// Run the data migrations this database hasn't had yet, once each.
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version = |conn: &Connection| conn.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0));
+    if version(conn)? >= SCHEMA_VERSION {
+        return Ok(());
+    }
+    // Take the write lock before re-reading, so two processes opening a new database don't both migrate it
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let from = version(&tx)?;
+    if from < 1 {
+        super::rules::seed_defaults(&tx)?;
+    }
+    if from < 2 {
+        // What runs each model, from the file layout the registry scans; the active ASR
+        // model is the registry's alone, so the separate backend setting goes
+        tx.execute_batch(
+            "ALTER TABLE model_config ADD COLUMN format TEXT;
+             UPDATE model_config SET format = CASE
+                 WHEN model_type = 'llm' THEN 'gguf'
+                 WHEN model_type = 'asr' AND model_path LIKE '%.bin' THEN 'ggml'
+                 ELSE 'onnx' END;
+             DELETE FROM settings WHERE key = 'asr_backend';",
+        )?;
+    }
+    if from < 3 {
+        // The seeded code-review rule matched "edge" inside names like "knowledge"; fix it unless edited
+        tx.execute(
+            "UPDATE app_rules SET bundle_pattern = ?1 WHERE bundle_pattern = ?2",
+            [
+                r"firefox|chrom|safari|brave|\bedge\b|edgemac|msedge|microsoft-edge|thebrowser|vivaldi|opera",
+                r"firefox|chrom|safari|brave|edge|thebrowser|vivaldi|opera",
+            ],
+        )?;
+    }
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    tx.commit()?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,6 +171,10 @@ mod tests {
         assert!(tables.contains(&"model_config".into()));
         assert!(tables.contains(&"settings".into()));
         assert!(tables.contains(&"hint_cache".into()));
+        assert!(tables.contains(&"app_rules".into()));
+        assert!(tables.contains(&"category_tones".into()));
+        assert!(tables.contains(&"app_output_languages".into()));
+        assert!(tables.contains(&"polish_rejections".into()));
     }
 
     #[test]
@@ -109,6 +186,69 @@ mod tests {
         ).unwrap();
     }
 
+    #[test]
+    fn migrations_run_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = init_db(&db_path).unwrap();
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        conn.execute("DELETE FROM category_tones", []).unwrap();
+        drop(conn);
+
+        let conn = init_db(&db_path).unwrap();
+        let tones: i64 = conn.query_row("SELECT COUNT(*) FROM category_tones", [], |row| row.get(0)).unwrap();
+        assert_eq!(tones, 0);
+    }
+
+    #[test]
+    fn seeded_browser_rule_is_fixed() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = init_db(&db_path).unwrap();
+        conn.execute_batch(
+            r"UPDATE app_rules SET bundle_pattern = 'firefox|chrom|safari|brave|edge|thebrowser|vivaldi|opera'
+                  WHERE category = 'code-review';
+              PRAGMA user_version = 2;",
+        ).unwrap();
+        drop(conn);
+
+        let conn = init_db(&db_path).unwrap();
+        let rules = crate::db::rules::load(&conn).unwrap();
+        let title = "Issues · acme/app · GitHub";
+        assert!(rules.find_match("org.example.knowledge", "Knowledge", title).is_none());
+        assert!(rules.find_match("microsoft-edge", "Microsoft Edge", title).is_some());
+    }
+
+    #[test]
+    fn model_formats_are_backfilled() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE model_config (id INTEGER PRIMARY KEY, model_type TEXT NOT NULL, model_name TEXT NOT NULL,
+                 model_path TEXT NOT NULL, quantization TEXT, is_active INTEGER DEFAULT 0,
+                 updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
+             INSERT INTO model_config (model_type, model_name, model_path) VALUES
+                 ('asr', 'base', '/m/ggml-base.bin'), ('asr', 'moonshine-base', '/m/onnx/moonshine-base'),
+                 ('vad', 'silero_vad', '/m/silero_vad.onnx'), ('llm', 'qwen', '/m/qwen.gguf');
+             CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             INSERT INTO settings VALUES ('asr_backend', '{\"kind\":\"whisper\"}');
+             PRAGMA user_version = 1;",
+        ).unwrap();
+        drop(conn);
+
+        let conn = init_db(&db_path).unwrap();
+        let formats: Vec<_> = crate::db::models::list(&conn).unwrap().into_iter()
+            .map(|m| (m.model_name, m.format.as_str()))
+            .collect();
+        assert_eq!(formats, vec![
+            ("base".to_string(), "ggml"), ("moonshine-base".to_string(), "onnx"),
+            ("qwen".to_string(), "gguf"), ("silero_vad".to_string(), "onnx"),
+        ]);
+        assert_eq!(crate::db::settings::get(&conn, "asr_backend").unwrap(), None);
+    }
+
     #[test]
     fn init_creates_parent_dirs() {
         let dir = tempfile::tempdir().unwrap();
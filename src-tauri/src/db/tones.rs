@@ -1,5 +1,14 @@
 use anyhow::Result;
 use rusqlite::Connection;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppTone {
+    pub bundle_id: String,
+    pub app_name: String,
+    pub category: String,
+    pub tone: String,
+}
 
 pub fn get_tone(conn: &Connection, bundle_id: &str) -> Result<Option<String>> {
     let mut stmt = conn.prepare("SELECT tone_directive FROM app_tones WHERE bundle_id = ?1")?;
@@ -17,6 +26,25 @@ pub fn set_tone(conn: &Connection, bundle_id: &str, app_name: &str, category: &s
     Ok(())
 }
 
+pub fn list(conn: &Connection) -> Result<Vec<AppTone>> {
+    let mut stmt = conn.prepare("SELECT bundle_id, app_name, category, tone_directive FROM app_tones ORDER BY app_name")?;
+    let tones = stmt
+        .query_map([], |row| Ok(AppTone {
+            bundle_id: row.get(0)?,
+            app_name: row.get(1)?,
+            category: row.get(2)?,
+            tone: row.get(3)?,
+        }))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(tones)
+}
+
+pub fn delete_tone(conn: &Connection, bundle_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM app_tones WHERE bundle_id = ?1", [bundle_id])?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -46,4 +74,19 @@ mod tests {
         set_tone(&conn, "com.slack", "Slack", "slack", "Very casual").unwrap();
         assert_eq!(get_tone(&conn, "com.slack").unwrap(), Some("Very casual".into()));
     }
+
+    #[test]
+    fn list_and_delete_tones() {
+        let conn = test_db();
+        set_tone(&conn, "com.microsoft.Outlook", "Outlook", "email", "Formal").unwrap();
+        set_tone(&conn, "com.slack", "Slack", "slack", "Casual").unwrap();
+        let tones = list(&conn).unwrap();
+        assert_eq!(tones.len(), 2);
+        assert_eq!(tones[0].app_name, "Outlook");
+        assert_eq!(tones[1].tone, "Casual");
+
+        delete_tone(&conn, "com.slack").unwrap();
+        assert_eq!(get_tone(&conn, "com.slack").unwrap(), None);
+        assert_eq!(list(&conn).unwrap().len(), 1);
+    }
 }
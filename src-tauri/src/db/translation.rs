@@ -0,0 +1,112 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+
+use super::settings;
+
+const GLOBAL_KEY: &str = "output_language";
+
+/// Language dictation into one app is written in. An empty `language` keeps whatever was
+/// spoken, overriding the global setting for that app.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppOutputLanguage {
+    pub bundle_id: String,
+    pub app_name: String,
+    pub language: String,
+}
+
+pub fn get_global(conn: &Connection) -> Result<Option<String>> {
+    Ok(settings::get(conn, GLOBAL_KEY)?.filter(|l| !l.is_empty()))
+}
+
+/// `None` turns global translation off.
+pub fn set_global(conn: &Connection, language: Option<&str>) -> Result<()> {
+    settings::set(conn, GLOBAL_KEY, language.unwrap_or(""))
+}
+
+pub fn get_for_app(conn: &Connection, bundle_id: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT language FROM app_output_languages WHERE bundle_id = ?1")?;
+    Ok(stmt.query_row([bundle_id], |row| row.get(0)).ok())
+}
+
+pub fn set_for_app(conn: &Connection, bundle_id: &str, app_name: &str, language: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO app_output_languages (bundle_id, app_name, language) VALUES (?1, ?2, ?3)",
+        [bundle_id, app_name, language],
+    )?;
+    Ok(())
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<AppOutputLanguage>> {
+    let mut stmt = conn.prepare("SELECT bundle_id, app_name, language FROM app_output_languages ORDER BY app_name")?;
+    let rows = stmt
+        .query_map([], |row| Ok(AppOutputLanguage {
+            bundle_id: row.get(0)?,
+            app_name: row.get(1)?,
+            language: row.get(2)?,
+        }))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+pub fn delete_for_app(conn: &Connection, bundle_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM app_output_languages WHERE bundle_id = ?1", [bundle_id])?;
+    Ok(())
+}
+
+/// Language to write dictation into `bundle_id` in: the app's setting, else the global one.
+/// `None` means no translation.
+pub fn resolve(conn: &Connection, bundle_id: &str) -> Option<String> {
+    match get_for_app(conn, bundle_id).ok().flatten() {
+        Some(language) => Some(language).filter(|l| !l.is_empty()),
+        None => get_global(conn).ok().flatten(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn test_db() -> Connection {
+        schema::init_db(std::path::Path::new(":memory:")).unwrap()
+    }
+
+    #[test]
+    fn global_setting_roundtrip() {
+        let conn = test_db();
+        assert_eq!(get_global(&conn).unwrap(), None);
+        set_global(&conn, Some("en")).unwrap();
+        assert_eq!(get_global(&conn).unwrap(), Some("en".into()));
+        set_global(&conn, None).unwrap();
+        assert_eq!(get_global(&conn).unwrap(), None);
+    }
+
+    #[test]
+    fn app_setting_overrides_global() {
+        let conn = test_db();
+        set_global(&conn, Some("en")).unwrap();
+        set_for_app(&conn, "com.tinyspeck.slackmacgap", "Slack", "hi").unwrap();
+        set_for_app(&conn, "com.apple.Notes", "Notes", "").unwrap();
+        assert_eq!(resolve(&conn, "com.tinyspeck.slackmacgap"), Some("hi".into()));
+        // Explicitly untranslated, despite the global setting
+        assert_eq!(resolve(&conn, "com.apple.Notes"), None);
+        assert_eq!(resolve(&conn, "com.apple.mail"), Some("en".into()));
+    }
+
+    #[test]
+    fn list_and_delete() {
+        let conn = test_db();
+        set_for_app(&conn, "com.tinyspeck.slackmacgap", "Slack", "en").unwrap();
+        set_for_app(&conn, "com.apple.mail", "Mail", "de").unwrap();
+        set_for_app(&conn, "com.apple.mail", "Mail", "fr").unwrap();
+        let all = list(&conn).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0], AppOutputLanguage { bundle_id: "com.apple.mail".into(), app_name: "Mail".into(), language: "fr".into() });
+
+        delete_for_app(&conn, "com.apple.mail").unwrap();
+        assert_eq!(resolve(&conn, "com.apple.mail"), None);
+        assert_eq!(list(&conn).unwrap().len(), 1);
+    }
+}
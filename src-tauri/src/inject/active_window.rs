@@ -0,0 +1,219 @@
+//! Focused-window lookup on Linux: Hyprland and sway/i3 IPC, then X11 EWMH properties.
+
+use serde_json::Value;
+use std::process::Command;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ActiveWindow {
+    pub app_name: String,
+    /// Wayland app_id or X11 WM_CLASS class — the closest thing Linux has to a bundle id.
+    pub app_id: String,
+    pub title: String,
+    /// Owning process, when the window system reports it.
+    pub pid: Option<u32>,
+}
+
+impl ActiveWindow {
+    fn new(app_id: &str, title: &str) -> Option<Self> {
+        if app_id.is_empty() {
+            return None;
+        }
+        Some(Self {
+            app_name: display_name(app_id),
+            app_id: app_id.to_string(),
+            title: title.to_string(),
+            pid: None,
+        })
+    }
+
+    fn with_pid(mut self, pid: Option<u64>) -> Self {
+        self.pid = pid.and_then(|p| u32::try_from(p).ok()).filter(|&p| p > 0);
+        self
+    }
+}
+
+pub fn active_window() -> Option<ActiveWindow> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        if let Some(w) = hyprland() { return Some(w); }
+    }
+    if std::env::var_os("SWAYSOCK").is_some() {
+        if let Some(w) = ipc_tree("swaymsg") { return Some(w); }
+    }
+    if std::env::var_os("I3SOCK").is_some() {
+        if let Some(w) = ipc_tree("i3-msg") { return Some(w); }
+    }
+    // Also covers XWayland clients on compositors without an IPC we know
+    if std::env::var_os("DISPLAY").is_some() {
+        return x11::active_window();
+    }
+    None
+}
+
+/// "org.wezfurlong.wezterm" → "Wezterm", "firefox" → "Firefox".
+pub(crate) fn display_name(app_id: &str) -> String {
+    let last = app_id.rsplit('.').next().unwrap_or(app_id);
+    let mut chars = last.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => app_id.to_string(),
+    }
+}
+
+fn run_json(cmd: &str, args: &[&str]) -> Option<Value> {
+    let out = Command::new(cmd).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&out.stdout).ok()
+}
+
+fn hyprland() -> Option<ActiveWindow> {
+    parse_hyprland(&run_json("hyprctl", &["activewindow", "-j"])?)
+}
+
+pub(crate) fn parse_hyprland(v: &Value) -> Option<ActiveWindow> {
+    let class = v["class"].as_str().filter(|s| !s.is_empty())
+        .or_else(|| v["initialClass"].as_str())?;
+    Some(ActiveWindow::new(class, v["title"].as_str().unwrap_or(""))?.with_pid(v["pid"].as_u64()))
+}
+
+fn ipc_tree(cmd: &str) -> Option<ActiveWindow> {
+    parse_sway_tree(&run_json(cmd, &["-t", "get_tree"])?)
+}
+
+/// Walk a sway/i3 `get_tree` reply for the focused leaf.
+pub(crate) fn parse_sway_tree(node: &Value) -> Option<ActiveWindow> {
+    if node["focused"].as_bool() == Some(true) {
+        // Native Wayland windows carry app_id; XWayland and i3 windows carry window_properties
+        let id = node["app_id"].as_str()
+            .or_else(|| node["window_properties"]["class"].as_str())
+            .unwrap_or("");
+        return Some(ActiveWindow::new(id, node["name"].as_str().unwrap_or(""))?.with_pid(node["pid"].as_u64()));
+    }
+    ["nodes", "floating_nodes"].iter()
+        .filter_map(|k| node[*k].as_array())
+        .flatten()
+        .find_map(parse_sway_tree)
+}
+
+/// WM_CLASS is "instance\0class\0"; the class part is the stable application name.
+pub(crate) fn parse_wm_class(raw: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(raw)
+        .split('\0')
+        .rfind(|s| !s.is_empty())
+        .map(String::from)
+}
+
+mod x11 {
+    use super::ActiveWindow;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, Window};
+
+    fn atom(conn: &impl Connection, name: &[u8]) -> Option<u32> {
+        Some(conn.intern_atom(false, name).ok()?.reply().ok()?.atom)
+    }
+
+    fn property(conn: &impl Connection, win: Window, prop: u32, ty: u32) -> Option<Vec<u8>> {
+        let reply = conn.get_property(false, win, prop, ty, 0, 1024).ok()?.reply().ok()?;
+        if reply.value.is_empty() { None } else { Some(reply.value) }
+    }
+
+    pub fn active_window() -> Option<ActiveWindow> {
+        let (conn, screen) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots[screen].root;
+
+        let net_active = atom(&conn, b"_NET_ACTIVE_WINDOW")?;
+        let win = conn.get_property(false, root, net_active, AtomEnum::WINDOW, 0, 1).ok()?
+            .reply().ok()?
+            .value32()?.next()
+            .filter(|&w| w != 0)?;
+
+        let class = property(&conn, win, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())
+            .and_then(|raw| super::parse_wm_class(&raw))?;
+
+        let utf8 = atom(&conn, b"UTF8_STRING")?;
+        let net_name = atom(&conn, b"_NET_WM_NAME")?;
+        let title = property(&conn, win, net_name, utf8)
+            .or_else(|| property(&conn, win, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))
+            .map(|raw| String::from_utf8_lossy(&raw).into_owned())
+            .unwrap_or_default();
+
+        let pid = atom(&conn, b"_NET_WM_PID")
+            .and_then(|net_pid| {
+                conn.get_property(false, win, net_pid, AtomEnum::CARDINAL, 0, 1).ok()?
+                    .reply().ok()?
+                    .value32()?.next()
+            })
+            .map(u64::from);
+
+        Some(ActiveWindow::new(&class, &title)?.with_pid(pid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn display_name_from_ids() {
+        assert_eq!(display_name("org.wezfurlong.wezterm"), "Wezterm");
+        assert_eq!(display_name("firefox"), "Firefox");
+        assert_eq!(display_name("Slack"), "Slack");
+    }
+
+    #[test]
+    fn wm_class_takes_class_part() {
+        assert_eq!(parse_wm_class(b"code\0Code\0"), Some("Code".into()));
+        assert_eq!(parse_wm_class(b"slack\0Slack"), Some("Slack".into()));
+        assert_eq!(parse_wm_class(b""), None);
+    }
+
+    #[test]
+    fn hyprland_active_window() {
+        let v = json!({"class": "Slack", "title": "#engineering - Slack", "initialClass": "Slack", "pid": 4242});
+        let w = parse_hyprland(&v).unwrap();
+        assert_eq!(w.app_id, "Slack");
+        assert_eq!(w.pid, Some(4242));
+        assert_eq!(w.title, "#engineering - Slack");
+        assert!(parse_hyprland(&json!({})).is_none());
+    }
+
+    #[test]
+    fn sway_tree_finds_focused_leaf() {
+        let tree = json!({
+            "focused": false,
+            "nodes": [{
+                "focused": false,
+                "nodes": [
+                    {"focused": false, "app_id": "firefox", "name": "GitHub"},
+                    {"focused": true, "app_id": "org.wezfurlong.wezterm", "name": "vim main.rs", "pid": 901}
+                ],
+                "floating_nodes": []
+            }]
+        });
+        let w = parse_sway_tree(&tree).unwrap();
+        assert_eq!(w.app_id, "org.wezfurlong.wezterm");
+        assert_eq!(w.app_name, "Wezterm");
+        assert_eq!(w.title, "vim main.rs");
+        assert_eq!(w.pid, Some(901));
+    }
+
+    #[test]
+    fn sway_tree_xwayland_and_floating() {
+        let tree = json!({
+            "nodes": [],
+            "floating_nodes": [
+                {"focused": true, "app_id": null, "window_properties": {"class": "Code"}, "name": "lib.rs"}
+            ]
+        });
+        let w = parse_sway_tree(&tree).unwrap();
+        assert_eq!(w.app_id, "Code");
+        assert_eq!(w.title, "lib.rs");
+    }
+
+    #[test]
+    fn sway_tree_nothing_focused() {
+        assert!(parse_sway_tree(&json!({"nodes": [{"focused": false}]})).is_none());
+    }
+}
@@ -0,0 +1,438 @@
+//! Focused-text context on Linux through AT-SPI2, the D-Bus accessibility bus.
+//!
+//! Finds registry → applications → active window → focused element and reads its
+//! Text interface: the selection if there is one, otherwise the text before the caret.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use zbus::blocking::Connection;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+const REGISTRY: &str = "org.a11y.atspi.Registry";
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+const ACCESSIBLE: &str = "org.a11y.atspi.Accessible";
+const TEXT: &str = "org.a11y.atspi.Text";
+const COLLECTION: &str = "org.a11y.atspi.Collection";
+
+// AtspiCollectionMatchType and AtspiCollectionSortOrder
+const MATCH_ALL: i32 = 1;
+const SORT_CANONICAL: u32 = 1;
+
+// Bit positions in the AtspiStateType bitfield returned by GetState
+const STATE_ACTIVE: u32 = 1;
+const STATE_FOCUSED: u32 = 12;
+
+/// An app that hangs its accessibility thread must not stall dictation.
+const CALL_TIMEOUT: Duration = Duration::from_millis(300);
+/// Browsers expose thousands of nodes; give up rather than walk all of them.
+const MAX_NODES: usize = 2000;
+/// Longest the tree walk may take when the window can't be asked for its focus directly.
+/// Past it there is no text context rather than a stalled dictation.
+const WALK_BUDGET: Duration = Duration::from_millis(50);
+const CONTEXT_CHARS: i32 = 200;
+
+/// (bus name, object path) — how AT-SPI refers to an accessible object.
+pub(crate) type ObjRef = (String, OwnedObjectPath);
+
+/// Active window title and the focused widget's selection or text before the caret.
+/// Empty strings when there is no accessibility bus or nothing is focused.
+pub fn get_text_context() -> (String, String) {
+    let Some(conn) = connect() else {
+        return (String::new(), String::new());
+    };
+    Reader::new(&conn, registry_root()).text_context()
+}
+
+/// The focused widget and the connection it was found on, so its text can be re-read
+/// without walking the tree again.
+pub struct FocusedText {
+    conn: Connection,
+    obj: ObjRef,
+}
+
+impl FocusedText {
+    pub fn find() -> Option<Self> {
+        Self::find_under(connect()?, registry_root())
+    }
+
+    fn find_under(conn: Connection, root: ObjRef) -> Option<Self> {
+        let (_, focused) = Reader::new(&conn, root).focused()?;
+        Some(Self { obj: focused?, conn })
+    }
+
+    /// Full text of the widget.
+    pub fn value(&self) -> Option<String> {
+        Reader::new(&self.conn, registry_root()).text(&self.obj, 0, -1)
+    }
+}
+
+fn registry_root() -> ObjRef {
+    (REGISTRY.to_string(), OwnedObjectPath::try_from(ROOT_PATH).unwrap())
+}
+
+/// The accessibility bus is separate from the session bus; its address comes from
+/// `AT_SPI_BUS_ADDRESS` or from asking `org.a11y.Bus` on the session bus.
+fn connect() -> Option<Connection> {
+    let address = match std::env::var("AT_SPI_BUS_ADDRESS") {
+        Ok(addr) if !addr.is_empty() => addr,
+        _ => bus_address_from_session().or_else(|| {
+            tracing::debug!("No accessibility bus available");
+            None
+        })?,
+    };
+    zbus::blocking::connection::Builder::address(address.as_str()).ok()?
+        .method_timeout(CALL_TIMEOUT)
+        .build()
+        .map_err(|e| tracing::debug!("Failed to connect to accessibility bus: {}", e))
+        .ok()
+}
+
+fn bus_address_from_session() -> Option<String> {
+    let session = zbus::blocking::connection::Builder::session().ok()?
+        .method_timeout(CALL_TIMEOUT)
+        .build().ok()?;
+    let reply = session
+        .call_method(Some("org.a11y.Bus"), "/org/a11y/bus", Some("org.a11y.Bus"), "GetAddress", &())
+        .ok()?;
+    reply.body().deserialize::<String>().ok()
+}
+
+/// A Collection match rule: states, attributes, roles and interfaces, each with how to
+/// match them, then whether to invert the match.
+type MatchRule = (Vec<i32>, i32, HashMap<String, String>, i32, Vec<i32>, i32, Vec<String>, i32, bool);
+
+pub(crate) struct Reader<'a> {
+    conn: &'a Connection,
+    root: ObjRef,
+    walk_budget: Duration,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(conn: &'a Connection, root: ObjRef) -> Self {
+        Self { conn, root, walk_budget: WALK_BUDGET }
+    }
+
+    pub(crate) fn text_context(&self) -> (String, String) {
+        let Some((title, focused)) = self.focused() else {
+            return (String::new(), String::new());
+        };
+        let context = focused.and_then(|f| self.selection(&f).or_else(|| self.before_caret(&f)));
+        (title, context.unwrap_or_default())
+    }
+
+    /// Title of the active window and its focused descendant, if any.
+    fn focused(&self) -> Option<(String, Option<ObjRef>)> {
+        let window = self.children(&self.root).iter()
+            .flat_map(|app| self.children(app))
+            .find(|w| self.has_state(w, STATE_ACTIVE))?;
+        let title = self.property::<String>(&window, ACCESSIBLE, "Name").unwrap_or_default();
+        let focused = match self.match_focused(&window) {
+            Some(matches) => matches.into_iter().next(),
+            None => self.find_focused(&window),
+        };
+        Some((title, focused))
+    }
+
+    /// Ask the window for its FOCUSED descendant in one call. None when the app doesn't
+    /// implement Collection; apps on at-spi2-atk (GTK, Firefox, Chromium, Electron) do.
+    fn match_focused(&self, window: &ObjRef) -> Option<Vec<ObjRef>> {
+        let mut states = vec![0i32; 2];
+        states[(STATE_FOCUSED / 32) as usize] |= 1 << (STATE_FOCUSED % 32);
+        let rule: MatchRule = (states, MATCH_ALL, HashMap::new(), MATCH_ALL, Vec::new(), MATCH_ALL, Vec::new(), MATCH_ALL, false);
+        self.call(window, COLLECTION, "GetMatches", &(rule, SORT_CANONICAL, 1i32, true))
+    }
+
+    /// Depth-first search under `start` for the element with the FOCUSED state, within
+    /// `walk_budget`.
+    fn find_focused(&self, start: &ObjRef) -> Option<ObjRef> {
+        let deadline = Instant::now() + self.walk_budget;
+        let mut stack = vec![start.clone()];
+        let mut visited = 0;
+        while let Some(obj) = stack.pop() {
+            visited += 1;
+            if visited > MAX_NODES || Instant::now() >= deadline {
+                tracing::debug!("AT-SPI walk gave up after {} nodes without finding focus", visited - 1);
+                return None;
+            }
+            if self.has_state(&obj, STATE_FOCUSED) {
+                return Some(obj);
+            }
+            // Reverse so the first child is visited first
+            stack.extend(self.children(&obj).into_iter().rev());
+        }
+        None
+    }
+
+    fn selection(&self, obj: &ObjRef) -> Option<String> {
+        let n: i32 = self.call(obj, TEXT, "GetNSelections", &())?;
+        if n < 1 {
+            return None;
+        }
+        let (start, end): (i32, i32) = self.call(obj, TEXT, "GetSelection", &(0i32,))?;
+        // Keep the prompt small
+        let end = end.min(start + CONTEXT_CHARS);
+        self.text(obj, start, end).filter(|s| !s.is_empty())
+    }
+
+    fn before_caret(&self, obj: &ObjRef) -> Option<String> {
+        let caret = self.property::<i32>(obj, TEXT, "CaretOffset")?;
+        if caret <= 0 {
+            return None;
+        }
+        self.text(obj, (caret - CONTEXT_CHARS).max(0), caret).filter(|s| !s.is_empty())
+    }
+
+    /// Offsets are in characters; an end of -1 means the end of the text.
+    fn text(&self, obj: &ObjRef, start: i32, end: i32) -> Option<String> {
+        self.call(obj, TEXT, "GetText", &(start, end))
+    }
+
+    fn children(&self, obj: &ObjRef) -> Vec<ObjRef> {
+        self.call::<Vec<ObjRef>, _>(obj, ACCESSIBLE, "GetChildren", &())
+            .unwrap_or_default()
+    }
+
+    fn has_state(&self, obj: &ObjRef, bit: u32) -> bool {
+        self.call::<Vec<u32>, _>(obj, ACCESSIBLE, "GetState", &())
+            .and_then(|words| words.get((bit / 32) as usize).copied())
+            .is_some_and(|word| word & (1 << (bit % 32)) != 0)
+    }
+
+    fn call<R, B>(&self, obj: &ObjRef, iface: &str, method: &str, body: &B) -> Option<R>
+    where
+        R: for<'d> serde::Deserialize<'d> + zbus::zvariant::Type,
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        let reply = self.conn
+            .call_method(Some(obj.0.as_str()), &obj.1, Some(iface), method, body)
+            .ok()?;
+        reply.body().deserialize().ok()
+    }
+
+    fn property<T: TryFrom<OwnedValue>>(&self, obj: &ObjRef, iface: &str, name: &str) -> Option<T> {
+        let value: OwnedValue = self.call(obj, "org.freedesktop.DBus.Properties", "Get", &(iface, name))?;
+        T::try_from(value).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+
+    /// Private bus so tests never touch the desktop's accessibility tree.
+    struct TestBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl TestBus {
+        fn start() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("dbus-daemon not found");
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+            Self { daemon, address: address.trim().to_string() }
+        }
+
+        fn connect(&self) -> Connection {
+            zbus::blocking::connection::Builder::address(self.address.as_str()).unwrap()
+                .build().unwrap()
+        }
+    }
+
+    impl Drop for TestBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+        }
+    }
+
+    struct StubAccessible {
+        name: String,
+        children: Vec<ObjRef>,
+        states: Vec<u32>,
+    }
+
+    #[zbus::interface(name = "org.a11y.atspi.Accessible")]
+    impl StubAccessible {
+        fn get_children(&self) -> Vec<ObjRef> {
+            self.children.clone()
+        }
+
+        fn get_state(&self) -> Vec<u32> {
+            self.states.clone()
+        }
+
+        #[zbus(property)]
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+    }
+
+    struct StubText {
+        text: String,
+        caret: i32,
+        selection: Option<(i32, i32)>,
+    }
+
+    #[zbus::interface(name = "org.a11y.atspi.Text")]
+    impl StubText {
+        fn get_text(&self, start: i32, end: i32) -> String {
+            let end = if end < 0 { self.text.chars().count() as i32 } else { end };
+            self.text.chars().skip(start as usize).take((end - start) as usize).collect()
+        }
+
+        fn get_n_selections(&self) -> i32 {
+            self.selection.is_some() as i32
+        }
+
+        fn get_selection(&self, _n: i32) -> (i32, i32) {
+            self.selection.unwrap_or((0, 0))
+        }
+
+        #[zbus(property)]
+        fn caret_offset(&self) -> i32 {
+            self.caret
+        }
+    }
+
+    /// Answers every GetMatches with `matches`, as a window implementing Collection would.
+    struct StubCollection {
+        matches: Vec<ObjRef>,
+    }
+
+    #[zbus::interface(name = "org.a11y.atspi.Collection")]
+    impl StubCollection {
+        fn get_matches(&self, _rule: MatchRule, _sort_by: u32, _count: i32, _traverse: bool) -> Vec<ObjRef> {
+            self.matches.clone()
+        }
+    }
+
+    fn path(p: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(p).unwrap()
+    }
+
+    fn accessible(name: &str, children: Vec<ObjRef>, states: &[u32]) -> StubAccessible {
+        let mut words = vec![0u32; 2];
+        for &bit in states {
+            words[(bit / 32) as usize] |= 1 << (bit % 32);
+        }
+        StubAccessible { name: name.into(), children, states: words }
+    }
+
+    /// root → app → [background window, active window → panel → entry]
+    fn serve_tree(bus: &TestBus, text: StubText, focused: bool) -> (Connection, ObjRef) {
+        let server = bus.connect();
+        let me = server.unique_name().unwrap().to_string();
+        let obj = |p: &str| (me.clone(), path(p));
+        let entry_states: &[u32] = if focused { &[STATE_FOCUSED] } else { &[] };
+
+        {
+            let objects = server.object_server();
+            objects.at("/root", accessible("", vec![obj("/app")], &[])).unwrap();
+            objects.at("/app", accessible("gedit", vec![obj("/bg"), obj("/win")], &[])).unwrap();
+            objects.at("/bg", accessible("Other", vec![], &[])).unwrap();
+            objects.at("/win", accessible("notes.txt - gedit", vec![obj("/panel")], &[STATE_ACTIVE])).unwrap();
+            objects.at("/panel", accessible("", vec![obj("/entry")], &[])).unwrap();
+            objects.at("/entry", accessible("", vec![], entry_states)).unwrap();
+            objects.at("/entry", text).unwrap();
+        }
+        let root = obj("/root");
+        (server, root)
+    }
+
+    #[test]
+    #[ignore] // requires dbus-daemon
+    fn reads_text_before_caret() {
+        let bus = TestBus::start();
+        let long = "x".repeat(300) + "ship the release on Tuesday";
+        let caret = long.chars().count() as i32;
+        let (_server, root) = serve_tree(&bus, StubText { text: long.clone(), caret, selection: None }, true);
+
+        let client = bus.connect();
+        let (title, ctx) = Reader::new(&client, root).text_context();
+        assert_eq!(title, "notes.txt - gedit");
+        assert_eq!(ctx.chars().count(), 200);
+        assert!(ctx.ends_with("ship the release on Tuesday"));
+    }
+
+    #[test]
+    #[ignore] // requires dbus-daemon
+    fn prefers_selection() {
+        let bus = TestBus::start();
+        let text = StubText { text: "hello Kubernetes world".into(), caret: 22, selection: Some((6, 16)) };
+        let (_server, root) = serve_tree(&bus, text, true);
+
+        let client = bus.connect();
+        let (_, ctx) = Reader::new(&client, root).text_context();
+        assert_eq!(ctx, "Kubernetes");
+    }
+
+    #[test]
+    #[ignore] // requires dbus-daemon
+    fn title_without_focused_text() {
+        let bus = TestBus::start();
+        let text = StubText { text: "unused".into(), caret: 6, selection: None };
+        let (_server, root) = serve_tree(&bus, text, false);
+
+        let client = bus.connect();
+        let (title, ctx) = Reader::new(&client, root).text_context();
+        assert_eq!(title, "notes.txt - gedit");
+        assert_eq!(ctx, "");
+    }
+
+    #[test]
+    #[ignore] // requires dbus-daemon
+    fn asks_the_window_for_focus() {
+        let bus = TestBus::start();
+        let text = StubText { text: "from collection".into(), caret: 15, selection: None };
+        // The entry lacks the FOCUSED state, so only the Collection answer can find it
+        let (server, root) = serve_tree(&bus, text, false);
+        let entry = (root.0.clone(), path("/entry"));
+        server.object_server().at("/win", StubCollection { matches: vec![entry] }).unwrap();
+
+        let client = bus.connect();
+        let (_, ctx) = Reader::new(&client, root).text_context();
+        assert_eq!(ctx, "from collection");
+    }
+
+    #[test]
+    #[ignore] // requires dbus-daemon
+    fn walk_gives_up_past_its_budget() {
+        let bus = TestBus::start();
+        let text = StubText { text: "unreached".into(), caret: 9, selection: None };
+        let (_server, root) = serve_tree(&bus, text, true);
+
+        let client = bus.connect();
+        let reader = Reader { walk_budget: Duration::ZERO, ..Reader::new(&client, root) };
+        let (title, ctx) = reader.text_context();
+        assert_eq!((title.as_str(), ctx.as_str()), ("notes.txt - gedit", ""));
+    }
+
+    #[test]
+    #[ignore] // requires dbus-daemon
+    fn focused_text_rereads_the_same_widget() {
+        let bus = TestBus::start();
+        let text = StubText { text: "draft".into(), caret: 5, selection: None };
+        let (server, root) = serve_tree(&bus, text, true);
+
+        let focused = FocusedText::find_under(bus.connect(), root).unwrap();
+        assert_eq!(focused.value().as_deref(), Some("draft"));
+        let entry = server.object_server().interface::<_, StubText>("/entry").unwrap();
+        entry.get_mut().text.push_str(" pasted");
+        assert_eq!(focused.value().as_deref(), Some("draft pasted"));
+    }
+
+    #[test]
+    #[ignore] // requires dbus-daemon
+    fn missing_registry_is_empty() {
+        let bus = TestBus::start();
+        let client = bus.connect();
+        let (title, ctx) = Reader::new(&client, registry_root()).text_context();
+        assert_eq!((title.as_str(), ctx.as_str()), ("", ""));
+    }
+}
@@ -1,18 +1,142 @@
 use anyhow::Result;
-use arboard::Clipboard;
+use arboard::{Clipboard, ImageData};
+use std::path::PathBuf;
+use std::sync::Mutex;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use super::context::FocusedText;
+
+/// Longest we keep dictated text on the clipboard waiting for the paste to land.
+const PASTE_TIMEOUT: Duration = Duration::from_millis(1000);
+/// Fallback wait when the focused element's value can't be read (terminals, apps without accessibility support).
+const PASTE_SETTLE: Duration = Duration::from_millis(150);
+
+/// The text was pasted, but the user's previous clipboard could not be put back.
+#[derive(Debug)]
+pub struct RestoreError(pub String);
+
+impl std::fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to restore clipboard: {}", self.0)
+    }
+}
+
+impl std::error::Error for RestoreError {}
+
+/// The last restore failure, until the pipeline picks it up to tell the user.
+static RESTORE_ERROR: Mutex<Option<RestoreError>> = Mutex::new(None);
+
+/// Take the restore failure from the last paste, if there was one.
+pub fn take_restore_error() -> Option<RestoreError> {
+    RESTORE_ERROR.lock().ok()?.take()
+}
+
+/// Every clipboard format arboard can read back.
+#[derive(Default)]
+pub struct ClipboardSnapshot {
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub image: Option<ImageData<'static>>,
+    pub files: Option<Vec<PathBuf>>,
+}
+
+impl ClipboardSnapshot {
+    pub fn capture(clip: &mut Clipboard) -> Self {
+        Self {
+            text: clip.get_text().ok(),
+            html: clip.get().html().ok(),
+            image: clip.get_image().ok(),
+            files: clip.get().file_list().ok().filter(|f| !f.is_empty()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none() && self.html.is_none() && self.image.is_none() && self.files.is_none()
+    }
+
+    /// The one write that puts back the most of the snapshot, and the formats it leaves out.
+    /// arboard replaces the whole clipboard on every write and can only pair HTML with its
+    /// plain-text alternative; files and images always go alone. So files win (their text is
+    /// just the paths), then text with its HTML, then an image. Text beats an image because
+    /// an image copied alongside text (a spreadsheet range, a rich document) renders that text.
+    fn plan(&self) -> (Restore<'_>, Vec<&'static str>) {
+        let captured = [
+            ("files", self.files.is_some()),
+            ("HTML", self.html.is_some()),
+            ("text", self.text.is_some()),
+            ("image", self.image.is_some()),
+        ];
+        let (restore, written): (Restore, &[&str]) = if let Some(files) = &self.files {
+            (Restore::Files(files), &["files"])
+        } else if let Some(html) = &self.html {
+            (Restore::Html { html, alt: self.text.as_deref() }, &["HTML", "text"])
+        } else if let Some(text) = &self.text {
+            (Restore::Text(text), &["text"])
+        } else if let Some(image) = &self.image {
+            (Restore::Image(image), &["image"])
+        } else {
+            (Restore::Clear, &[])
+        };
+        let lost = captured.iter()
+            .filter(|(name, present)| *present && !written.contains(name))
+            .map(|(name, _)| *name)
+            .collect();
+        (restore, lost)
+    }
+
+    /// Put the snapshot back in a single write. Formats arboard can't write together with
+    /// the rest are logged and left out (see `plan`).
+    pub fn restore(&self, clip: &mut Clipboard) -> Result<()> {
+        let (restore, lost) = self.plan();
+        if !lost.is_empty() {
+            tracing::warn!("Clipboard restore leaves out {}: they can't be written together with the rest", lost.join(", "));
+        }
+        match restore {
+            Restore::Files(files) => clip.set().file_list(files)?,
+            Restore::Html { html, alt } => clip.set_html(html, alt)?,
+            Restore::Text(text) => clip.set_text(text)?,
+            Restore::Image(image) => clip.set_image(image.clone())?,
+            Restore::Clear => clip.clear()?,
+        }
+        Ok(())
+    }
+}
+
+/// A single clipboard write.
+#[derive(Debug)]
+enum Restore<'a> {
+    Files(&'a [PathBuf]),
+    Html { html: &'a str, alt: Option<&'a str> },
+    Text(&'a str),
+    Image(&'a ImageData<'static>),
+    Clear,
+}
+
+#[cfg(target_os = "macos")]
 extern "C" {
     fn AXIsProcessTrustedWithOptions(options: *const std::ffi::c_void) -> bool;
 }
 
 /// Check if the app has Accessibility permission.
+#[cfg(target_os = "macos")]
 pub fn check_accessibility() -> bool {
     unsafe { AXIsProcessTrustedWithOptions(std::ptr::null()) }
 }
 
+/// Check if the session's input backend (XTest or uinput) can send key events.
+#[cfg(target_os = "linux")]
+pub fn check_accessibility() -> bool {
+    super::linux::check_input()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn check_accessibility() -> bool {
+    false
+}
+
 /// Simulate Cmd+V keystroke using CGEvent API directly.
+#[cfg(target_os = "macos")]
 fn simulate_paste() -> Result<()> {
     use core_graphics::event::{CGEvent, CGEventFlags, CGKeyCode};
     use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
@@ -36,24 +160,147 @@ fn simulate_paste() -> Result<()> {
     Ok(())
 }
 
+#[cfg(target_os = "linux")]
+fn simulate_paste() -> Result<()> {
+    super::linux::simulate_paste()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn simulate_paste() -> Result<()> {
+    anyhow::bail!("Paste simulation is not supported on this platform")
+}
+
+/// Poll `done` until it returns true or `timeout` elapses. Returns whether it completed.
+pub(crate) fn wait_until(timeout: Duration, mut done: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if done() { return true; }
+        if Instant::now() >= deadline { return false; }
+        thread::sleep(Duration::from_millis(15));
+    }
+}
+
+/// Wait until the paste has been consumed, i.e. the focused element's value moved away from `before`.
+fn wait_for_paste(focused: Option<&FocusedText>, before: Option<String>) {
+    let (Some(focused), Some(before)) = (focused, before) else {
+        thread::sleep(PASTE_SETTLE);
+        return;
+    };
+    let landed = wait_until(PASTE_TIMEOUT, || focused.value().is_none_or(|v| v != before));
+    if !landed {
+        tracing::warn!("Paste not observed within {:?}, restoring clipboard anyway", PASTE_TIMEOUT);
+    }
+}
+
 /// Inject text at cursor via clipboard paste simulation.
+/// The previous clipboard contents are restored once the paste has landed; a failed
+/// restore doesn't fail the paste, it is logged and left for [`take_restore_error`].
 pub fn inject_text(text: &str) -> Result<()> {
     if !check_accessibility() {
+        #[cfg(target_os = "linux")]
+        anyhow::bail!("No usable input backend (XTest or /dev/uinput) — paste will fail");
+        #[cfg(not(target_os = "linux"))]
         anyhow::bail!("Accessibility permission not granted — paste will fail");
     }
 
     let mut clip = Clipboard::new()?;
-    let original = clip.get_text().ok();
+    let snapshot = ClipboardSnapshot::capture(&mut clip);
 
     clip.set_text(text)?;
     thread::sleep(Duration::from_millis(50));
 
-    simulate_paste()?;
+    // Looked up once; each poll then only re-reads this element's value
+    let focused = FocusedText::find();
+    let before = focused.as_ref().and_then(FocusedText::value);
+    let pasted = simulate_paste();
+    if pasted.is_ok() {
+        wait_for_paste(focused.as_ref(), before);
+    }
 
-    thread::sleep(Duration::from_millis(100));
-    if let Some(orig) = original {
-        let _ = clip.set_text(&orig);
+    if let Err(e) = snapshot.restore(&mut clip) {
+        let error = RestoreError(e.to_string());
+        tracing::warn!("{}", error);
+        if let Ok(mut slot) = RESTORE_ERROR.lock() {
+            *slot = Some(error);
+        }
     }
+    pasted
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_until_returns_when_done() {
+        let mut polls = 0;
+        let done = wait_until(Duration::from_secs(1), || { polls += 1; polls == 3 });
+        assert!(done);
+        assert_eq!(polls, 3);
+    }
+
+    #[test]
+    fn wait_until_times_out() {
+        let start = Instant::now();
+        assert!(!wait_until(Duration::from_millis(50), || false));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn empty_snapshot() {
+        assert!(ClipboardSnapshot::default().is_empty());
+        let s = ClipboardSnapshot { html: Some("<b>hi</b>".into()), ..Default::default() };
+        assert!(!s.is_empty());
+    }
+
+    fn image() -> ImageData<'static> {
+        ImageData { width: 1, height: 1, bytes: vec![0u8; 4].into() }
+    }
+
+    #[test]
+    fn restore_writes_html_with_its_text() {
+        let s = ClipboardSnapshot { html: Some("<b>hi</b>".into()), text: Some("hi".into()), ..Default::default() };
+        let (restore, lost) = s.plan();
+        assert!(matches!(restore, Restore::Html { html: "<b>hi</b>", alt: Some("hi") }));
+        assert!(lost.is_empty());
+        assert!(matches!(ClipboardSnapshot::default().plan().0, Restore::Clear));
+    }
+
+    #[test]
+    fn restore_keeps_text_over_image() {
+        // arboard can't write an image and text in one set; the text survives
+        let s = ClipboardSnapshot { text: Some("A1\tB1".into()), image: Some(image()), ..Default::default() };
+        let (restore, lost) = s.plan();
+        assert!(matches!(restore, Restore::Text("A1\tB1")));
+        assert_eq!(lost, vec!["image"]);
+
+        let s = ClipboardSnapshot { image: Some(image()), ..Default::default() };
+        assert!(matches!(s.plan(), (Restore::Image(_), lost) if lost.is_empty()));
+    }
+
+    #[test]
+    fn restore_files_alone() {
+        let s = ClipboardSnapshot {
+            files: Some(vec!["/tmp/a.txt".into()]),
+            text: Some("a.txt".into()),
+            ..Default::default()
+        };
+        let (restore, lost) = s.plan();
+        assert!(matches!(restore, Restore::Files([f]) if f.as_path() == std::path::Path::new("/tmp/a.txt")));
+        assert_eq!(lost, vec!["text"]);
+    }
+
+    #[test]
+    fn restore_error_message() {
+        let e: anyhow::Error = RestoreError("busy".into()).into();
+        assert!(e.downcast_ref::<RestoreError>().is_some());
+        assert!(e.to_string().contains("restore clipboard"));
+    }
+
+    #[test]
+    fn restore_error_is_taken_once() {
+        *RESTORE_ERROR.lock().unwrap() = Some(RestoreError("busy".into()));
+        assert_eq!(take_restore_error().unwrap().0, "busy");
+        assert!(take_restore_error().is_none());
+    }
 }
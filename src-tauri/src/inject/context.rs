@@ -23,9 +23,39 @@ impl Default for AppContext {
     }
 }
 
+/// The focused app and window, looked up cheaply. The text around the cursor can take an
+/// accessibility walk or a terminal query to read, so it waits for `with_text_context`.
+pub struct ActiveApp {
+    /// Everything but `selected_text`.
+    pub ctx: AppContext,
+    #[cfg(target_os = "linux")]
+    window: Option<super::active_window::ActiveWindow>,
+}
+
+/// The active application with the text around the cursor.
+pub fn get_active_app() -> AppContext {
+    ActiveApp::detect().with_text_context()
+}
+
 /// Detect the active application on macOS.
 #[cfg(target_os = "macos")]
-pub fn get_active_app() -> AppContext {
+impl ActiveApp {
+    pub fn detect() -> Self {
+        let (app_name, bundle_id) = frontmost_app();
+        let window_title = get_ax_title();
+        let (category, tone) = classify(&bundle_id, &app_name, &window_title);
+        Self { ctx: AppContext { app_name, bundle_id, category, tone, window_title, selected_text: String::new() } }
+    }
+
+    pub fn with_text_context(self) -> AppContext {
+        let (title, selected_text) = get_ax_context();
+        let window_title = if self.ctx.window_title.is_empty() { title } else { self.ctx.window_title };
+        AppContext { window_title, selected_text, ..self.ctx }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn frontmost_app() -> (String, String) {
     use std::process::Command;
     // Use osascript to get frontmost app info
     let output = Command::new("osascript")
@@ -44,14 +74,7 @@ pub fn get_active_app() -> AppContext {
         }
         Err(_) => ("Unknown".into(), String::new()),
     };
-
-    let category = categorize_app(&bundle_id);
-    let tone = tone_for_category(&category);
-
-    // Get window title and selected text via Accessibility API
-    let (window_title, selected_text) = get_ax_context();
-
-    AppContext { app_name, bundle_id, category, tone, window_title, selected_text }
+    (app_name, bundle_id)
 }
 
 #[cfg(target_os = "macos")]
@@ -87,6 +110,41 @@ fn get_terminal_content() -> Option<String> {
     if s.is_empty() { None } else { Some(s) }
 }
 
+/// Title of the focused window.
+#[cfg(target_os = "macos")]
+fn get_ax_title() -> String {
+    use core_foundation::base::TCFType;
+    use core_foundation::string::CFString;
+
+    unsafe {
+        let sys_wide = AXUIElementCreateSystemWide();
+        let mut focused_app: CFTypeRef = std::ptr::null();
+        let key = CFString::new("AXFocusedApplication");
+        if AXUIElementCopyAttributeValue(sys_wide, key.as_concrete_TypeRef(), &mut focused_app) != 0 || focused_app.is_null() {
+            CFRelease(sys_wide as _);
+            return String::new();
+        }
+
+        let mut focused_window: CFTypeRef = std::ptr::null();
+        let win_key = CFString::new("AXFocusedWindow");
+        let title = if AXUIElementCopyAttributeValue(focused_app as AXUIElementRef, win_key.as_concrete_TypeRef(), &mut focused_window) == 0 && !focused_window.is_null() {
+            let mut title_val: CFTypeRef = std::ptr::null();
+            let title_key = CFString::new("AXTitle");
+            let title = if AXUIElementCopyAttributeValue(focused_window as AXUIElementRef, title_key.as_concrete_TypeRef(), &mut title_val) == 0 && !title_val.is_null() {
+                let s = CFString::wrap_under_get_rule(title_val as _).to_string();
+                CFRelease(title_val);
+                s
+            } else { String::new() };
+            CFRelease(focused_window);
+            title
+        } else { String::new() };
+
+        CFRelease(focused_app);
+        CFRelease(sys_wide as _);
+        title
+    }
+}
+
 #[cfg(target_os = "macos")]
 fn get_ax_text() -> (String, String) {
     use core_foundation::base::TCFType;
@@ -147,8 +205,71 @@ fn get_ax_text() -> (String, String) {
     }
 }
 
+/// The focused text element, held so its value can be re-read cheaply while waiting for a
+/// paste to land.
+#[cfg(target_os = "macos")]
+pub(crate) struct FocusedText(AXUIElementRef);
+
+#[cfg(target_os = "macos")]
+impl FocusedText {
+    pub(crate) fn find() -> Option<Self> {
+        use core_foundation::base::TCFType;
+        use core_foundation::string::CFString;
+
+        unsafe {
+            let sys_wide = AXUIElementCreateSystemWide();
+            let mut focused_elem: CFTypeRef = std::ptr::null();
+            let elem_key = CFString::new("AXFocusedUIElement");
+            let ok = AXUIElementCopyAttributeValue(sys_wide, elem_key.as_concrete_TypeRef(), &mut focused_elem) == 0;
+            CFRelease(sys_wide as _);
+            (ok && !focused_elem.is_null()).then(|| Self(focused_elem as AXUIElementRef))
+        }
+    }
+
+    /// Current value of the element.
+    pub(crate) fn value(&self) -> Option<String> {
+        use core_foundation::base::{CFType, TCFType};
+        use core_foundation::string::CFString;
+
+        unsafe {
+            let mut val: CFTypeRef = std::ptr::null();
+            let val_key = CFString::new("AXValue");
+            if AXUIElementCopyAttributeValue(self.0, val_key.as_concrete_TypeRef(), &mut val) == 0 && !val.is_null() {
+                // AXValue is not always a string (sliders, checkboxes)
+                CFType::wrap_under_create_rule(val).downcast_into::<CFString>().map(|s| s.to_string())
+            } else { None }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for FocusedText {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0 as _) }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use super::atspi::FocusedText;
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub(crate) struct FocusedText;
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+impl FocusedText {
+    pub(crate) fn find() -> Option<Self> {
+        None
+    }
+
+    pub(crate) fn value(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Last `max` characters of `s` — terminal output is full of multi-byte box drawing and prompts.
 pub(crate) fn tail_chars(s: &str, max: usize) -> String {
-    if s.len() <= max { s.to_string() } else { s[s.len()-max..].to_string() }
+    let len = s.chars().count();
+    s.chars().skip(len.saturating_sub(max)).collect()
 }
 
 #[cfg(target_os = "macos")]
@@ -162,31 +283,119 @@ extern "C" {
 #[cfg(target_os = "macos")]
 type AXUIElementRef = *const std::ffi::c_void;
 
-#[cfg(not(target_os = "macos"))]
-pub fn get_active_app() -> AppContext {
-    AppContext::default()
+/// Detect the focused window on Linux (X11, sway/i3, Hyprland), with text context from
+/// terminal scrollback or AT-SPI.
+#[cfg(target_os = "linux")]
+impl ActiveApp {
+    pub fn detect() -> Self {
+        let Some(win) = super::active_window::active_window() else {
+            return Self { ctx: AppContext::default(), window: None };
+        };
+        let (category, tone) = classify(&win.app_id, &win.app_name, &win.title);
+        let ctx = AppContext {
+            app_name: win.app_name.clone(),
+            bundle_id: win.app_id.clone(),
+            category,
+            tone,
+            window_title: win.title.clone(),
+            selected_text: String::new(),
+        };
+        Self { ctx, window: Some(win) }
+    }
+
+    pub fn with_text_context(self) -> AppContext {
+        let (mut ctx, Some(win)) = (self.ctx, self.window) else {
+            return AppContext::default();
+        };
+        // Terminals rarely expose their buffer over AT-SPI; ask tmux or the emulator instead
+        let terminal_text = if is_terminal_id(&win.app_id) {
+            super::terminal::recent_output(&win).map(|t| tail_chars(&t, 200))
+        } else {
+            None
+        };
+        match terminal_text {
+            Some(text) => ctx.selected_text = text,
+            None => {
+                let (ax_title, ax_text) = super::atspi::get_text_context();
+                // The compositor's title is authoritative; AT-SPI only fills in when it has none
+                if ctx.window_title.is_empty() {
+                    ctx.window_title = ax_title;
+                }
+                ctx.selected_text = ax_text;
+            }
+        }
+        ctx
+    }
 }
 
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+impl ActiveApp {
+    pub fn detect() -> Self {
+        Self { ctx: AppContext::default() }
+    }
+
+    pub fn with_text_context(self) -> AppContext {
+        self.ctx
+    }
+}
+
+/// Category and tone from the rules and tones in the database, falling back to the built-in defaults.
+fn classify(bundle_id: &str, app_name: &str, window_title: &str) -> (String, String) {
+    use crate::db::rules;
+
+    let Ok(conn) = crate::db::schema::init_db(&crate::config::AppConfig::default().db_path) else {
+        let category = categorize_app(bundle_id);
+        let tone = tone_for_category(&category);
+        return (category, tone);
+    };
+    let user_rules = rules::load(&conn).unwrap_or_default();
+    let category = user_rules.find_match(bundle_id, app_name, window_title)
+        .map(|r| r.category.clone())
+        .unwrap_or_else(|| "default".into());
+    let tone = resolve_tone(&conn, bundle_id, &category);
+    (category, tone)
+}
+
+/// Tone for an app: its own entry in `app_tones`, then the category's tone, then the built-in one.
+pub(crate) fn resolve_tone(conn: &rusqlite::Connection, bundle_id: &str, category: &str) -> String {
+    let per_app = if bundle_id.is_empty() {
+        None
+    } else {
+        crate::db::tones::get_tone(conn, bundle_id).ok().flatten()
+    };
+    per_app
+        .or_else(|| crate::db::rules::category_tone(conn, category).ok().flatten())
+        .unwrap_or_else(|| tone_for_category(category))
+}
+
+/// Category from the built-in rules by bundle id alone, for when the database is unavailable.
 pub(crate) fn categorize_app(bundle_id: &str) -> String {
-    match bundle_id {
-        b if b.contains("mail") || b.contains("Outlook") => "email",
-        b if b.contains("slack") => "slack",
-        b if b.contains("VSCode") || b.contains("Xcode") => "code",
-        b if b.contains("notion") => "notes",
-        b if b.contains("Terminal") || b.contains("iTerm") => "terminal",
-        _ => "default",
-    }.into()
+    crate::db::rules::RuleSet::new(crate::db::rules::default_rules())
+        .find_match(bundle_id, "", "")
+        .map(|r| r.category.clone())
+        .unwrap_or_else(|| "default".into())
 }
 
+/// Linux terminal emulators, matched on app_id / WM_CLASS. Unlike the category this
+/// can't be remapped by user rules, so it decides whether paste needs Ctrl+Shift+V.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_terminal_id(id: &str) -> bool {
+    const TERMINALS: &[&str] = &[
+        "terminal", "konsole", "alacritty", "kitty", "wezterm", "foot",
+        "xterm", "urxvt", "tilix", "terminator", "st-256color", "ghostty",
+    ];
+    let lower = id.to_lowercase();
+    TERMINALS.iter().any(|t| lower.contains(t))
+}
+
+/// Built-in tone for a category, used when `category_tones` has no row for it.
 pub(crate) fn tone_for_category(category: &str) -> String {
-    match category {
-        "email" => "Professional, concise.",
-        "slack" => "Casual, conversational.",
-        "code" => "Technical. Use backticks for code references.",
-        "terminal" => "Command-like. Be terse.",
-        "notes" => "Structured. Use headers and lists where appropriate.",
-        _ => "Natural, clear prose.",
-    }.into()
+    use crate::db::rules::DEFAULT_TONES;
+    DEFAULT_TONES.iter()
+        .find(|(c, _)| *c == category)
+        .or_else(|| DEFAULT_TONES.iter().find(|(c, _)| *c == "default"))
+        .map(|(_, tone)| tone.to_string())
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -206,6 +415,27 @@ mod tests {
         assert_eq!(categorize_app("notion.id"), "notes");
     }
 
+    #[test]
+    fn categorize_linux_apps() {
+        assert_eq!(categorize_app("Slack"), "slack");
+        assert_eq!(categorize_app("Code"), "code");
+        assert_eq!(categorize_app("jetbrains-idea"), "code");
+        assert_eq!(categorize_app("thunderbird"), "email");
+        assert_eq!(categorize_app("org.gnome.Terminal"), "terminal");
+        assert_eq!(categorize_app("Alacritty"), "terminal");
+        assert_eq!(categorize_app("org.wezfurlong.wezterm"), "terminal");
+        assert_eq!(categorize_app("firefox"), "default");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn terminal_ids() {
+        assert!(is_terminal_id("Gnome-terminal"));
+        assert!(is_terminal_id("kitty"));
+        assert!(!is_terminal_id("firefox"));
+        assert!(!is_terminal_id("Slack"));
+    }
+
     #[test]
     fn categorize_unknown_app() {
         assert_eq!(categorize_app("com.random.app"), "default");
@@ -224,6 +454,20 @@ mod tests {
         assert!(tone_for_category("unknown").contains("Natural"));
     }
 
+    #[test]
+    fn tone_resolution_order() {
+        use crate::db::{rules, schema, tones};
+        let conn = schema::init_db(std::path::Path::new(":memory:")).unwrap();
+        tones::set_tone(&conn, "com.tinyspeck.slackmacgap", "Slack", "slack", "Lowercase, no punctuation.").unwrap();
+        rules::set_category_tone(&conn, "email", "Formal. Sign off with Regards.").unwrap();
+        conn.execute("DELETE FROM category_tones WHERE category = 'code'", []).unwrap();
+
+        assert_eq!(resolve_tone(&conn, "com.tinyspeck.slackmacgap", "slack"), "Lowercase, no punctuation.");
+        assert_eq!(resolve_tone(&conn, "com.microsoft.Outlook", "email"), "Formal. Sign off with Regards.");
+        assert_eq!(resolve_tone(&conn, "com.microsoft.VSCode", "code"), tone_for_category("code"));
+        assert_eq!(resolve_tone(&conn, "", "unknown"), tone_for_category("default"));
+    }
+
     // --- tail_chars ---
     #[test]
     fn tail_chars_short_string() {
@@ -245,6 +489,12 @@ mod tests {
         assert_eq!(tail_chars("", 10), "");
     }
 
+    #[test]
+    fn tail_chars_multibyte() {
+        assert_eq!(tail_chars("──╯ λ ❯ cargo", 7), "❯ cargo");
+        assert_eq!(tail_chars("héllo", 0), "");
+    }
+
     // --- AppContext default ---
     #[test]
     fn app_context_default() {
@@ -0,0 +1,178 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::db::settings;
+
+/// Delivers finished text to the focused app.
+pub trait Injector: Send + Sync {
+    fn inject(&self, text: &str) -> Result<()>;
+
+    /// Whether text can go out a piece at a time as it is produced. A paste waits for the
+    /// whole text so it lands in one go.
+    fn incremental(&self) -> bool {
+        false
+    }
+}
+
+/// Paste via the clipboard and a simulated paste shortcut.
+pub struct ClipboardInjector;
+
+impl Injector for ClipboardInjector {
+    fn inject(&self, text: &str) -> Result<()> {
+        super::clipboard::inject_text(text)
+    }
+}
+
+/// Type each character as a synthetic keystroke.
+pub struct KeystrokeInjector;
+
+impl Injector for KeystrokeInjector {
+    fn inject(&self, text: &str) -> Result<()> {
+        super::keystroke::type_text(text)
+    }
+
+    fn incremental(&self) -> bool {
+        true
+    }
+}
+
+/// Records text instead of sending it anywhere — for dry runs and tests.
+#[derive(Default)]
+pub struct RecordingInjector {
+    injected: Mutex<Vec<String>>,
+    incremental: bool,
+}
+
+impl RecordingInjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records like a typing injector, accepting text a piece at a time.
+    pub fn typing() -> Self {
+        Self { incremental: true, ..Self::default() }
+    }
+
+    pub fn injected(&self) -> Vec<String> {
+        self.injected.lock().unwrap().clone()
+    }
+}
+
+impl Injector for RecordingInjector {
+    fn inject(&self, text: &str) -> Result<()> {
+        tracing::info!("Dry run, not injecting: {:?}", text);
+        self.injected.lock().unwrap().push(text.to_string());
+        Ok(())
+    }
+
+    fn incremental(&self) -> bool {
+        self.incremental
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InjectStrategy {
+    Clipboard,
+    Keystroke,
+    DryRun,
+}
+
+impl InjectStrategy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "clipboard" => Some(Self::Clipboard),
+            "keystroke" => Some(Self::Keystroke),
+            "dry_run" => Some(Self::DryRun),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Clipboard => "clipboard",
+            Self::Keystroke => "keystroke",
+            Self::DryRun => "dry_run",
+        }
+    }
+
+    pub fn injector(self) -> Box<dyn Injector> {
+        match self {
+            Self::Clipboard => Box::new(ClipboardInjector),
+            Self::Keystroke => Box::new(KeystrokeInjector),
+            Self::DryRun => Box::new(RecordingInjector::new()),
+        }
+    }
+}
+
+fn setting_key(category: &str) -> String {
+    format!("inject_strategy_{}", category)
+}
+
+/// Strategy configured for an app category (as assigned by the app rules). Defaults to clipboard paste.
+pub fn strategy_for_category(conn: &Connection, category: &str) -> InjectStrategy {
+    settings::get(conn, &setting_key(category)).ok().flatten()
+        .and_then(|s| InjectStrategy::parse(&s))
+        .unwrap_or(InjectStrategy::Clipboard)
+}
+
+pub fn set_strategy(conn: &Connection, category: &str, strategy: InjectStrategy) -> Result<()> {
+    settings::set(conn, &setting_key(category), strategy.as_str())
+}
+
+/// Injector for the given app category, read from the settings DB.
+pub fn for_category(category: &str) -> Box<dyn Injector> {
+    let strategy = crate::db::schema::init_db(&crate::config::AppConfig::default().db_path).ok()
+        .map(|c| strategy_for_category(&c, category))
+        .unwrap_or(InjectStrategy::Clipboard);
+    strategy.injector()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn test_db() -> Connection {
+        schema::init_db(std::path::Path::new(":memory:")).unwrap()
+    }
+
+    #[test]
+    fn recording_injector_records_in_order() {
+        let inj = RecordingInjector::new();
+        inj.inject("hello").unwrap();
+        inj.inject("\n\n").unwrap();
+        assert_eq!(inj.injected(), vec!["hello".to_string(), "\n\n".to_string()]);
+    }
+
+    #[test]
+    fn strategy_roundtrip() {
+        for s in [InjectStrategy::Clipboard, InjectStrategy::Keystroke, InjectStrategy::DryRun] {
+            assert_eq!(InjectStrategy::parse(s.as_str()), Some(s));
+        }
+        assert_eq!(InjectStrategy::parse("bogus"), None);
+    }
+
+    #[test]
+    fn strategy_defaults_to_clipboard() {
+        let conn = test_db();
+        assert_eq!(strategy_for_category(&conn, "terminal"), InjectStrategy::Clipboard);
+    }
+
+    #[test]
+    fn strategy_per_category() {
+        let conn = test_db();
+        set_strategy(&conn, "terminal", InjectStrategy::Keystroke).unwrap();
+        assert_eq!(strategy_for_category(&conn, "terminal"), InjectStrategy::Keystroke);
+        assert_eq!(strategy_for_category(&conn, "slack"), InjectStrategy::Clipboard);
+    }
+
+    #[test]
+    fn invalid_stored_strategy_falls_back() {
+        let conn = test_db();
+        settings::set(&conn, "inject_strategy_email", "telepathy").unwrap();
+        assert_eq!(strategy_for_category(&conn, "email"), InjectStrategy::Clipboard);
+    }
+}
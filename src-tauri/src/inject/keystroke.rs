@@ -0,0 +1,59 @@
+use anyhow::Result;
+
+/// Type text as synthetic keystrokes, one character at a time.
+/// Leaves the clipboard alone and works in apps that block paste.
+pub fn type_text(text: &str) -> Result<()> {
+    if !super::clipboard::check_accessibility() {
+        anyhow::bail!("Input permission not granted — typing will fail");
+    }
+    platform_type(text)
+}
+
+/// Post a keyboard event per character carrying the character as its Unicode string,
+/// so the result doesn't depend on the active keyboard layout.
+#[cfg(target_os = "macos")]
+fn platform_type(text: &str) -> Result<()> {
+    use core_graphics::event::{CGEvent, CGEventTapLocation, CGKeyCode};
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+    use std::thread;
+    use std::time::Duration;
+
+    const KEY_RETURN: CGKeyCode = 36;
+    const KEY_TAB: CGKeyCode = 48;
+
+    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|_| anyhow::anyhow!("Failed to create CGEventSource"))?;
+
+    for ch in text.chars() {
+        let keycode = match ch {
+            '\n' => KEY_RETURN,
+            '\t' => KEY_TAB,
+            _ => 0,
+        };
+        let key_down = CGEvent::new_keyboard_event(source.clone(), keycode, true)
+            .map_err(|_| anyhow::anyhow!("Failed to create key down event"))?;
+        let key_up = CGEvent::new_keyboard_event(source.clone(), keycode, false)
+            .map_err(|_| anyhow::anyhow!("Failed to create key up event"))?;
+        if keycode == 0 {
+            let mut buf = [0u16; 2];
+            let utf16 = ch.encode_utf16(&mut buf);
+            key_down.set_string_from_utf16_unchecked(utf16);
+            key_up.set_string_from_utf16_unchecked(utf16);
+        }
+        key_down.post(CGEventTapLocation::HID);
+        key_up.post(CGEventTapLocation::HID);
+        // Some apps drop events posted faster than they drain their queue
+        thread::sleep(Duration::from_millis(2));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn platform_type(text: &str) -> Result<()> {
+    super::linux::type_text(text)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn platform_type(_text: &str) -> Result<()> {
+    anyhow::bail!("Keystroke typing is not supported on this platform")
+}
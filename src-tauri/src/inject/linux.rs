@@ -0,0 +1,430 @@
+use anyhow::Result;
+use serde::Serialize;
+
+/// Input backend used to synthesize the paste shortcut on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Backend {
+    /// XTest key events on the X server.
+    X11,
+    /// A uinput virtual keyboard, picked up by the Wayland compositor like real hardware.
+    Wayland,
+}
+
+/// Pick the input backend from the current session type.
+pub fn session_backend() -> Option<Backend> {
+    backend_from_env(
+        std::env::var("XDG_SESSION_TYPE").ok().as_deref(),
+        std::env::var_os("WAYLAND_DISPLAY").is_some(),
+        std::env::var_os("DISPLAY").is_some(),
+    )
+}
+
+pub(crate) fn backend_from_env(session_type: Option<&str>, wayland_display: bool, x_display: bool) -> Option<Backend> {
+    match session_type {
+        Some("wayland") => Some(Backend::Wayland),
+        Some("x11") => Some(Backend::X11),
+        // tty / unset session type: fall back to whichever display is reachable
+        _ if wayland_display => Some(Backend::Wayland),
+        _ if x_display => Some(Backend::X11),
+        _ => None,
+    }
+}
+
+/// Check whether the session's backend can actually send input.
+pub fn check_input() -> bool {
+    match session_backend() {
+        Some(Backend::X11) => x11::available(),
+        Some(Backend::Wayland) => uinput::available(),
+        None => false,
+    }
+}
+
+/// Simulate the paste shortcut: Ctrl+V, or Ctrl+Shift+V when a terminal has focus.
+pub fn simulate_paste() -> Result<()> {
+    let terminal = super::active_window::active_window()
+        .is_some_and(|w| super::context::is_terminal_id(&w.app_id));
+    match session_backend() {
+        Some(Backend::X11) => x11::paste(terminal),
+        Some(Backend::Wayland) => uinput::paste(terminal),
+        None => anyhow::bail!("No X11 or Wayland session detected"),
+    }
+}
+
+/// Type `text` as individual key events. On Wayland the virtual keyboard only has US keys,
+/// so words it can't type, or all the text on another layout, are pasted instead.
+pub fn type_text(text: &str) -> Result<()> {
+    match session_backend() {
+        Some(Backend::X11) => x11::type_text(text),
+        Some(Backend::Wayland) if us_layout() => {
+            for run in key_runs(text) {
+                match run {
+                    Run::Keys(keys) => uinput::type_text(keys)?,
+                    Run::Paste(pasted) => super::clipboard::inject_text(pasted)?,
+                }
+            }
+            Ok(())
+        }
+        Some(Backend::Wayland) => super::clipboard::inject_text(text),
+        None => anyhow::bail!("No X11 or Wayland session detected"),
+    }
+}
+
+/// A piece of text and how it goes out.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Run<'a> {
+    Keys(&'a str),
+    Paste(&'a str),
+}
+
+/// Split `text` into runs the US virtual keyboard can type and runs to paste. A word with
+/// any character `us_key` can't map is pasted whole, with the whitespace after it, so a
+/// paste never lands mid-word; neighbouring words of the same kind share a run.
+pub(crate) fn key_runs(text: &str) -> Vec<Run<'_>> {
+    let mut runs = Vec::new();
+    let (mut start, mut typeable) = (0, true);
+    let mut offset = 0;
+    for word in text.split_inclusive(char::is_whitespace) {
+        let word_typeable = word.chars().all(|c| us_key(c).is_some());
+        if word_typeable != typeable && offset > start {
+            let run = &text[start..offset];
+            runs.push(if typeable { Run::Keys(run) } else { Run::Paste(run) });
+            start = offset;
+        }
+        typeable = word_typeable;
+        offset += word.len();
+    }
+    if offset > start {
+        let run = &text[start..offset];
+        runs.push(if typeable { Run::Keys(run) } else { Run::Paste(run) });
+    }
+    runs
+}
+
+/// Whether the keyboard layout is US, so `us_key` codes type what they mean. Taken from
+/// `XKB_DEFAULT_LAYOUT` or systemd's keymap; assumed when neither says.
+fn us_layout() -> bool {
+    static US: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *US.get_or_init(|| {
+        let layout = std::env::var("XKB_DEFAULT_LAYOUT").ok()
+            .or_else(|| {
+                let out = std::process::Command::new("localectl").arg("status").output().ok()?;
+                localectl_layout(&String::from_utf8_lossy(&out.stdout))
+            });
+        match layout {
+            Some(layout) => is_us_layout(&layout),
+            None => true,
+        }
+    })
+}
+
+/// First layout of a comma-separated XKB list is the one active at login.
+pub(crate) fn is_us_layout(layouts: &str) -> bool {
+    layouts.split(',').next().map(str::trim) == Some("us")
+}
+
+/// `X11 Layout: de,us` from `localectl status`.
+pub(crate) fn localectl_layout(status: &str) -> Option<String> {
+    status.lines()
+        .find_map(|l| l.trim().strip_prefix("X11 Layout:"))
+        .map(|l| l.trim().to_string())
+}
+
+/// X keysym for a character: Latin-1 maps directly, everything else uses the Unicode range.
+pub(crate) fn keysym_for_char(ch: char) -> u32 {
+    match ch {
+        '\n' => 0xff0d, // Return
+        '\t' => 0xff09, // Tab
+        ' '..='~' | '\u{a0}'..='\u{ff}' => ch as u32,
+        _ => 0x0100_0000 + ch as u32,
+    }
+}
+
+/// Evdev key and shift state for a character on a US layout. The uinput keyboard has no
+/// keymap of its own, so the compositor interprets these codes with the user's layout.
+pub(crate) fn us_key(ch: char) -> Option<(evdev::Key, bool)> {
+    use evdev::Key;
+    const LETTERS: [Key; 26] = [
+        Key::KEY_A, Key::KEY_B, Key::KEY_C, Key::KEY_D, Key::KEY_E, Key::KEY_F, Key::KEY_G,
+        Key::KEY_H, Key::KEY_I, Key::KEY_J, Key::KEY_K, Key::KEY_L, Key::KEY_M, Key::KEY_N,
+        Key::KEY_O, Key::KEY_P, Key::KEY_Q, Key::KEY_R, Key::KEY_S, Key::KEY_T, Key::KEY_U,
+        Key::KEY_V, Key::KEY_W, Key::KEY_X, Key::KEY_Y, Key::KEY_Z,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::KEY_0, Key::KEY_1, Key::KEY_2, Key::KEY_3, Key::KEY_4,
+        Key::KEY_5, Key::KEY_6, Key::KEY_7, Key::KEY_8, Key::KEY_9,
+    ];
+    const SHIFTED_DIGITS: &str = ")!@#$%^&*(";
+
+    if ch.is_ascii_lowercase() {
+        return Some((LETTERS[(ch as u8 - b'a') as usize], false));
+    }
+    if ch.is_ascii_uppercase() {
+        return Some((LETTERS[(ch as u8 - b'A') as usize], true));
+    }
+    if ch.is_ascii_digit() {
+        return Some((DIGITS[(ch as u8 - b'0') as usize], false));
+    }
+    if let Some(i) = SHIFTED_DIGITS.find(ch) {
+        return Some((DIGITS[i], true));
+    }
+    let key = match ch {
+        ' ' => (Key::KEY_SPACE, false),
+        '\n' => (Key::KEY_ENTER, false),
+        '\t' => (Key::KEY_TAB, false),
+        '-' => (Key::KEY_MINUS, false),
+        '_' => (Key::KEY_MINUS, true),
+        '=' => (Key::KEY_EQUAL, false),
+        '+' => (Key::KEY_EQUAL, true),
+        '[' => (Key::KEY_LEFTBRACE, false),
+        '{' => (Key::KEY_LEFTBRACE, true),
+        ']' => (Key::KEY_RIGHTBRACE, false),
+        '}' => (Key::KEY_RIGHTBRACE, true),
+        '\\' => (Key::KEY_BACKSLASH, false),
+        '|' => (Key::KEY_BACKSLASH, true),
+        ';' => (Key::KEY_SEMICOLON, false),
+        ':' => (Key::KEY_SEMICOLON, true),
+        '\'' => (Key::KEY_APOSTROPHE, false),
+        '"' => (Key::KEY_APOSTROPHE, true),
+        '`' => (Key::KEY_GRAVE, false),
+        '~' => (Key::KEY_GRAVE, true),
+        ',' => (Key::KEY_COMMA, false),
+        '<' => (Key::KEY_COMMA, true),
+        '.' => (Key::KEY_DOT, false),
+        '>' => (Key::KEY_DOT, true),
+        '/' => (Key::KEY_SLASH, false),
+        '?' => (Key::KEY_SLASH, true),
+        _ => return None,
+    };
+    Some(key)
+}
+
+mod x11 {
+    use anyhow::Result;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{ConnectionExt as _, KEY_PRESS_EVENT, KEY_RELEASE_EVENT};
+    use x11rb::protocol::xtest::ConnectionExt as _;
+
+    const XK_CONTROL_L: u32 = 0xffe3;
+    const XK_SHIFT_L: u32 = 0xffe1;
+    const XK_V: u32 = 0x0076;
+
+    pub fn available() -> bool {
+        match x11rb::connect(None) {
+            Ok((conn, _)) => conn.xtest_get_version(2, 2).ok().and_then(|c| c.reply().ok()).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    pub fn paste(terminal: bool) -> Result<()> {
+        let (conn, _) = x11rb::connect(None)?;
+        let ctrl = keycode_for(&conn, XK_CONTROL_L)?;
+        let shift = keycode_for(&conn, XK_SHIFT_L)?;
+        let v = keycode_for(&conn, XK_V)?;
+
+        let mut keys = vec![ctrl];
+        if terminal { keys.push(shift); }
+        keys.push(v);
+
+        for &k in &keys {
+            conn.xtest_fake_input(KEY_PRESS_EVENT, k, x11rb::CURRENT_TIME, x11rb::NONE, 0, 0, 0)?;
+        }
+        for &k in keys.iter().rev() {
+            conn.xtest_fake_input(KEY_RELEASE_EVENT, k, x11rb::CURRENT_TIME, x11rb::NONE, 0, 0, 0)?;
+        }
+        // Round-trip so the events are processed before the clipboard is restored
+        conn.get_input_focus()?.reply()?;
+        Ok(())
+    }
+
+    pub fn type_text(text: &str) -> Result<()> {
+        let (conn, _) = x11rb::connect(None)?;
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let map = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+        let per = map.keysyms_per_keycode.max(1) as usize;
+        let shift = keycode_for(&conn, XK_SHIFT_L)?;
+        // A keycode with no symbols, temporarily bound to characters the layout lacks
+        let spare = map.keysyms.chunks(per)
+            .position(|syms| syms.iter().all(|&s| s == 0))
+            .map(|i| min + i as u8);
+        let mut spare_used = false;
+
+        for ch in text.chars() {
+            let sym = super::keysym_for_char(ch);
+            let found = map.keysyms.chunks(per).enumerate().find_map(|(i, syms)| {
+                syms.iter().take(2).position(|&s| s == sym).map(|level| (min + i as u8, level == 1))
+            });
+            let (code, shifted) = match found {
+                Some(k) => k,
+                None => {
+                    let spare = spare.ok_or_else(|| anyhow::anyhow!("No spare keycode to type {:?}", ch))?;
+                    conn.change_keyboard_mapping(1, spare, per as u8, &vec![sym; per])?;
+                    conn.get_input_focus()?.reply()?;
+                    spare_used = true;
+                    (spare, false)
+                }
+            };
+            if shifted {
+                conn.xtest_fake_input(KEY_PRESS_EVENT, shift, x11rb::CURRENT_TIME, x11rb::NONE, 0, 0, 0)?;
+            }
+            conn.xtest_fake_input(KEY_PRESS_EVENT, code, x11rb::CURRENT_TIME, x11rb::NONE, 0, 0, 0)?;
+            conn.xtest_fake_input(KEY_RELEASE_EVENT, code, x11rb::CURRENT_TIME, x11rb::NONE, 0, 0, 0)?;
+            if shifted {
+                conn.xtest_fake_input(KEY_RELEASE_EVENT, shift, x11rb::CURRENT_TIME, x11rb::NONE, 0, 0, 0)?;
+            }
+            if found.is_none() {
+                // Remapped keycode: make sure the press lands before the next remap
+                conn.get_input_focus()?.reply()?;
+            }
+        }
+
+        if let (true, Some(spare)) = (spare_used, spare) {
+            conn.change_keyboard_mapping(1, spare, per as u8, &vec![0; per])?;
+        }
+        conn.get_input_focus()?.reply()?;
+        Ok(())
+    }
+
+    fn keycode_for(conn: &impl Connection, keysym: u32) -> Result<u8> {
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let map = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+        let per = map.keysyms_per_keycode.max(1) as usize;
+        map.keysyms.chunks(per)
+            .position(|syms| syms.contains(&keysym))
+            .map(|i| min + i as u8)
+            .ok_or_else(|| anyhow::anyhow!("No keycode for keysym {:#x}", keysym))
+    }
+}
+
+mod uinput {
+    use anyhow::Result;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+    use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
+    use evdev::{AttributeSet, EventType, InputEvent, Key};
+
+    /// Created once and kept alive: the compositor needs a moment to pick up a new device.
+    static KEYBOARD: Mutex<Option<VirtualDevice>> = Mutex::new(None);
+
+    pub fn available() -> bool {
+        std::fs::OpenOptions::new().write(true).open("/dev/uinput").is_ok()
+    }
+
+    fn create() -> Result<VirtualDevice> {
+        let mut keys = AttributeSet::<Key>::new();
+        keys.insert(Key::KEY_LEFTCTRL);
+        keys.insert(Key::KEY_LEFTSHIFT);
+        for ch in (' '..='~').chain(['\n', '\t']) {
+            if let Some((k, _)) = super::us_key(ch) { keys.insert(k); }
+        }
+        let device = VirtualDeviceBuilder::new()
+            .map_err(|e| anyhow::anyhow!("Cannot open /dev/uinput: {}", e))?
+            .name("OpenFlow Virtual Keyboard")
+            .with_keys(&keys)?
+            .build()?;
+        thread::sleep(Duration::from_millis(200));
+        Ok(device)
+    }
+
+    fn with_keyboard<T>(f: impl FnOnce(&mut VirtualDevice) -> Result<T>) -> Result<T> {
+        let mut guard = KEYBOARD.lock().unwrap();
+        if guard.is_none() {
+            *guard = Some(create()?);
+        }
+        f(guard.as_mut().unwrap())
+    }
+
+    fn key(device: &mut VirtualDevice, k: Key, down: bool) -> Result<()> {
+        device.emit(&[InputEvent::new(EventType::KEY, k.code(), down as i32)])?;
+        Ok(())
+    }
+
+    pub fn paste(terminal: bool) -> Result<()> {
+        let mut keys = vec![Key::KEY_LEFTCTRL];
+        if terminal { keys.push(Key::KEY_LEFTSHIFT); }
+        keys.push(Key::KEY_V);
+
+        with_keyboard(|device| {
+            for &k in &keys { key(device, k, true)?; }
+            for &k in keys.iter().rev() { key(device, k, false)?; }
+            Ok(())
+        })
+    }
+
+    pub fn type_text(text: &str) -> Result<()> {
+        let strokes = text.chars()
+            .map(|ch| super::us_key(ch).ok_or_else(|| anyhow::anyhow!("Cannot type {:?} with the virtual keyboard", ch)))
+            .collect::<Result<Vec<_>>>()?;
+
+        with_keyboard(|device| {
+            for (k, shifted) in strokes {
+                if shifted { key(device, Key::KEY_LEFTSHIFT, true)?; }
+                key(device, k, true)?;
+                key(device, k, false)?;
+                if shifted { key(device, Key::KEY_LEFTSHIFT, false)?; }
+                // Compositors coalesce events that arrive in the same frame
+                thread::sleep(Duration::from_millis(2));
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_type_wins() {
+        assert_eq!(backend_from_env(Some("wayland"), false, true), Some(Backend::Wayland));
+        assert_eq!(backend_from_env(Some("x11"), true, true), Some(Backend::X11));
+    }
+
+    #[test]
+    fn falls_back_to_display_vars() {
+        assert_eq!(backend_from_env(Some("tty"), true, false), Some(Backend::Wayland));
+        assert_eq!(backend_from_env(None, false, true), Some(Backend::X11));
+        assert_eq!(backend_from_env(None, false, false), None);
+    }
+
+    #[test]
+    fn keysyms_for_chars() {
+        assert_eq!(keysym_for_char('a'), 0x61);
+        assert_eq!(keysym_for_char('é'), 0xe9);
+        assert_eq!(keysym_for_char('\n'), 0xff0d);
+        assert_eq!(keysym_for_char('€'), 0x0100_20ac);
+    }
+
+    #[test]
+    fn us_layout_covers_printable_ascii() {
+        for ch in ' '..='~' {
+            assert!(us_key(ch).is_some(), "no key for {:?}", ch);
+        }
+        assert_eq!(us_key('A'), Some((evdev::Key::KEY_A, true)));
+        assert_eq!(us_key('!'), Some((evdev::Key::KEY_1, true)));
+        assert_eq!(us_key('é'), None);
+    }
+
+    #[test]
+    fn untypeable_words_are_pasted() {
+        assert_eq!(key_runs("Ship it today."), vec![Run::Keys("Ship it today.")]);
+        assert_eq!(key_runs("Meet at the café — it’s near. Ok"), vec![
+            Run::Keys("Meet at the "),
+            Run::Paste("café — it’s "),
+            Run::Keys("near. Ok"),
+        ]);
+        assert_eq!(key_runs("Привет мир"), vec![Run::Paste("Привет мир")]);
+        assert_eq!(key_runs(""), vec![]);
+    }
+
+    #[test]
+    fn reads_keyboard_layout() {
+        let status = "   System Locale: LANG=de_DE.UTF-8\n       X11 Layout: de,us\n        X11 Model: pc105\n";
+        assert_eq!(localectl_layout(status).as_deref(), Some("de,us"));
+        assert!(!is_us_layout("de,us"));
+        assert!(is_us_layout("us,de"));
+        assert_eq!(localectl_layout("System Locale: n/a"), None);
+    }
+}
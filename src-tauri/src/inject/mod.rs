@@ -1,2 +1,12 @@
 pub mod clipboard;
 pub mod context;
+pub mod injector;
+pub mod keystroke;
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(target_os = "linux")]
+pub mod active_window;
+#[cfg(target_os = "linux")]
+pub mod atspi;
+#[cfg(target_os = "linux")]
+pub mod terminal;
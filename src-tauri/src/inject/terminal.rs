@@ -0,0 +1,179 @@
+//! Recent terminal output on Linux: tmux panes first, then kitty and WezTerm remote control.
+
+use serde_json::Value;
+use std::process::Command;
+
+use super::active_window::ActiveWindow;
+
+/// Lines of history pulled from tmux — comfortably more than the 200 chars we keep.
+const TMUX_HISTORY_LINES: &str = "-50";
+
+/// Recent text from the terminal in `win`, if any source can see it.
+pub fn recent_output(win: &ActiveWindow) -> Option<String> {
+    let id = win.app_id.to_lowercase();
+    let text = tmux(win.pid)
+        .or_else(|| if id.contains("kitty") { kitty(win.pid) } else { None })
+        .or_else(|| if id.contains("wezterm") { wezterm() } else { None })?;
+    let trimmed = text.trim_end();
+    if trimmed.is_empty() { None } else { Some(trimmed.to_string()) }
+}
+
+fn run(cmd: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(cmd).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct TmuxClient {
+    pub pid: u32,
+    pub activity: u64,
+    pub pane_id: String,
+}
+
+/// Capture the pane of the tmux client running inside the focused terminal.
+fn tmux(terminal_pid: Option<u32>) -> Option<String> {
+    let listing = run("tmux", &["list-clients", "-F", "#{client_pid} #{client_activity} #{pane_id}"])?;
+    let clients = parse_tmux_clients(&listing);
+    let pane = pick_tmux_client(&clients, terminal_pid, is_descendant)?;
+    run("tmux", &["capture-pane", "-p", "-J", "-t", &pane.pane_id, "-S", TMUX_HISTORY_LINES])
+}
+
+pub(crate) fn parse_tmux_clients(listing: &str) -> Vec<TmuxClient> {
+    listing.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            Some(TmuxClient {
+                pid: parts.next()?.parse().ok()?,
+                activity: parts.next()?.parse().ok()?,
+                pane_id: parts.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Most recently active client running under the focused terminal's `terminal_pid`. Without
+/// the pid there is no telling which client is in that window, and another terminal's or a
+/// detached session's pane must not leak into the prompt, so there is none.
+pub(crate) fn pick_tmux_client(clients: &[TmuxClient], terminal_pid: Option<u32>, descends: impl Fn(u32, u32) -> bool) -> Option<&TmuxClient> {
+    let terminal_pid = terminal_pid?;
+    clients.iter()
+        .filter(|c| descends(c.pid, terminal_pid))
+        .max_by_key(|c| c.activity)
+}
+
+/// Whether `pid` is `ancestor` or runs somewhere beneath it, following /proc parent links.
+pub(crate) fn is_descendant(pid: u32, ancestor: u32) -> bool {
+    let mut current = pid;
+    // Bounded in case /proc changes under us
+    for _ in 0..64 {
+        if current == ancestor {
+            return true;
+        }
+        match parent_pid(current) {
+            Some(parent) if parent > 1 => current = parent,
+            _ => return false,
+        }
+    }
+    false
+}
+
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    parse_ppid(&stat)
+}
+
+/// /proc/<pid>/stat is "pid (comm) state ppid …"; comm may itself contain spaces and parens.
+pub(crate) fn parse_ppid(stat: &str) -> Option<u32> {
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    after_comm.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// kitty answers on the socket from its `listen_on` option, which it suffixes with its pid.
+/// Requires `allow_remote_control` in kitty.conf.
+fn kitty(terminal_pid: Option<u32>) -> Option<String> {
+    let mut sockets: Vec<String> = std::env::var("KITTY_LISTEN_ON").ok()
+        .filter(|s| !s.is_empty())
+        .into_iter()
+        .collect();
+    if let Some(pid) = terminal_pid {
+        sockets.push(format!("unix:/tmp/kitty-{}", pid));
+        sockets.push(format!("unix:@kitty-{}", pid));
+    }
+    sockets.iter().find_map(|to| {
+        run("kitty", &["@", "--to", to, "get-text", "--match", "state:focused", "--extent", "screen"])
+    })
+}
+
+/// `wezterm cli` finds the running GUI on its own; ask which pane the most recently
+/// used client has focused and read it.
+fn wezterm() -> Option<String> {
+    let clients: Value = serde_json::from_str(&run("wezterm", &["cli", "list-clients", "--format", "json"])?).ok()?;
+    let pane = wezterm_focused_pane(&clients)?;
+    run("wezterm", &["cli", "get-text", "--pane-id", &pane.to_string()])
+}
+
+pub(crate) fn wezterm_focused_pane(clients: &Value) -> Option<u64> {
+    let idle = |c: &Value| {
+        let t = &c["idle_time"];
+        (t["secs"].as_u64().unwrap_or(u64::MAX), t["nanos"].as_u64().unwrap_or(0))
+    };
+    clients.as_array()?
+        .iter()
+        .filter(|c| c["focused_pane_id"].is_u64())
+        .min_by_key(|c| idle(c))?
+        ["focused_pane_id"].as_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tmux_client_listing() {
+        let clients = parse_tmux_clients("1200 1700000000 %3\n1300 1700000500 %7\ngarbage\n");
+        assert_eq!(clients.len(), 2);
+        assert_eq!(clients[1], TmuxClient { pid: 1300, activity: 1700000500, pane_id: "%7".into() });
+        // A window without a pid can't be matched to a client, even when every client would do
+        assert!(pick_tmux_client(&clients, None, |_, _| true).is_none());
+    }
+
+    #[test]
+    fn picks_latest_client_of_focused_terminal() {
+        let clients = parse_tmux_clients("1200 1700000900 %3\n1300 1700000500 %7\n1400 1700000600 %9\n");
+        // Client 1200 is the most recent overall but lives in another terminal
+        let pane = pick_tmux_client(&clients, Some(1000), |pid, terminal| terminal == 1000 && pid != 1200).unwrap();
+        assert_eq!(pane.pane_id, "%9");
+        assert!(pick_tmux_client(&clients, Some(1000), |_, _| false).is_none());
+    }
+
+    #[test]
+    fn ppid_from_stat() {
+        assert_eq!(parse_ppid("4321 (bash) S 4000 4321 4321 0"), Some(4000));
+        assert_eq!(parse_ppid("77 (tmux: client (1)) S 12 77 77"), Some(12));
+        assert_eq!(parse_ppid(""), None);
+    }
+
+    #[test]
+    fn own_process_descends_from_parent() {
+        let me = std::process::id();
+        let parent = parent_pid(me).unwrap();
+        assert!(is_descendant(me, me));
+        assert!(is_descendant(me, parent));
+        assert!(!is_descendant(parent, me));
+    }
+
+    #[test]
+    fn wezterm_most_recent_client() {
+        let clients = json!([
+            {"focused_pane_id": 0, "idle_time": {"secs": 120, "nanos": 0}},
+            {"focused_pane_id": 4, "idle_time": {"secs": 1, "nanos": 5}},
+            {"focused_pane_id": null, "idle_time": {"secs": 0, "nanos": 0}}
+        ]);
+        assert_eq!(wezterm_focused_pane(&clients), Some(4));
+        assert_eq!(wezterm_focused_pane(&json!([])), None);
+    }
+}
@@ -8,13 +8,15 @@ pub mod state;
 pub mod config;
 pub mod models;
 
-use asr::engine::AsrEngine;
+use asr::backend::AsrBackend;
 use audio::chunker::Chunker;
 use audio::vad::SileroVad;
 use config::AppConfig;
 use db::{schema, settings};
+use db::models::ModelType;
+use pipeline::captions::{LiveCaptions, LIVE_CAPTIONS};
 use pipeline::orchestrator::{Orchestrator, PipelineEvent, POLISH_ENABLED};
-use polish::engine::PolishEngine;
+use polish::backend::{PolishBackend, PolishChoice};
 use state::AppState;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -32,8 +34,8 @@ static VAD_INIT_FAILED: AtomicBool = AtomicBool::new(false);
 
 struct AppResources {
     state: AppState,
-    asr: Option<Arc<AsrEngine>>,
-    polish: Option<Arc<PolishEngine>>,
+    asr: Option<Arc<dyn AsrBackend>>,
+    polish: Option<Arc<dyn PolishBackend>>,
     stop_tx: Option<mpsc::Sender<()>>,
     config: AppConfig,
     _device_watcher: Option<Box<dyn Send + Sync>>,
@@ -53,6 +55,15 @@ async fn load_on_thread<T: Send + 'static>(f: impl FnOnce() -> anyhow::Result<T>
     result
 }
 
+/// A failed warm-up only costs latency later, so it's logged rather than failing the load.
+fn warm_up(what: &str, f: impl FnOnce() -> anyhow::Result<()>) {
+    let started = std::time::Instant::now();
+    match f() {
+        Ok(()) => tracing::info!("{} warm-up took {:?}", what, started.elapsed()),
+        Err(e) => tracing::warn!("{} warm-up failed: {}", what, e),
+    }
+}
+
 fn launchd_plist_path() -> std::path::PathBuf {
     dirs::home_dir().unwrap().join("Library/LaunchAgents/com.openflow.app.plist")
 }
@@ -87,6 +98,7 @@ fn build_tray_menu(app: &tauri::AppHandle) -> tauri::Result<tauri::menu::Menu<ta
 
     let polish_on = POLISH_ENABLED.load(Ordering::Relaxed);
     let walkie_on = WALKIE_TALKIE.load(Ordering::Relaxed);
+    let captions_on = LIVE_CAPTIONS.load(Ordering::Relaxed);
     let autostart_on = is_autostart_enabled();
 
     let mic_granted = conn.as_ref()
@@ -107,6 +119,8 @@ fn build_tray_menu(app: &tauri::AppHandle) -> tauri::Result<tauri::menu::Menu<ta
         .id("polish").checked(polish_on).build(app)?;
     let walkie_item = CheckMenuItemBuilder::new("Walkie-Talkie")
         .id("walkie").checked(walkie_on).build(app)?;
+    let captions_item = CheckMenuItemBuilder::new("Live Captions")
+        .id("captions").checked(captions_on).build(app)?;
     let autostart_item = CheckMenuItemBuilder::new("Launch at Login")
         .id("autostart").checked(autostart_on).build(app)?;
 
@@ -193,6 +207,7 @@ fn build_tray_menu(app: &tauri::AppHandle) -> tauri::Result<tauri::menu::Menu<ta
         .separator()
         .item(&polish_item)
         .item(&walkie_item)
+        .item(&captions_item)
         .item(&autostart_item)
         .item(&mic_menu)
         .item(&color_menu)
@@ -204,7 +219,14 @@ fn build_tray_menu(app: &tauri::AppHandle) -> tauri::Result<tauri::menu::Menu<ta
 
 fn handle_menu_event(app: &tauri::AppHandle, id: &str) {
     match id {
-        "quit" => { std::process::exit(0); }
+        "quit" => {
+            // exit() runs no destructors, so a managed llama-server is stopped here
+            match app.state::<SharedResources>().try_lock() {
+                Ok(r) => if let Some(polish) = &r.polish { polish.shutdown(); },
+                Err(_) => tracing::warn!("Resources busy at quit; llama-server is cleaned up on next start"),
+            }
+            std::process::exit(0);
+        }
         "show" => { let _ = app.emit("show_window", ()); }
         "pill_color" => {
             let _ = app.emit("show_window", ());
@@ -223,6 +245,14 @@ fn handle_menu_event(app: &tauri::AppHandle, id: &str) {
             }
             let _ = app.emit("walkie_changed", new_val);
         }
+        "captions" => {
+            let new_val = !LIVE_CAPTIONS.load(Ordering::Relaxed);
+            LIVE_CAPTIONS.store(new_val, Ordering::Relaxed);
+            let cfg = AppConfig::default();
+            if let Ok(c) = schema::init_db(&cfg.db_path) {
+                let _ = settings::set(&c, "live_captions", if new_val { "1" } else { "0" });
+            }
+        }
         "autostart" => {
             let now_on = !is_autostart_enabled();
             set_autostart(now_on);
@@ -330,11 +360,14 @@ fn setup_tray(app: &tauri::App) -> tauri::Result<()> {
 #[tauri::command]
 async fn check_models() -> Result<serde_json::Value, String> {
     let config = AppConfig::default();
-    let vad = config.models_dir.join("silero_vad.onnx").exists();
-    let asr = config.models_dir.join("ggml-base.bin").exists()
-           || config.models_dir.join("ggml-small.bin").exists();
-    let llm = config.models_dir.join("qwen2.5-3b-instruct-q4_k_m.gguf").exists();
-    let server = config.models_dir.join(models::download::LLAMA_SERVER_FILENAME).exists();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    let installed = |ty| models::registry::active(&conn, ty).is_some();
+    let vad = installed(ModelType::Vad);
+    let asr = installed(ModelType::Asr);
+    // External LLM backends need neither a local model nor llama-server
+    let managed = matches!(polish::backend::load_choice(&conn), PolishChoice::Managed { .. });
+    let llm = !managed || installed(ModelType::Llm);
+    let server = !managed || config.models_dir.join(models::download::LLAMA_SERVER_FILENAME).exists();
     Ok(serde_json::json!({
         "vad": vad, "asr": asr, "llm": llm, "server": server,
         "models_dir": config.models_dir.to_string_lossy(),
@@ -347,42 +380,176 @@ async fn download_models(app: tauri::AppHandle) -> Result<String, String> {
     let config = AppConfig::default();
     let handle = app.clone();
     tokio::task::spawn_blocking(move || {
-        models::download::download_missing(&config.models_dir, &handle)
+        models::download::download_missing(&config.models_dir, &handle)?;
+        let conn = schema::init_db(&config.db_path)?;
+        models::registry::sync(&conn, &config.models_dir)
     }).await.map_err(|e| e.to_string())?.map_err(|e| e.to_string())?;
     Ok("done".into())
 }
 
 #[tauri::command]
-async fn load_models(res: tauri::State<'_, SharedResources>) -> Result<String, String> {
+async fn load_models(app: tauri::AppHandle, res: tauri::State<'_, SharedResources>) -> Result<String, String> {
     let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
     let mut r = res.lock().await;
     if r.asr.is_none() {
-        let p = config.models_dir.join("ggml-base.bin");
-        if !p.exists() {
-            let p2 = config.models_dir.join("ggml-small.bin");
-            if !p2.exists() { return Err("ASR model not found".into()); }
-            tracing::info!("Loading ASR model (small)...");
-            let engine = load_on_thread(move || AsrEngine::new(&p2)).await?;
-            r.asr = Some(Arc::new(engine));
-        } else {
-            tracing::info!("Loading ASR model (base)...");
-            let engine = load_on_thread(move || AsrEngine::new(&p)).await?;
-            r.asr = Some(Arc::new(engine));
-        }
-        tracing::info!("ASR loaded");
+        let model = models::registry::active(&conn, ModelType::Asr)
+            .ok_or("ASR model not found")?;
+        r.asr = Some(load_asr(model).await?);
     }
     if r.polish.is_none() {
-        let p = config.models_dir.join("qwen2.5-3b-instruct-q4_k_m.gguf");
-        if p.exists() {
-            tracing::info!("Loading LLM model...");
-            let engine = load_on_thread(move || PolishEngine::new(&p)).await?;
-            r.polish = Some(Arc::new(engine));
-            tracing::info!("LLM loaded");
+        let choice = polish::backend::load_choice(&conn);
+        let model = models::registry::active_path(&conn, ModelType::Llm);
+        if let PolishChoice::Managed { .. } = choice {
+            if model.is_some() {
+                r.polish = Some(load_polish(choice, model, app).await?);
+            }
+        } else {
+            // An unreachable server shouldn't stop dictation; it just goes unpolished
+            match load_polish(choice, model, app).await {
+                Ok(polish) => r.polish = Some(polish),
+                Err(e) => tracing::warn!("LLM backend unavailable: {}", e),
+            }
         }
     }
     Ok("Models loaded".into())
 }
 
+async fn load_asr(model: db::models::ModelEntry) -> Result<Arc<dyn AsrBackend>, String> {
+    let backend = load_on_thread(move || {
+        let backend = asr::backend::load_model(&model)?;
+        warm_up("ASR", || backend.warm_up());
+        Ok(backend)
+    }).await?;
+    tracing::info!("ASR loaded ({})", backend.name());
+    Ok(backend)
+}
+
+/// `model` is the GGUF file for the managed llama-server; external backends ignore it. The
+/// managed server's state is emitted as `llm_server_state`.
+async fn load_polish(choice: PolishChoice, model: Option<std::path::PathBuf>, app: tauri::AppHandle) -> Result<Arc<dyn PolishBackend>, String> {
+    tracing::info!("Loading LLM backend {:?}...", choice);
+    let on_state: polish::supervisor::StateListener = Arc::new(move |state| {
+        let _ = app.emit("llm_server_state", state);
+    });
+    let backend = load_on_thread(move || {
+        let backend = polish::backend::load(&choice, model.as_deref(), on_state)?;
+        warm_up("LLM", || backend.warm_up());
+        Ok(backend)
+    }).await?;
+    tracing::info!("LLM loaded ({})", backend.name());
+    Ok(backend)
+}
+
+/// How often polish output was refused by the validator over the last `days` days, next to
+/// the number of dictations.
+#[tauri::command]
+async fn get_polish_rejections(days: Option<u32>) -> Result<db::rejections::RejectionStats, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::rejections::stats(&conn, days.unwrap_or(30)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_polish_backend() -> Result<PolishChoice, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    Ok(polish::backend::load_choice(&conn).masked())
+}
+
+/// Switch LLM backends. The new one must load (and answer its health check) before it's
+/// saved; if models are loaded it replaces the running one, which is kept (or restarted)
+/// when the new one fails. A masked API key, as
+/// `get_polish_backend` returns it, keeps the saved key.
+#[tauri::command]
+async fn set_polish_backend(app: tauri::AppHandle, choice: PolishChoice, res: tauri::State<'_, SharedResources>) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    let saved = polish::backend::load_choice(&conn);
+    let choice = choice.unmasked(&saved);
+    choice.validate().map_err(|e| e.to_string())?;
+    let mut r = res.lock().await;
+    if r.stop_tx.is_some() {
+        return Err("Stop listening before switching models".into());
+    }
+    if r.asr.is_some() || r.polish.is_some() {
+        let model = models::registry::active_path(&conn, ModelType::Llm);
+        // Don't hold two models in memory at once: stop the old server first. Otherwise the
+        // old backend keeps running until the new one has loaded
+        let stopped = matches!(choice, PolishChoice::Managed { .. }) && r.polish.take().is_some();
+        match load_polish(choice.clone(), model.clone(), app.clone()).await {
+            Ok(polish) => r.polish = Some(polish),
+            Err(e) => {
+                if stopped {
+                    r.polish = load_polish(saved, model, app).await.ok();
+                }
+                return Err(e);
+            }
+        }
+    }
+    polish::backend::save_choice(&conn, &choice).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_models() -> Result<Vec<db::models::ModelEntry>, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    models::registry::sync(&conn, &config.models_dir).map_err(|e| e.to_string())
+}
+
+/// Make a model the active one of its type. An ASR or LLM model that is already loaded is
+/// replaced right away; VAD changes apply from the next time listening starts.
+#[tauri::command]
+async fn set_active_model(app: tauri::AppHandle, id: i64, res: tauri::State<'_, SharedResources>) -> Result<db::models::ModelEntry, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    models::registry::sync(&conn, &config.models_dir).map_err(|e| e.to_string())?;
+    let mut r = res.lock().await;
+    if r.stop_tx.is_some() {
+        return Err("Stop listening before switching models".into());
+    }
+    let target = db::models::get(&conn, id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No model with id {}", id))?;
+    if target.model_type == ModelType::Asr {
+        check_asr_language(&target, &asr::language::load(&conn))?;
+    }
+    let previous = db::models::active(&conn, target.model_type).ok().flatten();
+    let entry = db::models::set_active(&conn, id).map_err(|e| e.to_string())?;
+    let path = std::path::PathBuf::from(&entry.model_path);
+    let polish_choice = polish::backend::load_choice(&conn);
+    match entry.model_type {
+        ModelType::Asr if r.asr.is_some() => match load_asr(entry.clone()).await {
+            Ok(asr) => r.asr = Some(asr),
+            Err(e) => {
+                // The old engine stays loaded; keep the registry pointing at it
+                if let Some(previous) = previous {
+                    let _ = db::models::set_active(&conn, previous.id);
+                }
+                return Err(e);
+            }
+        },
+        // External LLM backends don't use local models
+        ModelType::Llm if r.polish.is_some() && matches!(polish_choice, PolishChoice::Managed { .. }) => {
+            let choice = polish_choice;
+            // Don't hold two models in memory at once: stop the old server first
+            r.polish = None;
+            match load_polish(choice.clone(), Some(path), app.clone()).await {
+                Ok(polish) => r.polish = Some(polish),
+                Err(e) => {
+                    if let Some(previous) = previous {
+                        let _ = db::models::set_active(&conn, previous.id);
+                        r.polish = load_polish(choice, Some(previous.model_path.into()), app).await.ok();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        _ => {}
+    }
+    tracing::info!("Active {} model: {}", entry.model_type.as_str(), entry.model_name);
+    Ok(entry)
+}
+
 #[tauri::command]
 async fn start_listening(app: tauri::AppHandle, res: tauri::State<'_, SharedResources>) -> Result<String, String> {
     let mut r = res.lock().await;
@@ -390,7 +557,9 @@ async fn start_listening(app: tauri::AppHandle, res: tauri::State<'_, SharedReso
 
     let asr = r.asr.clone().ok_or("Models not loaded")?;
     let polish = r.polish.clone();
-    let vad_path = r.config.models_dir.join("silero_vad.onnx");
+    let vad_path = schema::init_db(&r.config.db_path).ok()
+        .and_then(|c| models::registry::active_path(&c, ModelType::Vad))
+        .unwrap_or_else(|| r.config.models_dir.join(models::registry::DEFAULT_VAD));
     let mic_name = {
         let conn = schema::init_db(&r.config.db_path).ok();
         conn.and_then(|c| settings::get(&c, "mic_device").ok().flatten())
@@ -404,6 +573,8 @@ async fn start_listening(app: tauri::AppHandle, res: tauri::State<'_, SharedReso
     // Start orchestrator on Tauri's multi-threaded runtime (survives audio thread shutdown)
     let orchestrator = Orchestrator::start(asr.clone(), polish.clone(), app_handle.clone());
     let event_tx = orchestrator.event_tx.clone();
+    let captions = LIVE_CAPTIONS.load(Ordering::Relaxed)
+        .then(|| LiveCaptions::start(asr.clone(), app_handle.clone()));
 
     std::thread::spawn(move || {
         let rt = tokio::runtime::Builder::new_current_thread()
@@ -467,15 +638,20 @@ async fn start_listening(app: tauri::AppHandle, res: tauri::State<'_, SharedReso
                                 chunker.feed(&frame, true); // always "speech" so it never auto-flushes
                             } else {
                                 if let Some(segment) = chunker.feed(&frame, is_speech) {
+                                    if let Some(c) = &captions { c.end_utterance(); }
                                     if segment.len() > 4800 {
                                         let _ = event_tx.send(PipelineEvent::AudioSegment(segment));
                                     }
                                 }
                             }
+                            if let Some(c) = &captions {
+                                if chunker.is_buffering() { c.push(&frame); }
+                            }
                         }
                     }
                     _ = stop_rx.recv() => {
                         capture.stop();
+                        if let Some(c) = &captions { c.end_utterance(); }
                         if let Some(segment) = chunker.flush() {
                             if segment.len() > 4800 {
                                 let _ = event_tx.send(PipelineEvent::AudioSegment(segment));
@@ -693,67 +869,57 @@ async fn generate_hints(res: &SharedResources) {
 
     if !need_hints.is_empty() {
         sections.push(format!(
-            "HINTS: One voice dictation hint (max 6 words) per app. Format: AppName: hint\nApps: {}",
+            "hints: One voice dictation hint (max 6 words) per app, keyed by app name.\nApps: {}",
             need_hints.join(", ")
         ));
     }
 
     if need_mood {
-        sections.push(
-            "MOOD: Generate 6 time-of-day mood texts (max 4 words each). Calm, observational tone. \
-             Format: morning: text, afternoon: text, evening: text, night: text, late: text, early: text".to_string()
-        );
+        sections.push(format!(
+            "mood: One time-of-day mood text (max 4 words) for each of {}. Calm, observational tone.",
+            polish::structured::MOOD_TIMES.join(", ")
+        ));
     }
 
     if !need_affirm.is_empty() {
         sections.push(format!(
-            "AFFIRMATIONS: One calm, observational acknowledgment (3-5 words, no emoji, no exclamation) per app. \
-             Format: AppName: text\nApps: {}",
+            "affirmations: One calm, observational acknowledgment (3-5 words, no emoji, no exclamation) per app, \
+             keyed by app name.\nApps: {}",
             need_affirm.join(", ")
         ));
     }
 
     let prompt = sections.join("\n\n");
+    let schema = polish::structured::microcopy_schema(&need_hints, need_mood, &need_affirm);
     let engine_clone = engine.clone();
     let result = tokio::task::spawn_blocking(move || {
-        engine_clone.generate("You write ultra-concise UI microcopy. Be subtle, never cheerful.", &prompt, 256)
+        engine_clone.generate_json("You write ultra-concise UI microcopy. Be subtle, never cheerful.", &prompt, 384, &schema)
     }).await;
 
-    if let Ok(Ok(text)) = result {
-        let mut section = "";
-        for line in text.lines() {
-            let trimmed = line.trim();
-            if trimmed.starts_with("HINTS") { section = "hints"; continue; }
-            if trimmed.starts_with("MOOD") { section = "mood"; continue; }
-            if trimmed.starts_with("AFFIRMATION") { section = "affirm"; continue; }
-
-            if let Some((key, val)) = trimmed.split_once(':') {
-                let key = key.trim();
-                let val = val.trim().trim_matches('"');
-                if val.is_empty() { continue; }
-
-                match section {
-                    "hints" if need_hints.iter().any(|a| a == key) => {
-                        let _ = db::hints::save_hint(&conn, key, val);
-                    }
-                    "mood" => {
-                        let tag = format!("__mood_{}", key);
-                        let _ = db::hints::save_hint(&conn, &tag, val);
-                    }
-                    "affirm" if need_affirm.iter().any(|a| a == key) => {
-                        let tag = format!("__affirm_{}", key);
-                        let _ = db::hints::save_hint(&conn, &tag, val);
-                    }
-                    // If no section header, try to match as hint (backward compat)
-                    "" if need_hints.iter().any(|a| a == key) => {
-                        let _ = db::hints::save_hint(&conn, key, val);
-                    }
-                    _ => {}
-                }
-            }
+    let copy = match result {
+        Ok(Ok(reply)) => serde_json::from_value::<polish::structured::Microcopy>(reply),
+        Ok(Err(e)) => { tracing::warn!("Hint generation failed: {}", e); return; }
+        Err(_) => return,
+    };
+    let copy = match copy {
+        Ok(c) => c,
+        Err(e) => { tracing::warn!("Hint reply doesn't match its schema: {}", e); return; }
+    };
+    let texts = copy.hints.into_iter()
+        .filter(|(app, _)| need_hints.contains(app))
+        .chain(copy.mood.into_iter()
+            .filter(|(time, _)| polish::structured::MOOD_TIMES.contains(&time.as_str()))
+            .map(|(time, text)| (format!("__mood_{}", time), text)))
+        .chain(copy.affirmations.into_iter()
+            .filter(|(app, _)| need_affirm.contains(app))
+            .map(|(app, text)| (format!("__affirm_{}", app), text)));
+    for (tag, text) in texts {
+        let text = text.trim();
+        if !text.is_empty() {
+            let _ = db::hints::save_hint(&conn, &tag, text);
         }
-        tracing::info!("Generated hints/mood/affirmations");
     }
+    tracing::info!("Generated hints/mood/affirmations");
 }
 
 #[tauri::command]
@@ -769,6 +935,204 @@ async fn get_dictionary() -> Result<Vec<String>, String> {
     db::dictionary::get_all(&conn).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn get_inject_strategy(category: String) -> Result<inject::injector::InjectStrategy, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    Ok(inject::injector::strategy_for_category(&conn, &category))
+}
+
+#[tauri::command]
+async fn set_inject_strategy(category: String, strategy: inject::injector::InjectStrategy) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    inject::injector::set_strategy(&conn, &category, strategy).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_app_rules() -> Result<Vec<db::rules::AppRule>, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::rules::list(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn add_app_rule(rule: db::rules::AppRule) -> Result<i64, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::rules::add(&conn, &rule).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_app_rule(rule: db::rules::AppRule) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::rules::update(&conn, &rule).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_app_rule(id: i64) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::rules::delete(&conn, id).map_err(|e| e.to_string())
+}
+
+/// Which rule would categorize this app and window; `None` means the default category.
+#[tauri::command]
+async fn test_app_rule(bundle_id: String, app_name: String, window_title: String) -> Result<Option<db::rules::AppRule>, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    let rules = db::rules::load(&conn).map_err(|e| e.to_string())?;
+    Ok(rules.find_match(&bundle_id, &app_name, &window_title).cloned())
+}
+
+#[tauri::command]
+async fn list_app_tones() -> Result<Vec<db::tones::AppTone>, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::tones::list(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_app_tone(bundle_id: String, app_name: String, category: String, tone: String) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::tones::set_tone(&conn, &bundle_id, &app_name, &category, &tone).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_app_tone(bundle_id: String) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::tones::delete_tone(&conn, &bundle_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_category_tones() -> Result<Vec<(String, String)>, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::rules::list_category_tones(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_category_tone(category: String, tone: String) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::rules::set_category_tone(&conn, &category, &tone).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_asr_language() -> Result<asr::language::LanguageMode, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    Ok(asr::language::load(&conn))
+}
+
+#[tauri::command]
+async fn set_asr_language(mode: asr::language::LanguageMode) -> Result<(), String> {
+    mode.validate(|code| whisper_rs::get_lang_id(code).is_some()).map_err(|e| e.to_string())?;
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    if let Some(model) = models::registry::active(&conn, ModelType::Asr) {
+        check_asr_language(&model, &mode)?;
+    }
+    asr::language::save(&conn, &mode).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_asr_backend() -> Result<asr::backend::BackendChoice, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    Ok(models::registry::active(&conn, ModelType::Asr)
+        .map(|m| asr::backend::BackendChoice::of(&m))
+        .unwrap_or_default())
+}
+
+/// Activates the choice's model in the registry; takes effect the next time models are loaded.
+#[tauri::command]
+async fn set_asr_backend(choice: asr::backend::BackendChoice) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    models::registry::sync(&conn, &config.models_dir).map_err(|e| e.to_string())?;
+    if let asr::backend::BackendChoice::Onnx { model } = &choice {
+        asr::backend::english_only(model, &asr::language::load(&conn)).map_err(|e| e.to_string())?;
+    }
+    models::registry::activate_backend(&conn, &config.models_dir, &choice).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// English-only models can't be paired with another transcription language.
+fn check_asr_language(model: &db::models::ModelEntry, mode: &asr::language::LanguageMode) -> Result<(), String> {
+    match model.format {
+        db::models::ModelFormat::Onnx => asr::backend::english_only(&model.model_name, mode).map_err(|e| e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[tauri::command]
+async fn get_asr_decoding() -> Result<asr::decoding::DecodingSetting, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    Ok(asr::decoding::load(&conn))
+}
+
+#[tauri::command]
+async fn set_asr_decoding(setting: asr::decoding::DecodingSetting) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    asr::decoding::save(&conn, &setting).map_err(|e| e.to_string())
+}
+
+fn check_language_code(code: &str) -> Result<(), String> {
+    if code.is_empty() || whisper_rs::get_lang_id(code).is_some() {
+        Ok(())
+    } else {
+        Err(format!("Unknown language code: {}", code))
+    }
+}
+
+#[tauri::command]
+async fn get_output_language() -> Result<Option<String>, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::translation::get_global(&conn).map_err(|e| e.to_string())
+}
+
+/// `None` or "" writes dictation in the language it was spoken in.
+#[tauri::command]
+async fn set_output_language(language: Option<String>) -> Result<(), String> {
+    let language = language.filter(|l| !l.is_empty());
+    if let Some(code) = &language {
+        check_language_code(code)?;
+    }
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::translation::set_global(&conn, language.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_app_output_languages() -> Result<Vec<db::translation::AppOutputLanguage>, String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::translation::list(&conn).map_err(|e| e.to_string())
+}
+
+/// An empty `language` keeps this app untranslated even when a global output language is set.
+#[tauri::command]
+async fn set_app_output_language(bundle_id: String, app_name: String, language: String) -> Result<(), String> {
+    check_language_code(&language)?;
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::translation::set_for_app(&conn, &bundle_id, &app_name, &language).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_app_output_language(bundle_id: String) -> Result<(), String> {
+    let config = AppConfig::default();
+    let conn = schema::init_db(&config.db_path).map_err(|e| e.to_string())?;
+    db::translation::delete_for_app(&conn, &bundle_id).map_err(|e| e.to_string())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tracing_subscriber::fmt::init();
@@ -782,6 +1146,9 @@ pub fn run() {
         if let Ok(Some(v)) = settings::get(&conn, "walkie_talkie") {
             WALKIE_TALKIE.store(v == "1", Ordering::Relaxed);
         }
+        if let Ok(Some(v)) = settings::get(&conn, "live_captions") {
+            LIVE_CAPTIONS.store(v == "1", Ordering::Relaxed);
+        }
     }
 
     if !launchd_plist_path().exists() {
@@ -810,6 +1177,12 @@ pub fn run() {
         .setup(|app| {
             setup_tray(app)?;
 
+            // Pick up models installed or removed while the app wasn't running
+            let cfg = AppConfig::default();
+            if let Err(e) = schema::init_db(&cfg.db_path).and_then(|c| models::registry::sync(&c, &cfg.models_dir)) {
+                tracing::warn!("Model registry sync failed: {}", e);
+            }
+
             // Watch for audio device changes and rebuild tray menu
             #[cfg(target_os = "macos")]
             {
@@ -882,12 +1255,20 @@ pub fn run() {
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
-            check_models, download_models, load_models,
+            check_models, download_models, load_models, list_models, set_active_model,
+            get_polish_backend, set_polish_backend, get_polish_rejections,
             start_listening, stop_listening,
             get_app_state, open_accessibility_settings, check_accessibility_cmd, get_active_app_info,
             set_pill_color, get_pill_color,
             get_hint,
             add_dictionary_word, get_dictionary,
+            get_inject_strategy, set_inject_strategy,
+            list_app_rules, add_app_rule, update_app_rule, delete_app_rule, test_app_rule,
+            list_app_tones, set_app_tone, delete_app_tone, list_category_tones, set_category_tone,
+            get_asr_language, set_asr_language, get_asr_backend, set_asr_backend,
+            get_asr_decoding, set_asr_decoding,
+            get_output_language, set_output_language,
+            list_app_output_languages, set_app_output_language, delete_app_output_language,
             toggle_polish, get_polish_enabled,
             list_mics, set_mic, get_mic,
             save_window_pos, get_window_pos,
@@ -1047,7 +1428,7 @@ mod tests {
             e.split(" → ").nth(1).unwrap_or("").to_string()
         }).collect();
         let ctx = inject::context::AppContext::default();
-        let prompt = polish::prompt::build_system_prompt(&ctx, &words);
+        let prompt = polish::prompt::build_system_prompt(&ctx, &words, Default::default());
         assert!(prompt.contains("Kubernetes"));
         assert!(prompt.contains("gRPC"));
     }
@@ -1074,9 +1455,9 @@ mod tests {
     #[test]
     fn hint_top_apps_ranking() {
         let conn = test_db_conn();
-        for _ in 0..10 { db::hints::record_usage(&conn, "VS Code").unwrap(); }
-        for _ in 0..5 { db::hints::record_usage(&conn, "Slack").unwrap(); }
-        for _ in 0..2 { db::hints::record_usage(&conn, "Safari").unwrap(); }
+        for _ in 0..10 { db::hints::record_usage(&conn, "VS Code", None).unwrap(); }
+        for _ in 0..5 { db::hints::record_usage(&conn, "Slack", None).unwrap(); }
+        for _ in 0..2 { db::hints::record_usage(&conn, "Safari", None).unwrap(); }
         let top = db::hints::top_apps(&conn, 3).unwrap();
         assert_eq!(top[0], "VS Code");
         assert_eq!(top[1], "Slack");
@@ -1115,13 +1496,14 @@ mod tests {
             window_title: "#engineering".into(),
             selected_text: "let's deploy".into(),
         };
-        let prompt = polish::prompt::build_system_prompt(&ctx, &["Kubernetes".into()]);
+        let prompt = polish::prompt::build_system_prompt(&ctx, &["Kubernetes".into()], polish::prompt::Languages { spoken: "English", output: None });
         assert!(prompt.contains("Slack"));
         assert!(prompt.contains("slack"));
         assert!(prompt.contains("Casual"));
         assert!(prompt.contains("#engineering"));
         assert!(prompt.contains("let's deploy"));
         assert!(prompt.contains("Kubernetes"));
+        assert!(prompt.contains("Language: English"));
     }
 
     // ===== Layer 4: Error and edge case tests =====
@@ -1150,7 +1532,7 @@ mod tests {
     // --- Missing model error ---
     #[test]
     fn polish_engine_missing_model_error() {
-        let result = polish::engine::PolishEngine::new(std::path::Path::new("/tmp/nonexistent.gguf"));
+        let result = polish::engine::PolishEngine::new(std::path::Path::new("/tmp/nonexistent.gguf"), std::time::Duration::from_secs(15), Arc::new(|_| {}));
         assert!(result.is_err());
     }
 
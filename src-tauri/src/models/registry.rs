@@ -0,0 +1,251 @@
+//! Installed models, found by scanning the models directory and recorded in `model_config`
+//! with one active model per type.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+use crate::asr::backend::BackendChoice;
+use crate::db::models::{self, ModelEntry, ModelFormat, ModelType};
+
+pub const DEFAULT_VAD: &str = "silero_vad.onnx";
+pub const DEFAULT_LLM: &str = "qwen2.5-3b-instruct-q4_k_m.gguf";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovered {
+    pub model_type: ModelType,
+    pub format: ModelFormat,
+    pub name: String,
+    pub path: PathBuf,
+    pub quantization: Option<String>,
+}
+
+/// ggml/GGUF quantization suffixes: q5_1, q4_k_m, iq3_xs, f16...
+fn is_quantization(s: &str) -> bool {
+    let s = s.to_lowercase();
+    if matches!(s.as_str(), "f16" | "f32" | "bf16") {
+        return true;
+    }
+    let rest = s.strip_prefix("iq").or_else(|| s.strip_prefix('q')).unwrap_or("");
+    rest.starts_with(|c: char| c.is_ascii_digit())
+        && rest.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// "base.en-q5_1" -> ("base.en", Some("q5_1")), "Phi-3-mini.Q4_K_M" -> ("Phi-3-mini", Some("q4_k_m")).
+fn split_quantization(stem: &str) -> (String, Option<String>) {
+    if let Some(i) = stem.rfind(['-', '.']) {
+        let suffix = &stem[i + 1..];
+        if is_quantization(suffix) {
+            return (stem[..i].to_string(), Some(suffix.to_lowercase()));
+        }
+    }
+    (stem.to_string(), None)
+}
+
+fn classify(path: &Path) -> Option<Discovered> {
+    let file_name = path.file_name()?.to_str()?;
+    let (model_type, format, stem) = if let Some(whisper) = file_name.strip_prefix("ggml-").and_then(|f| f.strip_suffix(".bin")) {
+        (ModelType::Asr, ModelFormat::Ggml, whisper)
+    } else if let Some(llm) = file_name.strip_suffix(".gguf") {
+        (ModelType::Llm, ModelFormat::Gguf, llm)
+    } else if let Some(vad) = file_name.strip_suffix(".onnx").filter(|f| f.to_lowercase().contains("vad")) {
+        (ModelType::Vad, ModelFormat::Onnx, vad)
+    } else {
+        return None;
+    };
+    let (name, quantization) = split_quantization(stem);
+    Some(Discovered { model_type, format, name, path: path.to_path_buf(), quantization })
+}
+
+/// Models installed in `models_dir`: whisper.cpp `ggml-*.bin`, `*.gguf` LLMs, `*vad*.onnx`
+/// and ONNX ASR exports in `onnx/<name>/`.
+pub fn scan(models_dir: &Path) -> Vec<Discovered> {
+    let mut found: Vec<Discovered> = std::fs::read_dir(models_dir).into_iter().flatten()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .filter_map(|p| classify(&p))
+        .collect();
+    found.extend(
+        std::fs::read_dir(models_dir.join("onnx")).into_iter().flatten()
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_dir())
+            .filter_map(|p| {
+                let (name, quantization) = split_quantization(p.file_name()?.to_str()?);
+                Some(Discovered { model_type: ModelType::Asr, format: ModelFormat::Onnx, name, path: p, quantization })
+            }),
+    );
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+/// Model to activate when none of its type is: the one the app used before there was a
+/// choice, else the first installed.
+fn default_for<'a>(models_dir: &Path, model_type: ModelType, installed: &'a [ModelEntry]) -> Option<&'a ModelEntry> {
+    let preferred = match model_type {
+        ModelType::Asr => BackendChoice::Whisper.model_path(models_dir),
+        ModelType::Vad => Some(models_dir.join(DEFAULT_VAD)),
+        ModelType::Llm => Some(models_dir.join(DEFAULT_LLM)),
+    };
+    let of_type = || installed.iter().filter(|m| m.model_type == model_type);
+    preferred
+        .and_then(|p| of_type().find(|m| Path::new(&m.model_path) == p))
+        .or_else(|| of_type().next())
+}
+
+/// Bring `model_config` in line with what's on disk and make sure each type that has an
+/// installed model has an active one.
+pub fn sync(conn: &Connection, models_dir: &Path) -> Result<Vec<ModelEntry>> {
+    let found = scan(models_dir);
+    let mut paths = Vec::with_capacity(found.len());
+    for m in &found {
+        let path = m.path.to_string_lossy().to_string();
+        models::upsert(conn, m.model_type, m.format, &m.name, &path, m.quantization.as_deref())?;
+        paths.push(path);
+    }
+    let removed = models::retain(conn, &paths)?;
+    if removed > 0 {
+        tracing::info!("Removed {} missing models from the registry", removed);
+    }
+
+    let installed = models::list(conn)?;
+    for model_type in [ModelType::Asr, ModelType::Vad, ModelType::Llm] {
+        if installed.iter().any(|m| m.model_type == model_type && m.is_active) {
+            continue;
+        }
+        if let Some(default) = default_for(models_dir, model_type, &installed) {
+            tracing::info!("Activating {} model {}", model_type.as_str(), default.model_name);
+            models::set_active(conn, default.id)?;
+        }
+    }
+    models::list(conn)
+}
+
+/// The active model of `model_type`, if it is still on disk. Only reads the registry;
+/// `sync` is what picks up models installed or deleted since.
+pub fn active(conn: &Connection, model_type: ModelType) -> Option<ModelEntry> {
+    models::active(conn, model_type).ok().flatten()
+        .filter(|m| Path::new(&m.model_path).exists())
+}
+
+/// Path of the active model of `model_type`, if it is still on disk.
+pub fn active_path(conn: &Connection, model_type: ModelType) -> Option<PathBuf> {
+    active(conn, model_type).map(|m| PathBuf::from(m.model_path))
+}
+
+/// Make the ASR model `choice` names the active one, unless the active model already
+/// belongs to it (a quantized whisper model the user picked stays).
+pub fn activate_backend(conn: &Connection, models_dir: &Path, choice: &BackendChoice) -> Result<ModelEntry> {
+    if let Some(current) = active(conn, ModelType::Asr).filter(|m| BackendChoice::of(m) == *choice) {
+        return Ok(current);
+    }
+    let path = choice.model_path(models_dir)
+        .ok_or_else(|| anyhow::anyhow!("ASR model not installed for {:?}", choice))?;
+    let entry = models::list(conn)?.into_iter()
+        .find(|m| Path::new(&m.model_path) == path)
+        .ok_or_else(|| anyhow::anyhow!("{} is not in the model registry", path.display()))?;
+    models::set_active(conn, entry.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn test_db() -> Connection {
+        schema::init_db(Path::new(":memory:")).unwrap()
+    }
+
+    fn touch(dir: &Path, name: &str) {
+        std::fs::write(dir.join(name), b"").unwrap();
+    }
+
+    #[test]
+    fn splits_quantization() {
+        assert_eq!(split_quantization("base"), ("base".into(), None));
+        assert_eq!(split_quantization("base.en"), ("base.en".into(), None));
+        assert_eq!(split_quantization("small.en-q5_1"), ("small.en".into(), Some("q5_1".into())));
+        assert_eq!(split_quantization("large-v3-turbo-q8_0"), ("large-v3-turbo".into(), Some("q8_0".into())));
+        assert_eq!(split_quantization("qwen2.5-3b-instruct-q4_k_m"), ("qwen2.5-3b-instruct".into(), Some("q4_k_m".into())));
+        assert_eq!(split_quantization("Phi-3-mini.Q4_K_M"), ("Phi-3-mini".into(), Some("q4_k_m".into())));
+        assert_eq!(split_quantization("llama-3.2-1b-f16"), ("llama-3.2-1b".into(), Some("f16".into())));
+    }
+
+    #[test]
+    fn scan_classifies_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for f in ["ggml-tiny.bin", "ggml-medium-q5_0.bin", DEFAULT_VAD, DEFAULT_LLM, "llama-server", "notes.txt", "ggml-base.bin.part"] {
+            touch(dir.path(), f);
+        }
+        std::fs::create_dir_all(dir.path().join("onnx/moonshine-base")).unwrap();
+        let found = scan(dir.path());
+        let summary: Vec<_> = found.iter()
+            .map(|m| (m.model_type, m.format, m.name.as_str(), m.quantization.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![
+            (ModelType::Asr, ModelFormat::Ggml, "medium", Some("q5_0")),
+            (ModelType::Asr, ModelFormat::Ggml, "tiny", None),
+            (ModelType::Asr, ModelFormat::Onnx, "moonshine-base", None),
+            (ModelType::Llm, ModelFormat::Gguf, "qwen2.5-3b-instruct", Some("q4_k_m")),
+            (ModelType::Vad, ModelFormat::Onnx, "silero_vad", None),
+        ]);
+    }
+
+    #[test]
+    fn sync_activates_defaults_and_keeps_choice() {
+        let conn = test_db();
+        let dir = tempfile::tempdir().unwrap();
+        for f in ["ggml-base.bin", "ggml-small-q5_1.bin", DEFAULT_VAD, DEFAULT_LLM, "phi-3.Q8_0.gguf"] {
+            touch(dir.path(), f);
+        }
+        sync(&conn, dir.path()).unwrap();
+        assert_eq!(active_path(&conn, ModelType::Asr), Some(dir.path().join("ggml-base.bin")));
+        assert_eq!(active_path(&conn, ModelType::Llm), Some(dir.path().join(DEFAULT_LLM)));
+        assert_eq!(active_path(&conn, ModelType::Vad), Some(dir.path().join(DEFAULT_VAD)));
+
+        let small = models::list(&conn).unwrap().into_iter().find(|m| m.model_name == "small").unwrap();
+        models::set_active(&conn, small.id).unwrap();
+        sync(&conn, dir.path()).unwrap();
+        assert_eq!(active_path(&conn, ModelType::Asr), Some(dir.path().join("ggml-small-q5_1.bin")));
+    }
+
+    #[test]
+    fn backend_choice_follows_the_active_model() {
+        let conn = test_db();
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path(), "ggml-base.bin");
+        touch(dir.path(), "ggml-small-q5_1.bin");
+        std::fs::create_dir_all(dir.path().join("onnx/moonshine-base")).unwrap();
+        sync(&conn, dir.path()).unwrap();
+
+        let onnx = BackendChoice::Onnx { model: "moonshine-base".into() };
+        activate_backend(&conn, dir.path(), &onnx).unwrap();
+        assert_eq!(active_path(&conn, ModelType::Asr), Some(dir.path().join("onnx/moonshine-base")));
+        assert!(activate_backend(&conn, dir.path(), &BackendChoice::Onnx { model: "nope".into() }).is_err());
+
+        // Picking a model in the registry changes the backend too
+        let small = models::list(&conn).unwrap().into_iter().find(|m| m.model_name == "small").unwrap();
+        models::set_active(&conn, small.id).unwrap();
+        assert_eq!(BackendChoice::of(&active(&conn, ModelType::Asr).unwrap()), BackendChoice::Whisper);
+        // …and choosing whisper again keeps that model rather than the default one
+        assert_eq!(activate_backend(&conn, dir.path(), &BackendChoice::Whisper).unwrap().id, small.id);
+    }
+
+    #[test]
+    fn sync_replaces_deleted_active_model() {
+        let conn = test_db();
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path(), "ggml-base.bin");
+        touch(dir.path(), "ggml-tiny.en.bin");
+        sync(&conn, dir.path()).unwrap();
+        assert_eq!(active_path(&conn, ModelType::Asr), Some(dir.path().join("ggml-base.bin")));
+        std::fs::remove_file(dir.path().join("ggml-base.bin")).unwrap();
+        // Reads don't rescan the disk
+        assert_eq!(active_path(&conn, ModelType::Asr), None);
+        sync(&conn, dir.path()).unwrap();
+        assert_eq!(active_path(&conn, ModelType::Asr), Some(dir.path().join("ggml-tiny.en.bin")));
+        std::fs::remove_file(dir.path().join("ggml-tiny.en.bin")).unwrap();
+        sync(&conn, dir.path()).unwrap();
+        assert_eq!(active_path(&conn, ModelType::Asr), None);
+        assert!(models::list(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+use std::sync::Arc;
+use tauri::Emitter;
+
+use crate::asr::backend::{AsrBackend, TranscribeOptions};
+use crate::asr::decoding::DecodingOptions;
+use crate::asr::streaming::{Partial, StreamConfig, Streamer};
+use crate::asr::{hallucination, language};
+
+pub static LIVE_CAPTIONS: AtomicBool = AtomicBool::new(false);
+
+enum CaptionEvent {
+    Audio(Vec<f32>),
+    EndUtterance,
+}
+
+/// Streams `partial_transcript` events while the user speaks. Runs on its own thread so a
+/// slow pass never holds up capture; frames that arrive meanwhile are folded into the next
+/// pass. Only for display: the injected text still comes from the final segment.
+pub struct LiveCaptions {
+    tx: mpsc::Sender<CaptionEvent>,
+}
+
+impl LiveCaptions {
+    pub fn start(asr: Arc<dyn AsrBackend>, app_handle: tauri::AppHandle) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let conn = crate::db::schema::init_db(&crate::config::AppConfig::default().db_path).ok();
+        let options = TranscribeOptions {
+            language: conn.as_ref().map(language::load).unwrap_or_default(),
+            translate: false,
+            // Partials are thrown away; keep them cheap whatever the final decode uses
+            decoding: DecodingOptions::fast(),
+        };
+        let filter = conn.as_ref().map(hallucination::load).unwrap_or_default();
+        let streamer = Streamer::new(StreamConfig::default(), options, filter);
+        std::thread::Builder::new()
+            .name("live-captions".into())
+            .spawn(move || run(rx, streamer, asr.as_ref(), &app_handle))
+            .ok();
+        Self { tx }
+    }
+
+    pub fn push(&self, frame: &[f32]) {
+        let _ = self.tx.send(CaptionEvent::Audio(frame.to_vec()));
+    }
+
+    /// The utterance went to the pipeline; clear the caption and start over.
+    pub fn end_utterance(&self) {
+        let _ = self.tx.send(CaptionEvent::EndUtterance);
+    }
+}
+
+fn run(rx: mpsc::Receiver<CaptionEvent>, mut streamer: Streamer, asr: &dyn AsrBackend, app_handle: &tauri::AppHandle) {
+    // Ends when the LiveCaptions handle is dropped
+    while let Ok(first) = rx.recv() {
+        for event in std::iter::once(first).chain(rx.try_iter()) {
+            match event {
+                CaptionEvent::Audio(frame) => streamer.push(&frame),
+                CaptionEvent::EndUtterance => {
+                    streamer.reset();
+                    let _ = app_handle.emit("partial_transcript", Partial::default());
+                }
+            }
+        }
+        if !streamer.due() {
+            continue;
+        }
+        let start = std::time::Instant::now();
+        match streamer.update(asr) {
+            Ok(partial) => {
+                tracing::debug!("Partial ({:?}): {} | {}", start.elapsed(), partial.committed, partial.tentative);
+                let _ = app_handle.emit("partial_transcript", partial);
+            }
+            Err(e) => tracing::warn!("Partial transcription failed: {}", e),
+        }
+    }
+}
@@ -1,9 +1,17 @@
-use crate::asr::engine::AsrEngine;
+use crate::asr::backend::{AsrBackend, TranscribeOptions, Unsupported};
+use crate::asr::{decoding, hallucination, language};
+use crate::asr::prompt::AsrContext;
 use crate::inject::clipboard;
-use crate::inject::context::get_active_app;
+use crate::inject::context::{ActiveApp, AppContext};
+use crate::inject::injector::{self, Injector};
+use crate::db::dictionary::DictEntry;
 use crate::polish::commands::{self, VoiceCommand};
-use crate::polish::engine::PolishEngine;
-use crate::polish::prompt;
+use crate::polish::dictionary::{self, Substitution};
+use crate::polish::phonetic;
+use crate::polish::backend::PolishBackend;
+use crate::polish::prompt::{self, Languages};
+use crate::polish::stream::{self, SentenceStream};
+use crate::polish::validate::{self, Rejected};
 use anyhow::Result;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -24,14 +32,16 @@ pub struct Orchestrator {
 
 impl Orchestrator {
     pub fn start(
-        asr: Arc<AsrEngine>,
-        polish: Option<Arc<PolishEngine>>,
+        asr: Arc<dyn AsrBackend>,
+        polish: Option<Arc<dyn PolishBackend>>,
         app_handle: tauri::AppHandle,
     ) -> Self {
         let (event_tx, mut event_rx) = mpsc::unbounded_channel::<PipelineEvent>();
 
         tokio::spawn(async move {
             let mut pending: Option<tokio::task::JoinHandle<()>> = None;
+            // Text of the last dictated segment, carried into the next ASR prompt
+            let previous = Arc::new(std::sync::Mutex::new(String::new()));
             while let Some(event) = event_rx.recv().await {
                 match event {
                     PipelineEvent::AudioSegment(audio) => {
@@ -39,17 +49,35 @@ impl Orchestrator {
                         let asr = asr.clone();
                         let polish = polish.clone();
                         let handle = app_handle.clone();
+                        let previous = previous.clone();
                         let _ = handle.emit("pipeline_state", "processing");
                         pending = Some(tokio::task::spawn_blocking(move || {
-                            match process_segment(&asr, polish.as_deref(), &audio) {
-                                Ok((words, secs)) if words > 0 => {
+                            let prev_text = previous.lock().map(|p| p.clone()).unwrap_or_default();
+                            let on_partial = |partial: &str| { let _ = handle.emit("polish_partial", partial); };
+                            match process_segment(asr.as_ref(), polish.as_deref(), &audio, &prev_text, &on_partial) {
+                                Ok((dictated, secs)) if dictated.words > 0 => {
+                                    if let Ok(mut p) = previous.lock() {
+                                        p.clone_from(&dictated.text);
+                                    }
                                     let _ = handle.emit("dictation_stats", serde_json::json!({
-                                        "words": words, "seconds": (secs * 10.0).round() / 10.0
+                                        "words": dictated.words, "seconds": (secs * 10.0).round() / 10.0,
+                                        "substitutions": dictated.substitutions.iter().filter(|s| s.changed()).collect::<Vec<_>>(),
+                                        "language": dictated.language,
                                     }));
                                 }
-                                Err(e) => tracing::error!("Pipeline error: {}", e),
+                                Err(e) => {
+                                    tracing::error!("Pipeline error: {}", e);
+                                    // Nothing gets transcribed until the user changes a setting
+                                    if let Some(unsupported) = e.downcast_ref::<Unsupported>() {
+                                        let _ = handle.emit("asr_unsupported", unsupported.to_string());
+                                    }
+                                }
                                 _ => {}
                             }
+                            // The text went in; only the user's old clipboard was lost
+                            if let Some(restore) = clipboard::take_restore_error() {
+                                let _ = handle.emit("clipboard_restore_failed", restore.to_string());
+                            }
                             let _ = handle.emit("pipeline_state", "idle");
                         }));
                     }
@@ -66,62 +94,238 @@ impl Orchestrator {
     }
 }
 
-pub(crate) fn process_segment(asr: &AsrEngine, polish: Option<&PolishEngine>, audio: &[f32]) -> Result<(usize, f64)> {
+pub(crate) fn process_segment(asr: &dyn AsrBackend, polish: Option<&dyn PolishBackend>, audio: &[f32], previous_text: &str, on_partial: &dyn Fn(&str)) -> Result<(Dictated, f64)> {
     let start = std::time::Instant::now();
 
-    let raw_text = asr.transcribe(audio)?;
-    tracing::info!("ASR ({:?}): {}", start.elapsed(), &raw_text);
+    // Only the app and window title before ASR; the text around the cursor is read once
+    // there is something to inject
+    let app = ActiveApp::detect();
+    let conn = crate::db::schema::init_db(&crate::config::AppConfig::default().db_path).ok();
+    let vocab = conn.as_ref()
+        .and_then(|c| crate::db::dictionary::entries(c).ok())
+        .unwrap_or_default();
+    let output_language = conn.as_ref().and_then(|c| crate::db::translation::resolve(c, &app.ctx.bundle_id));
+    let options = TranscribeOptions {
+        language: conn.as_ref().map(language::load).unwrap_or_default(),
+        // Whisper translates into English natively; other targets go through polish
+        translate: output_language.as_deref() == Some("en"),
+        decoding: conn.as_ref().map(|c| decoding::load(c).options()).unwrap_or_default(),
+    };
+    let snippets = conn.as_ref()
+        .and_then(|c| crate::db::snippets::get_all(c).ok())
+        .unwrap_or_default();
+    let asr_ctx = AsrContext {
+        // Most-used dictionary terms first, then snippet triggers so they're heard verbatim
+        vocabulary: vocab.iter().map(|e| e.written.clone())
+            .chain(snippets.into_iter().map(|(trigger, _)| trigger))
+            .collect(),
+        window_title: app.ctx.window_title.clone(),
+        previous_text: previous_text.to_string(),
+    };
+
+    let transcript = asr.transcribe_with(audio, Some(&asr_ctx), &options)?;
+    let filter_config = conn.as_ref().map(hallucination::load).unwrap_or_default();
+    let (transcript, _) = hallucination::filter(transcript, &filter_config);
+    let raw_text = transcript.text;
+    tracing::info!("ASR {} ({:?}, {}): {}", asr.name(), start.elapsed(), transcript.language, &raw_text);
 
     if raw_text.is_empty() || raw_text.starts_with('[') || raw_text.starts_with('(') {
-        return Ok((0, 0.0));
+        return Ok((Dictated::default(), 0.0));
     }
 
-    let cmd = commands::parse_command(&raw_text);
+    let ctx = app.with_text_context();
+    let injector = injector::for_category(&ctx.category);
+    let written = if options.translate { "en" } else { transcript.language.as_str() };
+    let spoken_name = language::display_name(written);
+    let output_name = output_language.filter(|o| o != written).map(|o| language::display_name(&o));
+    let languages = Languages { spoken: &spoken_name, output: output_name.as_deref() };
+    let mut dictated = process_text(&raw_text, polish, &ctx, &vocab, languages, injector.as_ref(), on_partial)?;
+    dictated.language = transcript.language;
+    let elapsed = start.elapsed().as_secs_f64();
+    tracing::info!("Total pipeline ({:?})", start.elapsed());
+
+    if let Some(conn) = &conn {
+        for sub in &dictated.substitutions {
+            let _ = crate::db::dictionary::increment_usage(conn, sub.entry_id);
+        }
+        for r in &dictated.rejected {
+            let _ = crate::db::rejections::record(conn, r.rejection.reason(), &dictated.raw, &r.polished);
+        }
+    }
+    if dictated.words == 0 {
+        return Ok((dictated, 0.0));
+    }
+
+    // Record app usage for hint generation
+    if let Some(conn) = &conn {
+        let _ = crate::db::hints::record_usage(conn, &ctx.app_name, Some(&dictated.language));
+    }
+    crate::LAST_DICTATION.store(
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs(),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+
+    Ok((dictated, elapsed))
+}
+
+/// Result of one dictated segment.
+#[derive(Debug, Default)]
+pub(crate) struct Dictated {
+    /// Dictated words injected (0 for commands).
+    pub words: usize,
+    /// Text injected, empty for commands.
+    pub text: String,
+    /// Dictionary matches in the raw transcript, exact and phonetic, including those that
+    /// left the text as it was.
+    pub substitutions: Vec<Substitution>,
+    /// Whisper code of the language the segment was transcribed in.
+    pub language: String,
+    /// What polish was given: the transcript after dictionary replacements.
+    pub raw: String,
+    /// Polish output the validator refused.
+    pub rejected: Vec<Rejected>,
+}
+
+/// Everything after ASR: voice commands, the personal dictionary, optional polish, injection.
+/// `languages` tells polish what it is cleaning and whether to translate it; `on_partial` gets
+/// the polished text so far while it streams in.
+pub(crate) fn process_text(raw_text: &str, polish: Option<&dyn PolishBackend>, ctx: &AppContext, vocab: &[DictEntry], languages: Languages, injector: &dyn Injector, on_partial: &dyn Fn(&str)) -> Result<Dictated> {
+    let start = std::time::Instant::now();
+
+    let cmd = commands::parse_command(raw_text);
     if let Some(text) = commands::command_text(&cmd) {
-        clipboard::inject_text(text)?;
-        return Ok((0, 0.0));
+        injector.inject(text)?;
+        return Ok(Dictated::default());
     }
+    let VoiceCommand::None(text) = &cmd else {
+        return Ok(Dictated::default());
+    };
 
-    let use_polish = POLISH_ENABLED.load(Ordering::Relaxed);
+    // Applied before polish so the LLM already sees the written forms
+    let (text, mut substitutions) = dictionary::apply(text, vocab);
+    let (text, fuzzy) = phonetic::correct(&text, vocab, phonetic::DEFAULT_THRESHOLD);
+    substitutions.extend(fuzzy);
+    if !substitutions.is_empty() {
+        let changed = substitutions.iter().filter(|s| s.changed()).count();
+        tracing::info!("Dictionary: {} match(es), {} substitution(s)", substitutions.len(), changed);
+    }
 
-    let final_text = match (&cmd, polish) {
-        (VoiceCommand::None(text), Some(engine)) if use_polish => {
-            let ctx = get_active_app();
-            let sys_prompt = prompt::build_system_prompt(&ctx, &[]);
-            match engine.generate(&sys_prompt, text, 256) {
-                Ok(polished) => polished,
-                Err(e) => {
-                    tracing::warn!("LLM polish failed, using raw: {}", e);
-                    text.clone()
-                }
+    let mut rejected = Vec::new();
+    let final_text = match polish {
+        Some(engine) if POLISH_ENABLED.load(Ordering::Relaxed) => {
+            let sys_prompt = prompt::build_system_prompt(ctx, &dictionary::prompt_entries(vocab), languages);
+            let translating = languages.output.is_some();
+            // A translation can't be finished from the raw text, so it goes out whole
+            let incremental = injector.incremental() && !translating;
+            let (polished, refused) = polish_and_inject(engine, &sys_prompt, &text, injector, incremental, translating, on_partial)?;
+            for r in &refused {
+                tracing::warn!("Polish rejected ({}): {}; raw {:?}, polished {:?}", r.rejection.reason(), r.rejection, text, r.polished);
             }
+            rejected = refused;
+            polished
+        }
+        _ => {
+            if let Some(output) = languages.output {
+                tracing::warn!("Translation into {} needs polish; injecting untranslated", output);
+            }
+            injector.inject(&text)?;
+            text.clone()
         }
-        (VoiceCommand::None(text), _) => text.clone(),
-        _ => return Ok((0, 0.0)),
     };
 
-    let word_count = final_text.split_whitespace().count();
-    let elapsed = start.elapsed().as_secs_f64();
-    tracing::info!("Total pipeline ({:?}): {}", start.elapsed(), &final_text);
-    clipboard::inject_text(&final_text)?;
+    let words = final_text.split_whitespace().count();
+    tracing::info!("Polish + inject ({:?}): {}", start.elapsed(), &final_text);
+    Ok(Dictated { words, text: final_text, substitutions, raw: text, rejected, ..Default::default() })
+}
 
-    // Record app usage for hint generation
-    let ctx = get_active_app();
-    if let Ok(conn) = crate::db::schema::init_db(&crate::config::AppConfig::default().db_path) {
-        let _ = crate::db::hints::record_usage(&conn, &ctx.app_name);
+/// Stream the polish of `text` and inject the result, returning what was injected and any
+/// output the validator refused. With `incremental`, each sentence is checked and injected as
+/// soon as it is complete, and if the stream then fails or is refused the raw text not yet
+/// covered follows. Otherwise a refused reply gets one retry with a stricter prompt, and any
+/// other failure injects the raw text.
+fn polish_and_inject(engine: &dyn PolishBackend, sys_prompt: &str, text: &str, injector: &dyn Injector, incremental: bool, translating: bool, on_partial: &dyn Fn(&str)) -> Result<(String, Vec<Rejected>)> {
+    let mut sentences = SentenceStream::new();
+    let mut inject_error = None;
+    let mut rejection = None;
+    let result = engine.generate_stream(sys_prompt, text, 256, &mut |token| {
+        sentences.push(token);
+        on_partial(sentences.text());
+        if incremental && inject_error.is_none() && rejection.is_none() {
+            if let Some(ready) = sentences.peek() {
+                // Once typed it can't be taken back
+                let typed = format!("{}{}", sentences.emitted(), ready);
+                rejection = validate::check_partial(text, &typed, translating).err();
+                if rejection.is_none() {
+                    let ready = sentences.ready().unwrap_or_default();
+                    inject_error = injector.inject(&ready).err();
+                }
+            }
+        }
+    });
+    if let Some(e) = inject_error {
+        return Err(e);
     }
-    crate::LAST_DICTATION.store(
-        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs(),
-        std::sync::atomic::Ordering::Relaxed,
-    );
 
-    Ok((word_count, elapsed))
+    let rejection = rejection.or_else(|| {
+        result.as_ref().ok().and_then(|_| validate::check(text, sentences.text(), translating).err())
+    });
+    let mut rejected = Vec::new();
+    if let Some(rejection) = rejection {
+        rejected.push(Rejected::new(rejection, sentences.text()));
+    }
+
+    let final_text = match result {
+        Ok(_) if rejected.is_empty() => {
+            if !sentences.rest().is_empty() {
+                injector.inject(sentences.rest())?;
+            }
+            sentences.text().to_string()
+        }
+        _ if !sentences.emitted().is_empty() => {
+            let rest = stream::raw_rest(text, sentences.emitted());
+            match &result {
+                Err(e) => tracing::warn!("LLM polish failed partway, finishing with raw {:?}: {}", rest, e),
+                Ok(_) => tracing::warn!("LLM polish rejected partway, finishing with raw {:?}", rest),
+            }
+            if !rest.is_empty() {
+                injector.inject(rest)?;
+            }
+            format!("{}{}", sentences.emitted(), rest).trim_end().to_string()
+        }
+        Err(e) => {
+            tracing::warn!("LLM polish failed, using raw: {}", e);
+            injector.inject(text)?;
+            text.to_string()
+        }
+        Ok(_) => {
+            let retried = engine.generate(&prompt::strict(sys_prompt), text, 256)
+                .map_err(|e| tracing::warn!("LLM polish retry failed: {}", e))
+                .ok()
+                .and_then(|polished| match validate::check(text, &polished, translating) {
+                    Ok(()) => Some(polished),
+                    Err(rejection) => {
+                        rejected.push(Rejected::new(rejection, &polished));
+                        None
+                    }
+                });
+            let final_text = retried.unwrap_or_else(|| {
+                tracing::warn!("LLM polish rejected twice, using raw");
+                text.to_string()
+            });
+            injector.inject(&final_text)?;
+            final_text
+        }
+    };
+    Ok((final_text, rejected))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::asr::engine::AsrEngine;
     use crate::config::AppConfig;
+    use crate::inject::injector::RecordingInjector;
+    use std::sync::Mutex;
 
     fn asr_model_path() -> std::path::PathBuf {
         let cfg = AppConfig::default();
@@ -138,13 +342,190 @@ mod tests {
         POLISH_ENABLED.store(true, Ordering::Relaxed); // restore
     }
 
+    #[test]
+    fn process_text_injects_command_text() {
+        let inj = RecordingInjector::new();
+        let words = process_text("new paragraph", None, &AppContext::default(), &[], Languages::default(), &inj, &|_| {}).unwrap().words;
+        assert_eq!(words, 0);
+        assert_eq!(inj.injected(), vec!["\n\n".to_string()]);
+    }
+
+    #[test]
+    fn process_text_injects_raw_without_polish() {
+        let inj = RecordingInjector::new();
+        let words = process_text("deploy the new version", None, &AppContext::default(), &[], Languages::default(), &inj, &|_| {}).unwrap().words;
+        assert_eq!(words, 4);
+        assert_eq!(inj.injected(), vec!["deploy the new version".to_string()]);
+    }
+
+    #[test]
+    fn process_text_editing_command_injects_nothing() {
+        let inj = RecordingInjector::new();
+        let words = process_text("scratch that", None, &AppContext::default(), &[], Languages::default(), &inj, &|_| {}).unwrap().words;
+        assert_eq!(words, 0);
+        assert!(inj.injected().is_empty());
+    }
+
+    #[test]
+    fn process_text_applies_dictionary_without_polish() {
+        let inj = RecordingInjector::new();
+        let vocab = [DictEntry { id: 7, spoken: "k eight s".into(), written: "Kubernetes".into(), category: "tech".into(), usage_count: 0 }];
+        let dictated = process_text("scale the k eight s cluster", None, &AppContext::default(), &vocab, Languages::default(), &inj, &|_| {}).unwrap();
+        assert_eq!(inj.injected(), vec!["scale the Kubernetes cluster".to_string()]);
+        assert_eq!(dictated.words, 4);
+        assert_eq!(dictated.text, "scale the Kubernetes cluster");
+        assert_eq!(dictated.substitutions.len(), 1);
+        assert_eq!(dictated.substitutions[0].entry_id, 7);
+    }
+
+    #[test]
+    fn process_text_counts_matches_already_spelled_right() {
+        let inj = RecordingInjector::new();
+        let vocab = [DictEntry { id: 9, spoken: "kubectl".into(), written: "kubectl".into(), category: "tech".into(), usage_count: 0 }];
+        let dictated = process_text("run kubectl get pods", None, &AppContext::default(), &vocab, Languages::default(), &inj, &|_| {}).unwrap();
+        assert_eq!(dictated.substitutions.len(), 1);
+        assert!(!dictated.substitutions[0].changed());
+    }
+
+    #[test]
+    fn process_text_reports_phonetic_corrections() {
+        let inj = RecordingInjector::new();
+        let vocab = [DictEntry { id: 3, spoken: "cube control".into(), written: "kubectl".into(), category: "tech".into(), usage_count: 0 }];
+        let dictated = process_text("run kube cuttle get pods", None, &AppContext::default(), &vocab, Languages::default(), &inj, &|_| {}).unwrap();
+        assert_eq!(inj.injected(), vec!["run kubectl get pods".to_string()]);
+        assert_eq!(dictated.substitutions[0].from, "kube cuttle");
+    }
+
+    #[test]
+    fn process_text_without_polish_leaves_translation_undone() {
+        let inj = RecordingInjector::new();
+        let languages = Languages { spoken: "Hindi", output: Some("German") };
+        let dictated = process_text("namaste sab log", None, &AppContext::default(), &[], languages, &inj, &|_| {}).unwrap();
+        assert_eq!(dictated.words, 3);
+        assert_eq!(inj.injected(), vec!["namaste sab log".to_string()]);
+    }
+
+    /// Streams canned tokens, failing after `fail_after` of them if set, and answers the
+    /// stricter retry with `retry`.
+    struct Streamed {
+        tokens: Vec<&'static str>,
+        fail_after: Option<usize>,
+        retry: &'static str,
+    }
+
+    impl Streamed {
+        fn new(tokens: &[&'static str]) -> Self {
+            Self { tokens: tokens.to_vec(), fail_after: None, retry: "" }
+        }
+    }
+
+    impl PolishBackend for Streamed {
+        fn name(&self) -> &str { "streamed" }
+
+        fn generate_json(&self, _: &str, _: &str, _: i32, _: &serde_json::Value) -> Result<serde_json::Value> {
+            unreachable!("polish asks for text")
+        }
+
+        fn generate(&self, system_prompt: &str, _: &str, _: i32) -> Result<String> {
+            assert!(system_prompt.starts_with("WARNING"), "only the retry is not streamed");
+            Ok(self.retry.to_string())
+        }
+
+        fn generate_stream(&self, _: &str, _: &str, _: i32, on_token: &mut dyn FnMut(&str)) -> Result<String> {
+            for (i, token) in self.tokens.iter().enumerate() {
+                if self.fail_after == Some(i) {
+                    anyhow::bail!("connection reset");
+                }
+                on_token(token);
+            }
+            Ok(self.tokens.concat().trim().to_string())
+        }
+
+        fn health(&self) -> Result<()> { Ok(()) }
+    }
+
+    const RAW: &str = "um so we should deploy today and then uh tell the team";
+    const TOKENS: [&str; 6] = [" So we should", " deploy today.", " Then", " tell the", " team", "."];
+
+    #[test]
+    fn polish_types_sentences_as_they_stream() {
+        let inj = RecordingInjector::typing();
+        let partials = Mutex::new(Vec::new());
+        let engine = Streamed::new(&TOKENS);
+        let (text, rejected) = polish_and_inject(&engine, "", RAW, &inj, true, false, &|p| partials.lock().unwrap().push(p.to_string())).unwrap();
+        assert!(rejected.is_empty());
+        assert_eq!(text, "So we should deploy today. Then tell the team.");
+        assert_eq!(inj.injected(), vec!["So we should deploy today. ".to_string(), "Then tell the team.".to_string()]);
+        let partials = partials.into_inner().unwrap();
+        assert_eq!(partials.len(), TOKENS.len());
+        assert_eq!(partials[0], "So we should");
+    }
+
+    #[test]
+    fn polish_pastes_whole_text_when_not_typing() {
+        let inj = RecordingInjector::new();
+        let engine = Streamed::new(&TOKENS);
+        polish_and_inject(&engine, "", RAW, &inj, false, false, &|_| {}).unwrap();
+        assert_eq!(inj.injected(), vec!["So we should deploy today. Then tell the team.".to_string()]);
+    }
+
+    #[test]
+    fn polish_failure_partway_finishes_with_raw() {
+        let inj = RecordingInjector::typing();
+        let engine = Streamed { fail_after: Some(4), ..Streamed::new(&TOKENS) };
+        let (text, _) = polish_and_inject(&engine, "", RAW, &inj, true, false, &|_| {}).unwrap();
+        assert_eq!(inj.injected(), vec!["So we should deploy today. ".to_string(), "and then uh tell the team".to_string()]);
+        assert_eq!(text, "So we should deploy today. and then uh tell the team");
+    }
+
+    #[test]
+    fn polish_failure_before_a_sentence_injects_raw() {
+        let inj = RecordingInjector::typing();
+        let engine = Streamed { fail_after: Some(1), ..Streamed::new(&TOKENS) };
+        assert_eq!(polish_and_inject(&engine, "", RAW, &inj, true, false, &|_| {}).unwrap().0, RAW);
+        assert_eq!(inj.injected(), vec![RAW.to_string()]);
+    }
+
+    #[test]
+    fn rejected_polish_retries_with_strict_prompt() {
+        let inj = RecordingInjector::new();
+        let engine = Streamed { retry: "So we should deploy today, then tell the team.", ..Streamed::new(&["Sure! Here's the text:", " So we should deploy today."]) };
+        let (text, rejected) = polish_and_inject(&engine, "", RAW, &inj, false, false, &|_| {}).unwrap();
+        assert_eq!(text, "So we should deploy today, then tell the team.");
+        assert_eq!(inj.injected(), vec![text]);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].rejection.reason(), "preamble");
+        assert_eq!(rejected[0].polished, "Sure! Here's the text: So we should deploy today.");
+    }
+
+    #[test]
+    fn polish_rejected_twice_injects_raw() {
+        let inj = RecordingInjector::new();
+        let engine = Streamed { retry: "Sure, done.", ..Streamed::new(&["Great question!"]) };
+        let (text, rejected) = polish_and_inject(&engine, "", RAW, &inj, false, false, &|_| {}).unwrap();
+        assert_eq!(text, RAW);
+        assert_eq!(inj.injected(), vec![RAW.to_string()]);
+        assert_eq!(rejected.len(), 2);
+    }
+
+    #[test]
+    fn typed_polish_stops_at_rejected_sentence() {
+        let inj = RecordingInjector::typing();
+        let tokens = [" So we should deploy today.", " Also remember quarterly budget planning meetings happen", " Fridays."];
+        let engine = Streamed::new(&tokens);
+        let (text, rejected) = polish_and_inject(&engine, "", RAW, &inj, true, false, &|_| {}).unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(inj.injected(), vec!["So we should deploy today. ".to_string(), "and then uh tell the team".to_string()]);
+        assert_eq!(text, "So we should deploy today. and then uh tell the team");
+    }
+
     #[test]
     #[ignore] // requires ASR model
     fn process_segment_silence_returns_zero() {
         let asr = AsrEngine::new(&asr_model_path()).unwrap();
         let silence = vec![0.0f32; 16000];
-        let (words, _) = process_segment(&asr, None, &silence).unwrap();
-        assert_eq!(words, 0);
+        let (dictated, _) = process_segment(&asr, None, &silence, "", &|_| {}).unwrap();
+        assert_eq!(dictated.words, 0);
     }
 
     #[test]
@@ -154,7 +535,7 @@ mod tests {
         POLISH_ENABLED.store(false, Ordering::Relaxed);
         // 2 seconds of tone — ASR will produce something (possibly noise text)
         let audio: Vec<f32> = (0..32000).map(|i| (2.0 * std::f32::consts::PI * 200.0 * i as f32 / 16000.0).sin() * 0.5).collect();
-        let result = process_segment(&asr, None, &audio);
+        let result = process_segment(&asr, None, &audio, "", &|_| {});
         // Should not panic regardless of output
         assert!(result.is_ok());
         POLISH_ENABLED.store(true, Ordering::Relaxed);
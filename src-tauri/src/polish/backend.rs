@@ -0,0 +1,362 @@
+//! What polish needs from an LLM, and which one to use: the app's own llama-server, any
+//! OpenAI-compatible server, or Ollama.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::engine::PolishEngine;
+use super::ollama::Ollama;
+use super::openai::OpenAiCompatible;
+use super::structured;
+use super::supervisor::StateListener;
+use crate::db::settings;
+
+const SETTING_KEY: &str = "polish_backend";
+const DEFAULT_TIMEOUT_SECS: u64 = 15;
+pub const DEFAULT_OLLAMA_URL: &str = "http://127.0.0.1:11434";
+/// Stands in for a saved API key in logs and in what the UI is sent. Sent back unchanged,
+/// it keeps the saved key.
+pub const MASKED_KEY: &str = "********";
+
+pub trait PolishBackend: Send + Sync {
+    /// Short name for logs ("llama-server", "ollama:qwen2.5:3b").
+    fn name(&self) -> &str;
+
+    /// One chat completion with the reply held to the JSON `schema` (a grammar, on
+    /// llama.cpp), parsed.
+    fn generate_json(&self, system_prompt: &str, user_text: &str, max_tokens: i32, schema: &serde_json::Value) -> Result<serde_json::Value>;
+
+    /// The polished text for `user_text` under `system_prompt`: a reply held to
+    /// `{"text": …}`, so the model can't add anything around it.
+    fn generate(&self, system_prompt: &str, user_text: &str, max_tokens: i32) -> Result<String> {
+        structured::text_field(&self.generate_json(system_prompt, user_text, max_tokens, &structured::text_schema())?)
+    }
+
+    /// Like `generate`, passing each piece of the reply to `on_token` as it arrives. Backends
+    /// that can't stream hand over the whole reply at once.
+    fn generate_stream(&self, system_prompt: &str, user_text: &str, max_tokens: i32, on_token: &mut dyn FnMut(&str)) -> Result<String> {
+        let text = self.generate(system_prompt, user_text, max_tokens)?;
+        on_token(&text);
+        Ok(text)
+    }
+
+    /// Ok if the server answers and has the model.
+    fn health(&self) -> Result<()>;
+
+    /// Push a tiny prompt through so the model is paged in and the prompt cache primed
+    /// before the first dictation.
+    fn warm_up(&self) -> Result<()> {
+        self.generate("Repeat the user's text.", "Hello.", 16).map(|_| ())
+    }
+
+    /// Stop any process the backend runs, before the app exits.
+    fn shutdown(&self) {}
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn default_ollama_url() -> String {
+    DEFAULT_OLLAMA_URL.into()
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum PolishChoice {
+    /// llama-server started by the app with the active GGUF model.
+    Managed {
+        #[serde(default = "default_timeout")]
+        timeout_secs: u64,
+    },
+    /// A server speaking the OpenAI chat completions API: a shared llama.cpp, vLLM,
+    /// LM Studio or a hosted API.
+    OpenAi {
+        url: String,
+        #[serde(default)]
+        api_key: Option<String>,
+        /// Sent as `model`; servers with a single model ignore it.
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default = "default_timeout")]
+        timeout_secs: u64,
+    },
+    /// Ollama's native API.
+    Ollama {
+        #[serde(default = "default_ollama_url")]
+        url: String,
+        model: String,
+        #[serde(default = "default_timeout")]
+        timeout_secs: u64,
+    },
+}
+
+impl Default for PolishChoice {
+    fn default() -> Self {
+        PolishChoice::Managed { timeout_secs: DEFAULT_TIMEOUT_SECS }
+    }
+}
+
+/// By hand so the API key never ends up in a log.
+impl fmt::Debug for PolishChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolishChoice::Managed { timeout_secs } => f.debug_struct("Managed")
+                .field("timeout_secs", timeout_secs)
+                .finish(),
+            PolishChoice::OpenAi { url, api_key, model, timeout_secs } => f.debug_struct("OpenAi")
+                .field("url", url)
+                .field("api_key", &api_key.as_ref().map(|_| MASKED_KEY))
+                .field("model", model)
+                .field("timeout_secs", timeout_secs)
+                .finish(),
+            PolishChoice::Ollama { url, model, timeout_secs } => f.debug_struct("Ollama")
+                .field("url", url)
+                .field("model", model)
+                .field("timeout_secs", timeout_secs)
+                .finish(),
+        }
+    }
+}
+
+impl PolishChoice {
+    pub fn timeout(&self) -> Duration {
+        let secs = match self {
+            PolishChoice::Managed { timeout_secs }
+            | PolishChoice::OpenAi { timeout_secs, .. }
+            | PolishChoice::Ollama { timeout_secs, .. } => *timeout_secs,
+        };
+        Duration::from_secs(secs)
+    }
+
+    /// The choice as the UI gets to see it, with the API key masked.
+    pub fn masked(&self) -> Self {
+        match self {
+            PolishChoice::OpenAi { url, api_key: Some(_), model, timeout_secs } => PolishChoice::OpenAi {
+                url: url.clone(),
+                api_key: Some(MASKED_KEY.into()),
+                model: model.clone(),
+                timeout_secs: *timeout_secs,
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// A choice back from the UI, with a still-masked API key swapped for the one in `saved`.
+    pub fn unmasked(mut self, saved: &PolishChoice) -> Self {
+        if let PolishChoice::OpenAi { api_key, .. } = &mut self {
+            if api_key.as_deref() == Some(MASKED_KEY) {
+                *api_key = match saved {
+                    PolishChoice::OpenAi { api_key, .. } => api_key.clone(),
+                    _ => None,
+                };
+            }
+        }
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.timeout().is_zero() {
+            anyhow::bail!("Timeout must be at least a second");
+        }
+        match self {
+            PolishChoice::Managed { .. } => {}
+            PolishChoice::OpenAi { url, .. } => check_url(url)?,
+            PolishChoice::Ollama { url, model, .. } => {
+                check_url(url)?;
+                if model.trim().is_empty() {
+                    anyhow::bail!("Ollama needs a model name");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_url(url: &str) -> Result<()> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        anyhow::bail!("Server URL must start with http:// or https://: {}", url);
+    }
+    Ok(())
+}
+
+pub fn load_choice(conn: &Connection) -> PolishChoice {
+    settings::get(conn, SETTING_KEY).ok().flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_choice(conn: &Connection, choice: &PolishChoice) -> Result<()> {
+    choice.validate()?;
+    settings::set(conn, SETTING_KEY, &serde_json::to_string(choice)?)
+}
+
+/// Connect to the chosen backend, starting llama-server with `managed_model` if that's the
+/// choice; its state changes go to `on_state`. External servers must pass a health check.
+/// Slow: run it off the async runtime.
+pub fn load(choice: &PolishChoice, managed_model: Option<&Path>, on_state: StateListener) -> Result<Arc<dyn PolishBackend>> {
+    let timeout = choice.timeout();
+    let backend: Arc<dyn PolishBackend> = match choice {
+        PolishChoice::Managed { .. } => {
+            let path = managed_model.ok_or_else(|| anyhow::anyhow!("LLM model not found"))?;
+            return Ok(Arc::new(PolishEngine::new(path, timeout, on_state)?));
+        }
+        PolishChoice::OpenAi { url, api_key, model, .. } => {
+            Arc::new(OpenAiCompatible::new(url, api_key.as_deref(), model.as_deref(), timeout))
+        }
+        PolishChoice::Ollama { url, model, .. } => Arc::new(Ollama::new(url, model, timeout)),
+    };
+    backend.health()
+        .map_err(|e| anyhow::anyhow!("{} is not usable: {}", backend.name(), e))?;
+    Ok(backend)
+}
+
+pub(crate) fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::Agent::new_with_config(
+        ureq::config::Config::builder()
+            .timeout_global(Some(timeout))
+            .build()
+    )
+}
+
+/// Server base URL without a trailing slash.
+pub(crate) fn base_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
+
+pub(crate) fn non_empty(text: &str) -> Result<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        anyhow::bail!("LLM returned empty response")
+    }
+    Ok(text.to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    //! A local HTTP server that replays canned replies and records what it was sent.
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    pub struct Request {
+        pub method: String,
+        pub path: String,
+        pub authorization: Option<String>,
+        pub body: serde_json::Value,
+    }
+
+    pub struct MockServer {
+        pub url: String,
+        server: Arc<tiny_http::Server>,
+        requests: Arc<Mutex<Vec<Request>>>,
+    }
+
+    impl MockServer {
+        /// Answers requests in order with `replies` (status, JSON body), then 404. A string body
+        /// is sent as-is as a server-sent event stream.
+        pub fn start(replies: Vec<(u16, serde_json::Value)>) -> Self {
+            Self::start_with_delay(replies, Duration::ZERO)
+        }
+
+        pub fn start_with_delay(replies: Vec<(u16, serde_json::Value)>, delay: Duration) -> Self {
+            let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+            let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let (srv, log) = (server.clone(), requests.clone());
+            std::thread::spawn(move || {
+                let mut replies = replies.into_iter();
+                for mut request in srv.incoming_requests() {
+                    let mut body = String::new();
+                    let _ = request.as_reader().read_to_string(&mut body);
+                    log.lock().unwrap().push(Request {
+                        method: request.method().to_string(),
+                        path: request.url().to_string(),
+                        authorization: request.headers().iter()
+                            .find(|h| h.field.equiv("Authorization"))
+                            .map(|h| h.value.to_string()),
+                        body: serde_json::from_str(&body).unwrap_or_default(),
+                    });
+                    std::thread::sleep(delay);
+                    let (status, reply) = replies.next().unwrap_or((404, serde_json::json!({"error": "not found"})));
+                    let (content_type, body) = match reply {
+                        serde_json::Value::String(events) => ("text/event-stream", events),
+                        json => ("application/json", json.to_string()),
+                    };
+                    let header = tiny_http::Header::from_bytes("Content-Type", content_type).unwrap();
+                    let response = tiny_http::Response::from_string(body)
+                        .with_status_code(status)
+                        .with_header(header);
+                    let _ = request.respond(response);
+                }
+            });
+            Self { url, server, requests }
+        }
+
+        pub fn requests(&self) -> Vec<Request> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl Drop for MockServer {
+        fn drop(&mut self) {
+            self.server.unblock();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    #[test]
+    fn choice_roundtrip() {
+        let conn = schema::init_db(Path::new(":memory:")).unwrap();
+        assert_eq!(load_choice(&conn), PolishChoice::default());
+        let ollama = PolishChoice::Ollama { url: DEFAULT_OLLAMA_URL.into(), model: "qwen2.5:3b".into(), timeout_secs: 30 };
+        save_choice(&conn, &ollama).unwrap();
+        assert_eq!(load_choice(&conn), ollama);
+    }
+
+    #[test]
+    fn choice_defaults_and_validation() {
+        let parsed: PolishChoice = serde_json::from_str(r#"{"kind":"ollama","model":"llama3.2"}"#).unwrap();
+        assert_eq!(parsed, PolishChoice::Ollama { url: DEFAULT_OLLAMA_URL.into(), model: "llama3.2".into(), timeout_secs: 15 });
+        let parsed: PolishChoice = serde_json::from_str(r#"{"kind":"openai","url":"http://gpu-box:8080"}"#).unwrap();
+        assert_eq!(parsed.timeout(), Duration::from_secs(15));
+
+        assert!(PolishChoice::OpenAi { url: "gpu-box:8080".into(), api_key: None, model: None, timeout_secs: 15 }.validate().is_err());
+        assert!(PolishChoice::Ollama { url: DEFAULT_OLLAMA_URL.into(), model: " ".into(), timeout_secs: 15 }.validate().is_err());
+        assert!(PolishChoice::Managed { timeout_secs: 0 }.validate().is_err());
+    }
+
+    #[test]
+    fn api_key_stays_out_of_logs_and_ui() {
+        let saved = PolishChoice::OpenAi { url: "https://api.example.com".into(), api_key: Some("sk-secret".into()), model: None, timeout_secs: 15 };
+        assert!(!format!("{:?}", saved).contains("sk-secret"));
+        let shown = saved.masked();
+        assert!(!serde_json::to_string(&shown).unwrap().contains("sk-secret"));
+
+        assert_eq!(shown.clone().unmasked(&saved), saved);
+        let edited = PolishChoice::OpenAi { url: "https://api.example.com".into(), api_key: Some("sk-new".into()), model: None, timeout_secs: 15 };
+        assert_eq!(edited.clone().unmasked(&saved), edited);
+        let switched = shown.unmasked(&PolishChoice::default());
+        assert!(matches!(switched, PolishChoice::OpenAi { api_key: None, .. }));
+    }
+
+    #[test]
+    fn load_fails_health_check() {
+        let server = mock::MockServer::start(vec![(500, serde_json::json!({"error": "loading"}))]);
+        let choice = PolishChoice::OpenAi { url: server.url.clone(), api_key: None, model: None, timeout_secs: 2 };
+        let err = load(&choice, None, Arc::new(|_| {})).err().unwrap();
+        assert!(err.to_string().contains("not usable"));
+        assert!(load(&PolishChoice::default(), None, Arc::new(|_| {})).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Deterministic personal-dictionary pass over ASR output. Runs whether or not polish is on.
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use crate::db::dictionary::DictEntry;
+
+/// One dictionary match in the transcript. Matches that changed the text are reported
+/// back to the UI; every match counts towards the entry's usage.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Substitution {
+    pub entry_id: i64,
+    pub from: String,
+    pub to: String,
+}
+
+impl Substitution {
+    /// The written form differs from what was heard (an exact spelling matches too).
+    pub fn changed(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+/// Replace spoken forms with written forms, case-insensitively and on word boundaries.
+/// Longer spoken forms win, and replaced text is never matched again. Returns every match,
+/// including those already in their written form.
+pub fn apply(text: &str, entries: &[DictEntry]) -> (String, Vec<Substitution>) {
+    let mut ordered: Vec<&DictEntry> = entries.iter()
+        .filter(|e| !e.spoken.trim().is_empty())
+        .collect();
+    ordered.sort_by_key(|e| std::cmp::Reverse(e.spoken.len()));
+    let Some(re) = build_pattern(&ordered) else {
+        return (text.to_string(), Vec::new());
+    };
+
+    let mut subs = Vec::new();
+    let out = re.replace_all(text, |caps: &regex::Captures| {
+        // Group i + 1 is entry i; exactly one of them participated
+        let (i, m) = (0..ordered.len())
+            .find_map(|i| caps.get(i + 1).map(|m| (i, m)))
+            .expect("one alternative matched");
+        let entry = ordered[i];
+        subs.push(Substitution { entry_id: entry.id, from: m.as_str().to_string(), to: entry.written.clone() });
+        entry.written.clone()
+    });
+    (out.into_owned(), subs)
+}
+
+/// One alternation with a capture group per entry. Words of a spoken form may be separated
+/// by any whitespace or hyphens, since ASR is inconsistent about both.
+fn build_pattern(entries: &[&DictEntry]) -> Option<Regex> {
+    if entries.is_empty() {
+        return None;
+    }
+    let alternatives: Vec<String> = entries.iter()
+        .map(|e| {
+            let spoken = e.spoken.trim();
+            let body = spoken.split_whitespace()
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(r"[\s\-]+");
+            // \b only means something next to a word character ("c++" has none at the end)
+            let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+            let start = if is_word(spoken.chars().next()) { r"\b" } else { "" };
+            let end = if is_word(spoken.chars().last()) { r"\b" } else { "" };
+            format!("{}({}){}", start, body, end)
+        })
+        .collect();
+    RegexBuilder::new(&alternatives.join("|"))
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| tracing::warn!("Dictionary pattern failed to compile: {}", e))
+        .ok()
+}
+
+/// Entries formatted for the polish prompt.
+pub fn prompt_entries(entries: &[DictEntry]) -> Vec<String> {
+    entries.iter().map(|e| format!("{} → {}", e.spoken, e.written)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i64, spoken: &str, written: &str) -> DictEntry {
+        DictEntry { id, spoken: spoken.into(), written: written.into(), category: "general".into(), usage_count: 0 }
+    }
+
+    #[test]
+    fn replaces_spoken_form() {
+        let dict = [entry(1, "k eight s", "Kubernetes")];
+        let (out, subs) = apply("deploy it to K Eight S today", &dict);
+        assert_eq!(out, "deploy it to Kubernetes today");
+        assert_eq!(subs, vec![Substitution { entry_id: 1, from: "K Eight S".into(), to: "Kubernetes".into() }]);
+    }
+
+    #[test]
+    fn respects_word_boundaries() {
+        let dict = [entry(1, "rust", "Rust")];
+        let (out, subs) = apply("trust the rust compiler, rusty", &dict);
+        assert_eq!(out, "trust the Rust compiler, rusty");
+        assert_eq!(subs.len(), 1);
+    }
+
+    #[test]
+    fn longest_spoken_form_wins() {
+        let dict = [entry(1, "cube", "Kube"), entry(2, "cube control", "kubectl")];
+        let (out, subs) = apply("run cube control apply, then cube", &dict);
+        assert_eq!(out, "run kubectl apply, then Kube");
+        assert_eq!(subs.iter().map(|s| s.entry_id).collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn replacements_are_not_rematched() {
+        let dict = [entry(1, "g r p c", "gRPC"), entry(2, "grpc", "GRPC-legacy")];
+        let (out, _) = apply("use g-r-p-c", &dict);
+        assert_eq!(out, "use gRPC");
+    }
+
+    #[test]
+    fn exact_written_form_matches_without_changing() {
+        let dict = [entry(1, "kubernetes", "Kubernetes")];
+        let (out, subs) = apply("Kubernetes and kubernetes", &dict);
+        assert_eq!(out, "Kubernetes and Kubernetes");
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs.iter().filter(|s| s.changed()).count(), 1);
+    }
+
+    #[test]
+    fn symbols_and_empty_dict() {
+        let dict = [entry(1, "c plus plus", "C++"), entry(2, "c++", "C++"), entry(3, "  ", "x")];
+        assert_eq!(apply("I write c plus plus and c++.", &dict).0, "I write C++ and C++.");
+        assert_eq!(apply("nothing here", &[]).0, "nothing here");
+    }
+}
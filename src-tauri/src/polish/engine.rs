@@ -1,140 +1,106 @@
 use anyhow::Result;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
+use std::process::Command;
+use std::time::Duration;
 
+use super::backend::{self, PolishBackend};
+use super::openai::Endpoint;
+use super::supervisor::{ServerSpec, StateListener, Supervisor};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// llama-server run by the app on a loopback port of its own, kept up by a supervisor.
 pub struct PolishEngine {
-    model_path: PathBuf,
-    server: Mutex<Option<Child>>,
-    port: u16,
+    server: Supervisor,
+    agent: ureq::Agent,
 }
 
 impl PolishEngine {
-    pub fn new(model_path: &Path) -> Result<Self> {
+    /// Start llama-server with `model_path` and wait until it is ready. Server state
+    /// changes, including crashes and restarts, go to `on_state`.
+    pub fn new(model_path: &Path, timeout: Duration, on_state: StateListener) -> Result<Self> {
         if !model_path.exists() {
             anyhow::bail!("LLM model not found: {}", model_path.display());
         }
-        let port = 8384;
-        let engine = Self {
-            model_path: model_path.to_path_buf(),
-            server: Mutex::new(None),
-            port,
+        let bin = Self::find_llama_server()
+            .ok_or_else(|| anyhow::anyhow!("llama-server not found"))?;
+        let model = model_path.to_path_buf();
+        let spec = ServerSpec {
+            name: "llama-server".into(),
+            command: Box::new(move |port, key_file| {
+                let mut cmd = Command::new(&bin);
+                cmd.arg("-m").arg(&model)
+                    .args(["--host", "127.0.0.1", "--port", &port.to_string()])
+                    .arg("--api-key-file").arg(key_file)
+                    .args(["-ngl", "99"]);
+                cmd
+            }),
+            is_ready: Box::new(|port, _| {
+                ureq::get(format!("http://127.0.0.1:{}/health", port)).call().is_ok()
+            }),
+            startup_timeout: STARTUP_TIMEOUT,
         };
-        engine.ensure_server()?;
-        Ok(engine)
+        let log_path = crate::config::AppConfig::default().logs_dir.join("llama-server.log");
+        Ok(Self {
+            server: Supervisor::start(spec, &log_path, on_state)?,
+            agent: backend::agent(timeout),
+        })
     }
 
-    fn find_llama_server() -> Option<String> {
+    fn find_llama_server() -> Option<PathBuf> {
         // Check our bundled copy first
         let bundled = crate::config::AppConfig::default()
             .models_dir.join("llama-server");
-        if bundled.exists() { return Some(bundled.to_string_lossy().into()); }
+        if bundled.exists() { return Some(bundled); }
 
         let candidates = [
             "/opt/homebrew/bin/llama-server",
             "/usr/local/bin/llama-server",
         ];
         for p in candidates {
-            if std::path::Path::new(p).exists() { return Some(p.to_string()); }
+            if std::path::Path::new(p).exists() { return Some(p.into()); }
         }
         Command::new("which").arg("llama-server")
             .output().ok()
             .and_then(|o| String::from_utf8(o.stdout).ok())
             .map(|s| s.trim().to_string())
             .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
     }
 
-    fn ensure_server(&self) -> Result<()> {
-        let mut guard = self.server.lock().unwrap();
+    /// Base URL of the running server; fails while it is restarting.
+    fn base_url(&self) -> Result<String> {
+        Ok(format!("http://127.0.0.1:{}/v1", self.server.port()?))
+    }
 
-        if let Some(ref mut child) = *guard {
-            if child.try_wait()?.is_none() {
-                return Ok(());
-            }
-        }
+    fn endpoint<'a>(&'a self, base_url: &'a str) -> Endpoint<'a> {
+        Endpoint { agent: &self.agent, base_url, api_key: Some(self.server.api_key()), model: None }
+    }
+}
 
-        // Kill any orphaned llama-server on our port from a previous crash
-        let _ = Command::new("sh")
-            .args(["-c", &format!("lsof -ti tcp:{} | xargs kill -9 2>/dev/null", self.port)])
-            .status();
+impl PolishBackend for PolishEngine {
+    fn name(&self) -> &str {
+        "llama-server"
+    }
 
-        let bin = Self::find_llama_server()
-            .ok_or_else(|| anyhow::anyhow!("llama-server not found"))?;
+    fn generate_json(&self, system_prompt: &str, user_text: &str, max_tokens: i32, schema: &serde_json::Value) -> Result<serde_json::Value> {
+        let base_url = self.base_url()?;
+        self.endpoint(&base_url).chat(system_prompt, user_text, max_tokens, schema)
+    }
 
-        tracing::info!("Starting llama-server on port {}...", self.port);
-        let child = Command::new(&bin)
-            .args([
-                "-m", self.model_path.to_str().unwrap(),
-                "--port", &self.port.to_string(),
-                "-ngl", "99",
-                "--log-disable",
-            ])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn();
-
-        match child {
-            Ok(c) => {
-                *guard = Some(c);
-                drop(guard);
-                // Wait for server to be ready (max 30s)
-                let url = format!("http://127.0.0.1:{}/health", self.port);
-                for _ in 0..60 {
-                    std::thread::sleep(std::time::Duration::from_millis(500));
-                    if ureq::get(&url).call().is_ok() {
-                        tracing::info!("llama-server ready");
-                        return Ok(());
-                    }
-                }
-                anyhow::bail!("llama-server failed to start within 30s")
-            }
-            Err(e) => anyhow::bail!("Failed to start llama-server: {}", e),
-        }
+    fn generate_stream(&self, system_prompt: &str, user_text: &str, max_tokens: i32, on_token: &mut dyn FnMut(&str)) -> Result<String> {
+        let base_url = self.base_url()?;
+        self.endpoint(&base_url).chat_stream(system_prompt, user_text, max_tokens, on_token)
     }
 
-    pub fn generate(&self, system_prompt: &str, user_text: &str, max_tokens: i32) -> Result<String> {
-        self.ensure_server()?;
-
-        let url = format!("http://127.0.0.1:{}/v1/chat/completions", self.port);
-        let body = serde_json::json!({
-            "messages": [
-                {"role": "system", "content": system_prompt},
-                {"role": "user", "content": user_text}
-            ],
-            "max_tokens": max_tokens,
-            "temperature": 0.1,
-            "stream": false
-        });
-
-        let agent = ureq::Agent::new_with_config(
-            ureq::config::Config::builder()
-                .timeout_global(Some(std::time::Duration::from_secs(15)))
-                .build()
-        );
-        let mut resp = agent.post(&url)
-            .header("Content-Type", "application/json")
-            .send(&serde_json::to_vec(&body)?)?;
-
-        let body_str = resp.body_mut().read_to_string()?;
-        let json: serde_json::Value = serde_json::from_str(&body_str)?;
-        let text = json["choices"][0]["message"]["content"]
-            .as_str()
-            .unwrap_or("")
-            .trim()
-            .to_string();
-
-        if text.is_empty() {
-            anyhow::bail!("LLM returned empty response")
-        }
-        Ok(text)
+    fn health(&self) -> Result<()> {
+        let port = self.server.port()?;
+        self.agent.get(format!("http://127.0.0.1:{}/health", port)).call()?;
+        Ok(())
     }
-}
 
-impl Drop for PolishEngine {
-    fn drop(&mut self) {
-        if let Some(ref mut child) = *self.server.lock().unwrap() {
-            let _ = child.kill();
-        }
+    fn shutdown(&self) {
+        self.server.stop();
     }
 }
 
@@ -142,6 +108,7 @@ impl Drop for PolishEngine {
 mod tests {
     use super::*;
     use crate::config::AppConfig;
+    use std::sync::Arc;
 
     fn llm_model_path() -> std::path::PathBuf {
         AppConfig::default().models_dir.join("qwen2.5-3b-instruct-q4_k_m.gguf")
@@ -149,23 +116,30 @@ mod tests {
 
     #[test]
     fn new_fails_on_missing_model() {
-        let result = PolishEngine::new(std::path::Path::new("/nonexistent/model.gguf"));
+        let result = PolishEngine::new(std::path::Path::new("/nonexistent/model.gguf"), Duration::from_secs(15), Arc::new(|_| {}));
         assert!(result.is_err());
     }
 
     #[test]
     #[ignore] // requires LLM model + llama-server
     fn generate_returns_text() {
-        let engine = PolishEngine::new(&llm_model_path()).unwrap();
+        let engine = PolishEngine::new(&llm_model_path(), Duration::from_secs(15), Arc::new(|_| {})).unwrap();
         let result = engine.generate("You fix grammar.", "i went to the store yesterday and buyed some milk", 64);
         let text = result.unwrap();
         assert!(!text.is_empty());
     }
 
+    #[test]
+    #[ignore] // requires LLM model + llama-server
+    fn warm_up_succeeds() {
+        let engine = PolishEngine::new(&llm_model_path(), Duration::from_secs(15), Arc::new(|_| {})).unwrap();
+        engine.warm_up().unwrap();
+    }
+
     #[test]
     #[ignore] // requires LLM model + llama-server
     fn generate_respects_system_prompt() {
-        let engine = PolishEngine::new(&llm_model_path()).unwrap();
+        let engine = PolishEngine::new(&llm_model_path(), Duration::from_secs(15), Arc::new(|_| {})).unwrap();
         let result = engine.generate(
             "You are a dictation-to-text converter. Output ONLY the polished transcript.",
             "um so basically i think we should uh deploy the new version",
@@ -1,3 +1,12 @@
+pub mod backend;
 pub mod engine;
+pub mod ollama;
+pub mod openai;
 pub mod prompt;
 pub mod commands;
+pub mod dictionary;
+pub mod phonetic;
+pub mod stream;
+pub mod validate;
+pub mod structured;
+pub mod supervisor;
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use std::time::Duration;
+
+use super::backend::{self, PolishBackend};
+use super::structured;
+
+/// Ollama's native `/api/chat`, so a model the user already runs there can do the polish.
+pub struct Ollama {
+    name: String,
+    base_url: String,
+    model: String,
+    agent: ureq::Agent,
+}
+
+impl Ollama {
+    pub fn new(url: &str, model: &str, timeout: Duration) -> Self {
+        Self {
+            name: format!("ollama:{}", model),
+            base_url: backend::base_url(url),
+            model: model.to_string(),
+            agent: backend::agent(timeout),
+        }
+    }
+
+    /// Ollama lists untagged pulls as `name:latest`.
+    fn is_model(&self, name: &str) -> bool {
+        name == self.model || (!self.model.contains(':') && name == format!("{}:latest", self.model))
+    }
+}
+
+impl PolishBackend for Ollama {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate_json(&self, system_prompt: &str, user_text: &str, max_tokens: i32, schema: &serde_json::Value) -> Result<serde_json::Value> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": [
+                {"role": "system", "content": system_prompt},
+                {"role": "user", "content": user_text}
+            ],
+            "stream": false,
+            "format": schema,
+            "options": {"num_predict": max_tokens, "temperature": 0.1}
+        });
+        let mut resp = self.agent.post(format!("{}/api/chat", self.base_url))
+            .header("Content-Type", "application/json")
+            .send(&serde_json::to_vec(&body)?)?;
+        let json: serde_json::Value = serde_json::from_str(&resp.body_mut().read_to_string()?)?;
+        structured::parse(json["message"]["content"].as_str().unwrap_or(""))
+    }
+
+    /// The server is up and the model has been pulled.
+    fn health(&self) -> Result<()> {
+        let mut resp = self.agent.get(format!("{}/api/tags", self.base_url)).call()?;
+        let json: serde_json::Value = serde_json::from_str(&resp.body_mut().read_to_string()?)?;
+        let pulled = json["models"].as_array().into_iter().flatten()
+            .filter_map(|m| m["name"].as_str())
+            .any(|name| self.is_model(name));
+        if !pulled {
+            anyhow::bail!("model {} is not pulled (ollama pull {})", self.model, self.model);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polish::backend::mock::MockServer;
+    use serde_json::json;
+
+    #[test]
+    fn chats_with_model_and_token_limit() {
+        let server = MockServer::start(vec![(200, json!({"message": {"role": "assistant", "content": "{\"text\": \"Ship it.\"}"}, "done": true}))]);
+        let llm = Ollama::new(&server.url, "qwen2.5:3b", Duration::from_secs(5));
+        assert_eq!(llm.generate("Fix grammar.", "ship it", 32).unwrap(), "Ship it.");
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "/api/chat");
+        assert_eq!(request.body["model"], "qwen2.5:3b");
+        assert_eq!(request.body["stream"], false);
+        assert_eq!(request.body["options"]["num_predict"], 32);
+        assert_eq!(request.body["format"], structured::text_schema());
+        assert_eq!(request.body["messages"][0]["role"], "system");
+    }
+
+    #[test]
+    fn health_checks_model_is_pulled() {
+        let tags = json!({"models": [{"name": "llama3.2:latest"}, {"name": "qwen2.5:3b"}]});
+        let server = MockServer::start(vec![(200, tags.clone()), (200, tags.clone()), (200, tags)]);
+        assert!(Ollama::new(&server.url, "llama3.2", Duration::from_secs(5)).health().is_ok());
+        assert!(Ollama::new(&server.url, "qwen2.5:3b", Duration::from_secs(5)).health().is_ok());
+        let err = Ollama::new(&server.url, "mistral", Duration::from_secs(5)).health().unwrap_err();
+        assert!(err.to_string().contains("ollama pull mistral"));
+        assert_eq!(server.requests()[0].path, "/api/tags");
+    }
+
+    #[test]
+    fn unreachable_server_fails() {
+        let llm = Ollama::new("http://127.0.0.1:9", "qwen2.5:3b", Duration::from_secs(1));
+        assert!(llm.health().is_err());
+        assert!(llm.generate("s", "u", 8).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+use super::backend::{self, PolishBackend};
+use super::structured::{self, TextFieldStream};
+
+/// Any server with OpenAI's `/v1/chat/completions`, e.g. a llama.cpp server shared across
+/// machines.
+pub struct OpenAiCompatible {
+    name: String,
+    /// Up to and including `/v1`.
+    base_url: String,
+    api_key: Option<String>,
+    model: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl OpenAiCompatible {
+    /// `url` may be the server root or already end in `/v1`.
+    pub fn new(url: &str, api_key: Option<&str>, model: Option<&str>, timeout: Duration) -> Self {
+        let mut base_url = backend::base_url(url);
+        if !base_url.ends_with("/v1") {
+            base_url.push_str("/v1");
+        }
+        Self {
+            name: format!("openai:{}", model.unwrap_or(&base_url)),
+            base_url,
+            api_key: api_key.filter(|k| !k.is_empty()).map(String::from),
+            model: model.filter(|m| !m.is_empty()).map(String::from),
+            agent: backend::agent(timeout),
+        }
+    }
+}
+
+/// Where to send chat completions: `{base_url}/chat/completions`.
+pub(crate) struct Endpoint<'a> {
+    pub agent: &'a ureq::Agent,
+    pub base_url: &'a str,
+    pub api_key: Option<&'a str>,
+    pub model: Option<&'a str>,
+}
+
+impl Endpoint<'_> {
+    fn post(&self, system_prompt: &str, user_text: &str, max_tokens: i32, schema: &Value, stream: bool) -> Result<ureq::http::Response<ureq::Body>> {
+        let mut body = serde_json::json!({
+            "messages": [
+                {"role": "system", "content": system_prompt},
+                {"role": "user", "content": user_text}
+            ],
+            "max_tokens": max_tokens,
+            "temperature": 0.1,
+            "stream": stream,
+            // llama-server turns the schema into a grammar, so the reply can't stray from it
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {"name": "reply", "strict": true, "schema": schema}
+            }
+        });
+        if let Some(model) = self.model {
+            body["model"] = model.into();
+        }
+
+        let mut request = self.agent.post(format!("{}/chat/completions", self.base_url))
+            .header("Content-Type", "application/json");
+        if let Some(key) = self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+        Ok(request.send(&serde_json::to_vec(&body)?)?)
+    }
+
+    /// The reply, held to `schema` and parsed.
+    pub fn chat(&self, system_prompt: &str, user_text: &str, max_tokens: i32, schema: &Value) -> Result<Value> {
+        let mut resp = self.post(system_prompt, user_text, max_tokens, schema, false)?;
+        let body_str = resp.body_mut().read_to_string()?;
+        let json: Value = serde_json::from_str(&body_str)?;
+        structured::parse(json["choices"][0]["message"]["content"].as_str().unwrap_or(""))
+    }
+
+    /// Polished text as `PolishBackend::generate` returns it, read from the server-sent event
+    /// stream so each piece of the text goes to `on_token` as it arrives. Fails if the
+    /// stream stops before the server finishes.
+    pub fn chat_stream(&self, system_prompt: &str, user_text: &str, max_tokens: i32, on_token: &mut dyn FnMut(&str)) -> Result<String> {
+        let mut resp = self.post(system_prompt, user_text, max_tokens, &structured::text_schema(), true)?;
+        let mut field = TextFieldStream::new();
+        let mut text = String::new();
+        let mut finished = false;
+        for line in BufReader::new(resp.body_mut().as_reader()).lines() {
+            let line = line?;
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                continue; // blank separators, comments, event names
+            };
+            if data == "[DONE]" {
+                finished = true;
+                break;
+            }
+            let json: Value = serde_json::from_str(data)?;
+            if let Some(error) = json.get("error") {
+                anyhow::bail!("LLM stream error: {}", error);
+            }
+            let choice = &json["choices"][0];
+            if let Some(token) = choice["delta"]["content"].as_str().filter(|t| !t.is_empty()) {
+                text.push_str(token);
+                let decoded = field.push(token);
+                if !decoded.is_empty() {
+                    on_token(&decoded);
+                }
+            }
+            finished |= !choice["finish_reason"].is_null();
+        }
+        if !finished {
+            anyhow::bail!("LLM stream ended early");
+        }
+        structured::text_field(&structured::parse(&text)?)
+    }
+}
+
+impl OpenAiCompatible {
+    fn endpoint(&self) -> Endpoint<'_> {
+        Endpoint {
+            agent: &self.agent,
+            base_url: &self.base_url,
+            api_key: self.api_key.as_deref(),
+            model: self.model.as_deref(),
+        }
+    }
+}
+
+impl PolishBackend for OpenAiCompatible {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate_json(&self, system_prompt: &str, user_text: &str, max_tokens: i32, schema: &Value) -> Result<Value> {
+        self.endpoint().chat(system_prompt, user_text, max_tokens, schema)
+    }
+
+    fn generate_stream(&self, system_prompt: &str, user_text: &str, max_tokens: i32, on_token: &mut dyn FnMut(&str)) -> Result<String> {
+        self.endpoint().chat_stream(system_prompt, user_text, max_tokens, on_token)
+    }
+
+    /// `/v1/models` answers without running the model, and checks the key.
+    fn health(&self) -> Result<()> {
+        let mut request = self.agent.get(format!("{}/models", self.base_url));
+        if let Some(key) = &self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+        request.call()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polish::backend::mock::MockServer;
+    use serde_json::json;
+
+    fn reply(text: &str) -> serde_json::Value {
+        json!({"choices": [{"message": {"role": "assistant", "content": json!({"text": text}).to_string()}}]})
+    }
+
+    #[test]
+    fn sends_chat_completion_with_model_and_key() {
+        let server = MockServer::start(vec![(200, json!({"data": []})), (200, reply("  Hello, world.\n"))]);
+        let llm = OpenAiCompatible::new(&format!("{}/", server.url), Some("secret"), Some("qwen2.5-7b"), Duration::from_secs(5));
+        llm.health().unwrap();
+        assert_eq!(llm.generate("Fix grammar.", "hello world", 64).unwrap(), "Hello, world.");
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/models");
+        assert_eq!(requests[1].method, "POST");
+        assert_eq!(requests[1].path, "/v1/chat/completions");
+        assert_eq!(requests[1].authorization.as_deref(), Some("Bearer secret"));
+        assert_eq!(requests[1].body["model"], "qwen2.5-7b");
+        assert_eq!(requests[1].body["max_tokens"], 64);
+        assert_eq!(requests[1].body["messages"][1]["content"], "hello world");
+        assert_eq!(requests[1].body["response_format"]["json_schema"]["schema"], structured::text_schema());
+    }
+
+    #[test]
+    fn keeps_v1_and_omits_optional_fields() {
+        let server = MockServer::start(vec![(200, reply("ok"))]);
+        let llm = OpenAiCompatible::new(&format!("{}/v1", server.url), Some(""), None, Duration::from_secs(5));
+        llm.generate("s", "u", 8).unwrap();
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/chat/completions");
+        assert_eq!(requests[0].authorization, None);
+        assert!(requests[0].body.get("model").is_none());
+    }
+
+    #[test]
+    fn reports_errors() {
+        let server = MockServer::start(vec![(401, json!({"error": "bad key"})), (200, reply("   "))]);
+        let llm = OpenAiCompatible::new(&server.url, Some("wrong"), None, Duration::from_secs(5));
+        assert!(llm.health().is_err());
+        let err = llm.generate("s", "u", 8).unwrap_err();
+        assert!(err.to_string().contains("empty"));
+    }
+
+    fn events(tokens: &[&str], finish: bool) -> serde_json::Value {
+        let mut body: String = tokens.iter()
+            .map(|t| format!("data: {}\n\n", json!({"choices": [{"delta": {"content": t}, "finish_reason": null}]})))
+            .collect();
+        if finish {
+            body.push_str(&format!("data: {}\n\n", json!({"choices": [{"delta": {}, "finish_reason": "stop"}]})));
+            body.push_str("data: [DONE]\n\n");
+        }
+        serde_json::Value::String(body)
+    }
+
+    #[test]
+    fn streams_tokens() {
+        let server = MockServer::start(vec![(200, events(&["{\"text\": \"", "Ship", " it", " \\\"today\\\".", "\"}"], true))]);
+        let llm = OpenAiCompatible::new(&server.url, None, None, Duration::from_secs(5));
+        let mut tokens = Vec::new();
+        let text = llm.generate_stream("s", "ship it today", 32, &mut |t| tokens.push(t.to_string())).unwrap();
+        assert_eq!(text, "Ship it \"today\".");
+        assert_eq!(tokens, vec!["Ship", " it", " \"today\"."]);
+        assert_eq!(server.requests()[0].body["stream"], true);
+    }
+
+    #[test]
+    fn truncated_stream_fails_after_partial_tokens() {
+        let server = MockServer::start(vec![(200, events(&["{\"text\":\"Ship", " it."], false))]);
+        let llm = OpenAiCompatible::new(&server.url, None, None, Duration::from_secs(5));
+        let mut received = String::new();
+        let err = llm.generate_stream("s", "u", 32, &mut |t| received.push_str(t)).unwrap_err();
+        assert!(err.to_string().contains("ended early"));
+        assert_eq!(received, "Ship it.");
+    }
+
+    #[test]
+    fn times_out() {
+        let server = MockServer::start_with_delay(vec![(200, reply("late"))], Duration::from_millis(1500));
+        let llm = OpenAiCompatible::new(&server.url, None, None, Duration::from_millis(300));
+        assert!(llm.generate("s", "u", 8).is_err());
+    }
+}
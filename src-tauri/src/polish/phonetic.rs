@@ -0,0 +1,271 @@
+//! Sound-alike correction of dictionary terms. Whisper spells jargon however it hears it
+//! ("cubectl", "kube cuttle"), so compare runs of transcript words against each entry by
+//! phonetic key and spelling, and replace the confident matches.
+
+use strsim::normalized_levenshtein;
+
+use super::dictionary::Substitution;
+use crate::db::dictionary::DictEntry;
+
+/// Minimum combined score for a replacement; below this, leave the transcript alone.
+pub const DEFAULT_THRESHOLD: f64 = 0.85;
+/// Phonetic similarity counts for more than spelling: ASR errors sound right, not look right.
+const PHONETIC_WEIGHT: f64 = 0.7;
+/// Short keys ("Go", "Jo") sound like too many ordinary words to correct safely.
+const MIN_KEY_LEN: usize = 3;
+const MAX_WINDOW_WORDS: usize = 4;
+
+/// Metaphone-style phonetic key: consonant skeleton with common English spelling rules
+/// folded together, so "cube", "kube" and "qube" all encode as "KB".
+pub fn phonetic_key(word: &str) -> String {
+    let w: Vec<char> = word.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let at = |i: usize| w.get(i).copied().unwrap_or('\0');
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u');
+    let mut key = String::new();
+    let push = |k: &str, key: &mut String| {
+        // Collapse repeats across letters too ("ck" → K, not KK)
+        if !key.ends_with(k) {
+            key.push_str(k);
+        }
+    };
+
+    let mut i = 0;
+    // Silent leading letters
+    if matches!((at(0), at(1)), ('k', 'n') | ('g', 'n') | ('p', 'n') | ('w', 'r') | ('a', 'e')) {
+        i = 1;
+    }
+    while i < w.len() {
+        let (c, next) = (at(i), at(i + 1));
+        let prev = if i > 0 { at(i - 1) } else { '\0' };
+        if c == prev && c != 'c' {
+            i += 1;
+            continue;
+        }
+        match c {
+            'a' | 'e' | 'i' | 'o' | 'u' => {
+                if i == 0 { push("A", &mut key); }
+            }
+            'b' => if !(prev == 'm' && i + 1 == w.len()) { push("B", &mut key) },
+            'c' => match next {
+                'h' => { push("X", &mut key); i += 1; }
+                'i' if at(i + 2) == 'a' => push("X", &mut key),
+                'i' | 'e' | 'y' => push("S", &mut key),
+                _ => push("K", &mut key),
+            },
+            'd' => if next == 'g' && matches!(at(i + 2), 'e' | 'i' | 'y') {
+                push("J", &mut key);
+                i += 1;
+            } else {
+                push("T", &mut key)
+            },
+            'g' => match next {
+                'h' if !is_vowel(at(i + 2)) => i += 1,
+                'n' if i + 2 >= w.len() => {}
+                'i' | 'e' | 'y' => push("J", &mut key),
+                _ => push("K", &mut key),
+            },
+            'h' => if !is_vowel(prev) && is_vowel(next) { push("H", &mut key) },
+            'k' => if prev != 'c' { push("K", &mut key) },
+            'p' => if next == 'h' { push("F", &mut key); i += 1; } else { push("P", &mut key) },
+            'q' => push("K", &mut key),
+            's' => if next == 'h' || (next == 'i' && matches!(at(i + 2), 'o' | 'a')) {
+                push("X", &mut key);
+                if next == 'h' { i += 1; }
+            } else {
+                push("S", &mut key)
+            },
+            't' => if next == 'h' {
+                push("0", &mut key);
+                i += 1;
+            } else if next == 'i' && matches!(at(i + 2), 'o' | 'a') {
+                push("X", &mut key)
+            } else if !(next == 'c' && at(i + 2) == 'h') {
+                push("T", &mut key)
+            },
+            'v' => push("F", &mut key),
+            'w' | 'y' => if is_vowel(next) { push(if c == 'w' { "W" } else { "Y" }, &mut key) },
+            'x' => push(if i == 0 { "S" } else { "KS" }, &mut key),
+            'z' => push("S", &mut key),
+            other => push(&other.to_ascii_uppercase().to_string(), &mut key),
+        }
+        i += 1;
+    }
+    key
+}
+
+/// A dictionary term prepared for matching.
+struct Target<'a> {
+    entry: &'a DictEntry,
+    key: String,
+    letters: String,
+    words: usize,
+}
+
+/// Word spans (byte offsets) in the transcript.
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        let in_word = c.is_alphanumeric() || c == '\'';
+        match (in_word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => { spans.push((s, i)); start = None; }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, text.len()));
+    }
+    spans
+}
+
+fn squash(s: &str) -> String {
+    s.chars().filter(|c| c.is_alphanumeric()).flat_map(|c| c.to_lowercase()).collect()
+}
+
+fn score(target: &Target, window: &str) -> f64 {
+    let letters = squash(window);
+    let key = phonetic_key(&letters);
+    // Sound-alikes practically always share their first consonant
+    let phonetic = if target.key.chars().next() == key.chars().next() {
+        normalized_levenshtein(&target.key, &key)
+    } else {
+        0.0
+    };
+    let spelling = normalized_levenshtein(&target.letters, &letters);
+    PHONETIC_WEIGHT * phonetic + (1.0 - PHONETIC_WEIGHT) * spelling
+}
+
+/// Replace sound-alikes of dictionary written forms scoring at least `threshold`.
+/// Matches never overlap; the best-scoring one wins.
+pub fn correct(text: &str, entries: &[DictEntry], threshold: f64) -> (String, Vec<Substitution>) {
+    let targets: Vec<Target> = entries.iter()
+        .map(|e| Target {
+            entry: e,
+            key: phonetic_key(&squash(&e.written)),
+            letters: squash(&e.written),
+            words: e.written.split_whitespace().count().max(1),
+        })
+        .filter(|t| t.key.len() >= MIN_KEY_LEN)
+        .collect();
+    let spans = words(text);
+
+    // (score, first word, last word, target)
+    let mut candidates: Vec<(f64, usize, usize, &Target)> = Vec::new();
+    for start in 0..spans.len() {
+        for end in start..spans.len().min(start + MAX_WINDOW_WORDS) {
+            // Only join words separated by spaces or hyphens, never across punctuation
+            if end > start && !text[spans[end - 1].1..spans[end].0].chars().all(|c| c.is_whitespace() || c == '-') {
+                break;
+            }
+            let window = &text[spans[start].0..spans[end].1];
+            let n = end - start + 1;
+            let letters = squash(window);
+            for t in &targets {
+                if n > t.words + 2 || letters == t.letters {
+                    continue;
+                }
+                let len = letters.len();
+                if len * 2 < t.letters.len() || len > t.letters.len() * 2 {
+                    continue;
+                }
+                let s = score(t, window);
+                if s >= threshold {
+                    candidates.push((s, start, end, t));
+                }
+            }
+        }
+    }
+
+    // Best first; on ties prefer the window covering fewer words
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.2 - a.1).cmp(&(b.2 - b.1))));
+    let mut taken = vec![false; spans.len()];
+    let mut chosen = Vec::new();
+    for (s, start, end, t) in candidates {
+        if taken[start..=end].iter().any(|&x| x) {
+            continue;
+        }
+        taken[start..=end].iter_mut().for_each(|x| *x = true);
+        chosen.push((start, end, t, s));
+    }
+    chosen.sort_by_key(|c| c.0);
+
+    let mut out = String::with_capacity(text.len());
+    let mut subs = Vec::new();
+    let mut last = 0;
+    for (start, end, t, s) in chosen {
+        let (from_start, from_end) = (spans[start].0, spans[end].1);
+        let from = &text[from_start..from_end];
+        tracing::debug!("Phonetic match {:?} → {:?} ({:.2})", from, t.entry.written, s);
+        out.push_str(&text[last..from_start]);
+        out.push_str(&t.entry.written);
+        subs.push(Substitution { entry_id: t.entry.id, from: from.to_string(), to: t.entry.written.clone() });
+        last = from_end;
+    }
+    out.push_str(&text[last..]);
+    (out, subs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i64, spoken: &str, written: &str) -> DictEntry {
+        DictEntry { id, spoken: spoken.into(), written: written.into(), category: "tech".into(), usage_count: 0 }
+    }
+
+    #[test]
+    fn keys_fold_spelling_variants() {
+        assert_eq!(phonetic_key("kubectl"), phonetic_key("cubectl"));
+        assert_eq!(phonetic_key("kubectl"), phonetic_key("kubecuttle"));
+        assert_eq!(phonetic_key("phone"), phonetic_key("fone"));
+        assert_eq!(phonetic_key("knight"), phonetic_key("nite"));
+        assert_eq!(phonetic_key("thing"), "0NK");
+        assert_eq!(phonetic_key(""), "");
+    }
+
+    #[test]
+    fn corrects_sound_alikes() {
+        let dict = [entry(1, "cube control", "kubectl")];
+        for heard in ["run cubectl apply", "run kube cuttle apply", "run Kube-Cuttle apply"] {
+            let (out, subs) = correct(heard, &dict, DEFAULT_THRESHOLD);
+            assert_eq!(out, "run kubectl apply", "for {:?}", heard);
+            assert_eq!(subs.len(), 1);
+            assert_eq!(subs[0].entry_id, 1);
+        }
+    }
+
+    #[test]
+    fn reports_what_was_replaced() {
+        let dict = [entry(4, "postgres", "PostgreSQL"), entry(5, "vercel", "Vercel")];
+        let (out, subs) = correct("moved to postgress sequel, then versel.", &dict, DEFAULT_THRESHOLD);
+        assert_eq!(out, "moved to PostgreSQL, then Vercel.");
+        assert_eq!(subs[0].from, "postgress sequel");
+        assert_eq!(subs[1], Substitution { entry_id: 5, from: "versel".into(), to: "Vercel".into() });
+    }
+
+    #[test]
+    fn leaves_unrelated_words_alone() {
+        let dict = [entry(1, "cube control", "kubectl"), entry(2, "go", "Go"), entry(3, "grafana", "Grafana")];
+        let text = "the cable company called about the bill, go figure";
+        assert_eq!(correct(text, &dict, DEFAULT_THRESHOLD), (text.to_string(), vec![]));
+    }
+
+    #[test]
+    fn correct_spelling_is_not_a_substitution() {
+        let dict = [entry(1, "cube control", "kubectl")];
+        let (out, subs) = correct("kubectl get pods", &dict, DEFAULT_THRESHOLD);
+        assert_eq!(out, "kubectl get pods");
+        assert!(subs.is_empty());
+    }
+
+    #[test]
+    fn does_not_join_across_punctuation() {
+        let dict = [entry(1, "cube control", "kubectl")];
+        let (out, _) = correct("a kube. cuttle", &dict, DEFAULT_THRESHOLD);
+        assert_eq!(out, "a kube. cuttle");
+    }
+}
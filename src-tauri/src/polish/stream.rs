@@ -0,0 +1,137 @@
+//! Incremental delivery of polish output: completed sentences are injected while the LLM is
+//! still writing, and a stream that dies partway is finished from the raw transcript.
+
+/// Accumulates streamed tokens and hands out each sentence once it is complete.
+#[derive(Debug, Default)]
+pub struct SentenceStream {
+    text: String,
+    /// Byte offset up to which text has been handed out.
+    emitted: usize,
+}
+
+impl SentenceStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, token: &str) {
+        // Models often open with a space or newline
+        let token = if self.text.is_empty() { token.trim_start() } else { token };
+        self.text.push_str(token);
+    }
+
+    /// Everything received so far.
+    pub fn text(&self) -> &str {
+        self.text.trim()
+    }
+
+    /// Text handed out by `ready` so far.
+    pub fn emitted(&self) -> &str {
+        &self.text[..self.emitted]
+    }
+
+    /// Completed sentences not handed out yet, with the space that follows them, or None.
+    /// A sentence is complete once whitespace follows its closing punctuation, so "3.5" and
+    /// "e.g.," never split.
+    pub fn peek(&self) -> Option<&str> {
+        let pending = &self.text[self.emitted..];
+        let mut end = None;
+        let mut chars = pending.char_indices().peekable();
+        while let Some((_, c)) = chars.next() {
+            let closes = matches!(c, '.' | '!' | '?' | '…' | '\n');
+            if let (true, Some(&(i, next))) = (closes, chars.peek()) {
+                if next.is_whitespace() {
+                    end = Some(i + next.len_utf8());
+                }
+            }
+        }
+        end.map(|end| &pending[..end])
+    }
+
+    /// Hand out what `peek` returns.
+    pub fn ready(&mut self) -> Option<String> {
+        let sentences = self.peek()?.to_string();
+        self.emitted += sentences.len();
+        Some(sentences)
+    }
+
+    /// What is left after the last sentence handed out, once the stream has finished.
+    pub fn rest(&self) -> &str {
+        self.text[self.emitted..].trim_end()
+    }
+}
+
+fn normalize(word: &str) -> String {
+    word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// How far ahead in the raw transcript to look for each polished word. Polish drops fillers
+/// and false starts, so raw runs ahead of the polished text by a few words at a time.
+const ALIGN_WINDOW: usize = 6;
+
+/// The part of `raw` not yet covered by `polished`, a prefix of its polished form. Words are
+/// matched in order, skipping raw words polish left out; polished words with no match nearby
+/// (rewordings) are skipped too.
+pub fn raw_rest<'a>(raw: &'a str, polished: &str) -> &'a str {
+    let raw_words: Vec<(usize, &str)> = raw.split_whitespace()
+        .map(|w| (w.as_ptr() as usize - raw.as_ptr() as usize, w))
+        .collect();
+    let mut covered = 0;
+    for word in polished.split_whitespace().map(normalize).filter(|w| !w.is_empty()) {
+        let mut window = raw_words.iter().enumerate().skip(covered).take(ALIGN_WINDOW);
+        if let Some((i, _)) = window.find(|(_, (_, w))| normalize(w) == word) {
+            covered = i + 1;
+        }
+    }
+    match raw_words.get(covered) {
+        Some(&(offset, _)) => &raw[offset..],
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(tokens: &[&str]) -> (SentenceStream, Vec<String>) {
+        let mut stream = SentenceStream::new();
+        let mut out = Vec::new();
+        for token in tokens {
+            stream.push(token);
+            out.extend(stream.ready());
+        }
+        (stream, out)
+    }
+
+    #[test]
+    fn hands_out_complete_sentences() {
+        let (stream, out) = feed(&[" Ship", " it", " today.", " Then", " tell", " Sam!", " Maybe", " 3.5", " hours"]);
+        assert_eq!(out, vec!["Ship it today. ".to_string(), "Then tell Sam! ".to_string()]);
+        assert_eq!(stream.rest(), "Maybe 3.5 hours");
+        assert_eq!(stream.text(), "Ship it today. Then tell Sam! Maybe 3.5 hours");
+        assert_eq!(format!("{}{}", stream.emitted(), stream.rest()), stream.text());
+    }
+
+    #[test]
+    fn waits_for_whitespace_after_punctuation() {
+        let (mut stream, out) = feed(&["Use", " e.g.", "," , " this."]);
+        assert!(out.is_empty());
+        stream.push("\n");
+        assert_eq!(stream.ready().as_deref(), Some("Use e.g., this.\n"));
+        assert_eq!(stream.rest(), "");
+    }
+
+    #[test]
+    fn raw_rest_skips_what_polish_covered() {
+        let raw = "um so we should uh deploy the new version and then like tell the team";
+        assert_eq!(raw_rest(raw, "So we should deploy the new version."), "and then like tell the team");
+        assert_eq!(raw_rest(raw, ""), raw);
+        assert_eq!(raw_rest(raw, "So we should deploy the new version, and then tell the team."), "");
+    }
+
+    #[test]
+    fn raw_rest_survives_rewording() {
+        let raw = "gonna ship it friday and then we test";
+        assert_eq!(raw_rest(raw, "Going to ship it Friday."), "and then we test");
+    }
+}
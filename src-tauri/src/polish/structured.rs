@@ -0,0 +1,200 @@
+//! JSON schemas that constrain LLM replies, and decoding of what comes back. With the reply
+//! held to a schema (a grammar on llama-server) the model can't wrap the text in quotes,
+//! explain itself or echo the prompt, and parsing never guesses.
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::backend;
+
+/// Time-of-day brackets the pill picks a mood line from.
+pub const MOOD_TIMES: [&str; 6] = ["early", "morning", "afternoon", "evening", "night", "late"];
+
+/// Polish replies: `{"text": "<polished transcript>"}`.
+pub fn text_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {"text": {"type": "string"}},
+        "required": ["text"],
+        "additionalProperties": false
+    })
+}
+
+/// The `text` of a reply to `text_schema`, trimmed and non-empty.
+pub fn text_field(reply: &Value) -> Result<String> {
+    let text = reply.get("text").and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("LLM reply has no text field: {}", reply))?;
+    backend::non_empty(text)
+}
+
+/// Parse a reply constrained to a schema.
+pub fn parse(reply: &str) -> Result<Value> {
+    serde_json::from_str(reply.trim())
+        .map_err(|e| anyhow::anyhow!("LLM reply is not the requested JSON ({}): {}", e, reply))
+}
+
+fn string_fields<S: AsRef<str>>(keys: &[S]) -> Value {
+    let properties: serde_json::Map<String, Value> = keys.iter()
+        .map(|k| (k.as_ref().to_string(), json!({"type": "string"})))
+        .collect();
+    let required: Vec<&str> = keys.iter().map(AsRef::as_ref).collect();
+    json!({"type": "object", "properties": properties, "required": required, "additionalProperties": false})
+}
+
+/// Pill microcopy for the apps that need it: `{"hints": {app: …}, "mood": {time: …},
+/// "affirmations": {app: …}}`, with only the sections asked for.
+pub fn microcopy_schema(hint_apps: &[String], mood: bool, affirm_apps: &[String]) -> Value {
+    let mut properties = serde_json::Map::new();
+    if !hint_apps.is_empty() {
+        properties.insert("hints".into(), string_fields(hint_apps));
+    }
+    if mood {
+        properties.insert("mood".into(), string_fields(&MOOD_TIMES));
+    }
+    if !affirm_apps.is_empty() {
+        properties.insert("affirmations".into(), string_fields(affirm_apps));
+    }
+    let required: Vec<String> = properties.keys().cloned().collect();
+    json!({"type": "object", "properties": properties, "required": required, "additionalProperties": false})
+}
+
+/// A reply to `microcopy_schema`.
+#[derive(Debug, Default, Deserialize)]
+pub struct Microcopy {
+    #[serde(default)]
+    pub hints: HashMap<String, String>,
+    #[serde(default)]
+    pub mood: HashMap<String, String>,
+    #[serde(default)]
+    pub affirmations: HashMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+enum State {
+    /// Before the opening quote of the `text` value; holds what has arrived so far.
+    #[default]
+    Key,
+    Value,
+    Escape,
+    /// Hex digits of a `\u` escape.
+    Unicode(String),
+    Done,
+}
+
+/// Decodes the `text` value of a streamed `{"text": "…"}` reply as it arrives, so streamed
+/// polish can be shown and typed before the JSON is complete.
+#[derive(Debug, Default)]
+pub struct TextFieldStream {
+    state: State,
+    head: String,
+    /// High half of a UTF-16 surrogate pair waiting for its low half.
+    surrogate: Option<u32>,
+}
+
+impl TextFieldStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed the next piece of the raw reply; returns the newly decoded text, possibly empty.
+    pub fn push(&mut self, token: &str) -> String {
+        let mut out = String::new();
+        for c in token.chars() {
+            self.state = match std::mem::take(&mut self.state) {
+                State::Key => {
+                    self.head.push(c);
+                    if self.at_value() { State::Value } else { State::Key }
+                }
+                State::Value => match c {
+                    '\\' => State::Escape,
+                    '"' => State::Done,
+                    c => { out.push(c); State::Value }
+                },
+                State::Escape if c == 'u' => State::Unicode(String::new()),
+                State::Escape => {
+                    out.push(match c {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        c => c, // \" \\ \/
+                    });
+                    State::Value
+                }
+                State::Unicode(mut hex) => {
+                    hex.push(c);
+                    if hex.len() < 4 {
+                        State::Unicode(hex)
+                    } else {
+                        let unit = u32::from_str_radix(&hex, 16).unwrap_or(0xFFFD);
+                        match (self.surrogate.take(), unit) {
+                            (None, 0xD800..=0xDBFF) => self.surrogate = Some(unit),
+                            (Some(high), 0xDC00..=0xDFFF) => {
+                                out.extend(char::from_u32(0x10000 + ((high - 0xD800) << 10) + (unit - 0xDC00)));
+                            }
+                            (_, unit) => out.push(char::from_u32(unit).unwrap_or('\u{FFFD}')),
+                        }
+                        State::Value
+                    }
+                }
+                State::Done => State::Done,
+            };
+        }
+        out
+    }
+
+    /// `head` ends in `"text"`, a colon and an opening quote, give or take whitespace.
+    fn at_value(&self) -> bool {
+        let Some(rest) = self.head.strip_suffix('"') else { return false };
+        let Some(rest) = rest.trim_end().strip_suffix(':') else { return false };
+        rest.trim_end().ends_with("\"text\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(tokens: &[&str]) -> Vec<String> {
+        let mut decoder = TextFieldStream::new();
+        tokens.iter().map(|t| decoder.push(t)).collect()
+    }
+
+    #[test]
+    fn decodes_text_field_as_it_streams() {
+        let out = stream(&["{\"", "text", "\": ", "\"Ship", " it", ".\\n", "Then \\\"go\\\"", "\"}"]);
+        assert_eq!(out, vec!["", "", "", "Ship", " it", ".\n", "Then \"go\"", ""]);
+    }
+
+    #[test]
+    fn decodes_unicode_escapes_split_across_tokens() {
+        let out = stream(&["{ \"text\" : \"caf\\u00", "e9 \\ud83d", "\\ude80\"}"]).concat();
+        assert_eq!(out, "café 🚀");
+    }
+
+    #[test]
+    fn reads_text_field() {
+        assert_eq!(text_field(&parse(" {\"text\": \" Hello. \"}\n").unwrap()).unwrap(), "Hello.");
+        assert!(text_field(&json!({"text": " "})).is_err());
+        assert!(text_field(&json!({"answer": "Hi"})).is_err());
+        assert!(parse("Sure! {\"text\": \"Hi\"}").is_err());
+    }
+
+    #[test]
+    fn microcopy_schema_has_only_requested_sections() {
+        let schema = microcopy_schema(&["Slack".into(), "Notes".into()], false, &[]);
+        assert_eq!(schema["required"], json!(["hints"]));
+        assert_eq!(schema["properties"]["hints"]["required"], json!(["Slack", "Notes"]));
+        let schema = microcopy_schema(&[], true, &["Slack".into()]);
+        assert_eq!(schema["properties"]["mood"]["required"].as_array().unwrap().len(), 6);
+        assert!(schema["properties"].get("hints").is_none());
+
+        let reply = json!({"mood": {"morning": "Quiet start"}, "affirmations": {"Slack": "Message sent"}});
+        let copy: Microcopy = serde_json::from_value(reply).unwrap();
+        assert_eq!(copy.mood["morning"], "Quiet start");
+        assert!(copy.hints.is_empty());
+    }
+}
@@ -0,0 +1,556 @@
+//! Keeps a local server process running: launches it on a free loopback port with a fresh
+//! API key (handed over in a private file, not on the command line), captures its output to a rotating log, and restarts it with backoff when it
+//! crashes. State changes go to a listener so the UI can show them. The running child's pid
+//! is kept in a file so one orphaned by an app crash is killed on the next start.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// How often the monitor checks the process, and startup polls for readiness.
+const POLL: Duration = Duration::from_millis(250);
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+/// A server that ran this long before dying starts the backoff over.
+const STABLE_AFTER: Duration = Duration::from_secs(120);
+const LOG_MAX_BYTES: u64 = 1024 * 1024;
+/// Rotated logs kept besides the current one: `name.1` … `name.N`.
+const LOG_KEEP: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerState {
+    Starting,
+    Ready,
+    Crashed,
+    Stopped,
+}
+
+pub type StateListener = Arc<dyn Fn(ServerState) + Send + Sync>;
+
+/// Builds the command for a launch from its port and the file holding its API key. Any
+/// local user can read a process's arguments, so the key itself never goes on them.
+pub type CommandFn = Box<dyn Fn(u16, &Path) -> Command + Send + Sync>;
+/// Whether the server on this port, with this API key, answers yet.
+pub type ReadyFn = Box<dyn Fn(u16, &str) -> bool + Send + Sync>;
+
+/// What to run and how to tell it's up.
+pub struct ServerSpec {
+    pub name: String,
+    pub command: CommandFn,
+    pub is_ready: ReadyFn,
+    pub startup_timeout: Duration,
+}
+
+/// Delay before restart number `crashes` (1 for the first crash): doubling from a second,
+/// capped at a minute.
+pub fn backoff(crashes: u32) -> Duration {
+    let exp = crashes.saturating_sub(1).min(16);
+    (BACKOFF_BASE * 2u32.pow(exp)).min(BACKOFF_MAX)
+}
+
+/// A port nothing is listening on right now.
+pub fn free_port() -> Result<u16> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// A random token so only this app can talk to the server.
+pub fn api_key() -> String {
+    let mut bytes = [0u8; 16];
+    let from_os = File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut bytes)).is_ok();
+    if !from_os {
+        // RandomState is seeded from the OS too, just less directly
+        use std::hash::{BuildHasher, Hasher};
+        for chunk in bytes.chunks_mut(8) {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Write `contents` to a file only the current user can read.
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // The mode applies only to a new file
+    let _ = fs::remove_file(path);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Appends lines to `path`, moving it to `path.1` (and older ones up) once it passes
+/// `max_bytes`.
+pub struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Option<File>,
+    written: u64,
+}
+
+impl RotatingLog {
+    pub fn new(path: &Path, max_bytes: u64, keep: usize) -> Self {
+        Self { path: path.to_path_buf(), max_bytes, keep, file: None, written: 0 }
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        for n in (1..self.keep).rev() {
+            let _ = fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        if self.keep > 0 {
+            fs::rename(&self.path, self.rotated(1))?;
+        } else {
+            fs::remove_file(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+            self.written = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        if self.written >= self.max_bytes {
+            self.rotate()?;
+            return self.write_line(line);
+        }
+        if let Some(file) = &mut self.file {
+            writeln!(file, "{}", line)?;
+            self.written += line.len() as u64 + 1;
+        }
+        Ok(())
+    }
+}
+
+struct Process {
+    child: Option<Child>,
+    port: u16,
+    state: ServerState,
+    crashes: u32,
+    started: Instant,
+}
+
+struct Shared {
+    spec: ServerSpec,
+    api_key: String,
+    log: Arc<Mutex<RotatingLog>>,
+    key_path: PathBuf,
+    pid_path: PathBuf,
+    on_state: StateListener,
+    process: Mutex<Process>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn set_state(&self, process: &mut Process, state: ServerState) {
+        if process.state != state {
+            process.state = state;
+            tracing::info!("{} {:?}", self.spec.name, state);
+            (self.on_state)(state);
+        }
+    }
+
+    fn log(&self, line: &str) {
+        let _ = self.log.lock().unwrap().write_line(line);
+    }
+
+    fn remove_files(&self) {
+        let _ = fs::remove_file(&self.pid_path);
+        let _ = fs::remove_file(&self.key_path);
+    }
+
+    /// Start the process and wait for it to answer. The lock is released while waiting, so
+    /// `port` fails fast with the server still Starting and `stop` can kill it midway.
+    fn launch(&self) -> Result<()> {
+        let port = {
+            let mut process = self.process.lock().unwrap();
+            // Checked under the lock so a launch can't follow stop() and outlive it
+            if self.stop.load(Ordering::Relaxed) {
+                anyhow::bail!("{} is stopped", self.spec.name);
+            }
+            self.set_state(&mut process, ServerState::Starting);
+            let port = free_port()?;
+            self.log(&format!("--- starting {} on port {}", self.spec.name, port));
+            let mut child = (self.spec.command)(port, &self.key_path)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| anyhow::anyhow!("Failed to start {}: {}", self.spec.name, e))?;
+            pump(child.stdout.take(), self.log.clone());
+            pump(child.stderr.take(), self.log.clone());
+            let _ = fs::write(&self.pid_path, child.id().to_string());
+            process.child = Some(child);
+            process.port = port;
+            process.started = Instant::now();
+            port
+        };
+
+        let deadline = Instant::now() + self.spec.startup_timeout;
+        loop {
+            {
+                let mut process = self.process.lock().unwrap();
+                if self.stop.load(Ordering::Relaxed) {
+                    anyhow::bail!("{} stopped during startup", self.spec.name);
+                }
+                if let Some(status) = process.child.as_mut().and_then(|c| c.try_wait().ok().flatten()) {
+                    process.child = None;
+                    anyhow::bail!("{} exited during startup ({})", self.spec.name, status);
+                }
+            }
+            if (self.spec.is_ready)(port, &self.api_key) {
+                let mut process = self.process.lock().unwrap();
+                if self.stop.load(Ordering::Relaxed) {
+                    anyhow::bail!("{} stopped during startup", self.spec.name);
+                }
+                self.set_state(&mut process, ServerState::Ready);
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                kill(&mut self.process.lock().unwrap());
+                anyhow::bail!("{} failed to start within {:?}", self.spec.name, self.spec.startup_timeout);
+            }
+            std::thread::sleep(POLL);
+        }
+    }
+}
+
+/// Whoever drops the last reference, the supervisor or a monitor midway through a restart,
+/// takes the process down with it.
+impl Drop for Shared {
+    fn drop(&mut self) {
+        if let Ok(process) = self.process.get_mut() {
+            kill(process);
+        }
+        self.remove_files();
+    }
+}
+
+/// Copy a child's output into the log line by line until the pipe closes.
+fn pump(stream: Option<impl Read + Send + 'static>, log: Arc<Mutex<RotatingLog>>) {
+    if let Some(stream) = stream {
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(|l| l.ok()) {
+                let _ = log.lock().unwrap().write_line(&line);
+            }
+        });
+    }
+}
+
+/// Kill the process recorded in `pid_path` by an earlier run that died without stopping it,
+/// if it is still running as `name`.
+fn kill_orphan(pid_path: &Path, name: &str) {
+    let Some(pid) = fs::read_to_string(pid_path).ok().and_then(|s| s.trim().parse::<u32>().ok()) else {
+        return;
+    };
+    let _ = fs::remove_file(pid_path);
+    // The pid may belong to an unrelated process by now
+    let running_as_name = Command::new("ps").args(["-p", &pid.to_string(), "-o", "command="]).output()
+        .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).contains(name));
+    if running_as_name {
+        tracing::warn!("Killing {} left running by a previous session (pid {})", name, pid);
+        let _ = Command::new("kill").arg(pid.to_string()).status();
+    }
+}
+
+fn kill(process: &mut Process) {
+    if let Some(mut child) = process.child.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Restart the process whenever it dies, until the supervisor is dropped.
+fn monitor(shared: Weak<Shared>) {
+    loop {
+        std::thread::sleep(POLL);
+        let Some(shared) = shared.upgrade() else { return };
+        if shared.stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let crashes = {
+            let mut process = shared.process.lock().unwrap();
+            let exited = match process.child.as_mut() {
+                Some(child) => match child.try_wait() {
+                    Ok(Some(status)) => Some(status.to_string()),
+                    _ => None,
+                },
+                // A failed launch left nothing running
+                None => Some("not running".into()),
+            };
+            let Some(exited) = exited else { continue };
+            process.child = None;
+            if process.started.elapsed() >= STABLE_AFTER {
+                process.crashes = 0;
+            }
+            process.crashes += 1;
+            tracing::warn!("{} crashed ({}), restart #{} in {:?}", shared.spec.name, exited, process.crashes, backoff(process.crashes));
+            shared.log(&format!("--- {} exited: {}", shared.spec.name, exited));
+            shared.set_state(&mut process, ServerState::Crashed);
+            process.crashes
+        };
+
+        let resume = Instant::now() + backoff(crashes);
+        while Instant::now() < resume {
+            if shared.stop.load(Ordering::Relaxed) {
+                return;
+            }
+            std::thread::sleep(POLL);
+        }
+        if let Err(e) = shared.launch() {
+            tracing::warn!("{}", e);
+            let mut process = shared.process.lock().unwrap();
+            if shared.stop.load(Ordering::Relaxed) {
+                return;
+            }
+            // Restart the clock so the failed launch counts as a crash, not a stable run
+            process.started = Instant::now();
+            shared.set_state(&mut process, ServerState::Crashed);
+        }
+    }
+}
+
+/// A supervised server; stopping it is dropping it.
+pub struct Supervisor {
+    shared: Arc<Shared>,
+}
+
+impl Supervisor {
+    /// Launch the server, waiting until it is ready, and keep it running from then on. The
+    /// pidfile and key file go next to the log.
+    pub fn start(spec: ServerSpec, log_path: &Path, on_state: StateListener) -> Result<Self> {
+        let pid_path = log_path.with_extension("pid");
+        kill_orphan(&pid_path, &spec.name);
+        let key_path = log_path.with_extension("key");
+        let api_key = api_key();
+        write_private(&key_path, &api_key)
+            .map_err(|e| anyhow::anyhow!("Failed to write API key for {}: {}", spec.name, e))?;
+        let shared = Arc::new(Shared {
+            spec,
+            api_key,
+            log: Arc::new(Mutex::new(RotatingLog::new(log_path, LOG_MAX_BYTES, LOG_KEEP))),
+            key_path,
+            pid_path,
+            on_state,
+            process: Mutex::new(Process {
+                child: None,
+                port: 0,
+                state: ServerState::Stopped,
+                crashes: 0,
+                started: Instant::now(),
+            }),
+            stop: AtomicBool::new(false),
+        });
+        if let Err(e) = shared.launch() {
+            let mut process = shared.process.lock().unwrap();
+            shared.set_state(&mut process, ServerState::Crashed);
+            return Err(e);
+        }
+        let weak = Arc::downgrade(&shared);
+        std::thread::spawn(move || monitor(weak));
+        Ok(Self { shared })
+    }
+
+    /// Port of the running server, or an error while it is down or restarting.
+    pub fn port(&self) -> Result<u16> {
+        let process = self.shared.process.lock().unwrap();
+        match process.state {
+            ServerState::Ready => Ok(process.port),
+            state => anyhow::bail!("{} is not ready ({:?})", self.shared.spec.name, state),
+        }
+    }
+
+    pub fn api_key(&self) -> &str {
+        &self.shared.api_key
+    }
+
+    pub fn state(&self) -> ServerState {
+        self.shared.process.lock().unwrap().state
+    }
+
+    /// Kill the server for good. Dropping the supervisor does this too, but `process::exit`
+    /// runs no destructors.
+    pub fn stop(&self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        let mut process = self.shared.process.lock().unwrap();
+        kill(&mut process);
+        self.shared.remove_files();
+        self.shared.set_state(&mut process, ServerState::Stopped);
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_a_minute() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(7), Duration::from_secs(60));
+        assert_eq!(backoff(100), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn keys_and_ports_differ() {
+        let key = api_key();
+        assert_eq!(key.len(), 32);
+        assert_ne!(key, api_key());
+        assert!(free_port().unwrap() > 0);
+    }
+
+    #[test]
+    fn log_rotates_and_keeps_recent_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("server.log");
+        let mut log = RotatingLog::new(&path, 20, 2);
+        for i in 0..8 {
+            log.write_line(&format!("line number {}", i)).unwrap();
+        }
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "line number 6\nline number 7\n");
+        assert_eq!(read(log.rotated(1)), "line number 4\nline number 5\n");
+        assert_eq!(read(log.rotated(2)), "line number 2\nline number 3\n");
+        assert!(!log.rotated(3).exists());
+    }
+
+    fn shell(script: &'static str) -> ServerSpec {
+        ServerSpec {
+            name: "test-server".into(),
+            command: Box::new(move |port, key_file| {
+                let mut cmd = Command::new("sh");
+                cmd.args(["-c", script, "test-server", &port.to_string()]).arg(key_file);
+                cmd
+            }),
+            is_ready: Box::new(|_, _| true),
+            startup_timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn recorder() -> (StateListener, Arc<Mutex<Vec<ServerState>>>) {
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let log = states.clone();
+        (Arc::new(move |s| log.lock().unwrap().push(s)), states)
+    }
+
+    #[test]
+    fn captures_output_and_passes_port_and_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.log");
+        let (on_state, states) = recorder();
+        let server = Supervisor::start(shell("echo port=$1 key=$(cat $2); echo oops >&2; sleep 30"), &path, on_state).unwrap();
+        let port = server.port().unwrap();
+        let key = server.api_key().to_string();
+        std::thread::sleep(Duration::from_millis(300));
+        let log = fs::read_to_string(&path).unwrap();
+        assert!(log.contains(&format!("port={} key={}", port, key)));
+        assert!(log.contains("oops"));
+        assert!(dir.path().join("server.pid").exists());
+        let key_file = fs::metadata(dir.path().join("server.key")).unwrap();
+        assert_eq!(std::os::unix::fs::PermissionsExt::mode(&key_file.permissions()) & 0o777, 0o600);
+        server.stop();
+        assert!(!dir.path().join("server.pid").exists());
+        assert!(!dir.path().join("server.key").exists());
+        drop(server);
+        assert_eq!(*states.lock().unwrap(), vec![ServerState::Starting, ServerState::Ready, ServerState::Stopped]);
+    }
+
+    #[test]
+    fn kills_server_orphaned_by_previous_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let orphan = |name: &str| Command::new("sh").args(["-c", "sleep 30", name]).spawn().unwrap();
+        let (mut ours, mut unrelated) = (orphan("test-server"), orphan("other"));
+
+        fs::write(dir.path().join("server.pid"), ours.id().to_string()).unwrap();
+        let server = Supervisor::start(shell("sleep 30"), &dir.path().join("server.log"), Arc::new(|_| {})).unwrap();
+        assert!(ours.wait().is_ok());
+        drop(server);
+
+        fs::write(dir.path().join("server.pid"), unrelated.id().to_string()).unwrap();
+        let server = Supervisor::start(shell("sleep 30"), &dir.path().join("server.log"), Arc::new(|_| {})).unwrap();
+        assert!(unrelated.try_wait().unwrap().is_none());
+        drop(server);
+        let _ = unrelated.kill();
+    }
+
+    #[test]
+    fn restarts_after_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let (on_state, states) = recorder();
+        let server = Supervisor::start(shell("sleep 0.3; exit 3"), &dir.path().join("server.log"), on_state).unwrap();
+        let ready_twice = || states.lock().unwrap().iter().filter(|s| **s == ServerState::Ready).count() >= 2;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !ready_twice() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let seen = states.lock().unwrap().clone();
+        assert_eq!(&seen[..4], &[ServerState::Starting, ServerState::Ready, ServerState::Crashed, ServerState::Starting]);
+        assert!(ready_twice());
+        let log = fs::read_to_string(dir.path().join("server.log")).unwrap();
+        assert!(log.contains("exited: exit status: 3"));
+        drop(server);
+    }
+
+    #[test]
+    fn port_fails_fast_while_restarting() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spec = shell("sleep 0.3; exit 3");
+        // Ready the first time, then never again, so the restart sits in startup
+        let checks = std::sync::atomic::AtomicUsize::new(0);
+        spec.is_ready = Box::new(move |_, _| checks.fetch_add(1, Ordering::Relaxed) == 0);
+        let server = Supervisor::start(spec, &dir.path().join("server.log"), Arc::new(|_| {})).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while server.state() != ServerState::Starting && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(server.state(), ServerState::Starting);
+
+        let asked = Instant::now();
+        assert!(server.port().is_err());
+        assert!(asked.elapsed() < Duration::from_millis(100));
+        drop(server);
+        assert!(asked.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn failed_start_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let (on_state, states) = recorder();
+        let mut spec = shell("exit 1");
+        spec.is_ready = Box::new(|_, _| false);
+        assert!(Supervisor::start(spec, &dir.path().join("server.log"), on_state).is_err());
+        assert_eq!(*states.lock().unwrap(), vec![ServerState::Starting, ServerState::Crashed]);
+    }
+}
@@ -0,0 +1,237 @@
+//! Checks that polish cleaned up the transcript rather than answering it. A small model
+//! told "you are NOT an assistant" still replies to dictated questions now and then, or
+//! opens with "Sure! Here's…"; such output must never be typed into the user's document.
+
+use std::collections::HashSet;
+use std::fmt;
+use strsim::normalized_levenshtein;
+
+/// Openers of a chat reply rather than of dictated text.
+const PREAMBLES: &[&str] = &[
+    "sure", "certainly", "of course", "absolutely", "great question", "good question",
+    "here is", "here's", "heres", "i'd be happy", "id be happy", "i can help", "i'm sorry",
+    "im sorry", "as an ai", "polished text", "polished transcript", "cleaned up text",
+    "corrected text", "the corrected", "the polished",
+];
+
+/// Said before a phrase that polish keeps at the front: "um so here's the plan".
+const FILLERS: &[&str] = &["um", "uh", "er", "ah", "hmm", "oh", "so", "well", "like", "okay", "ok"];
+/// Words into the transcript (fillers aside) within which a preamble-like phrase was said.
+const PREAMBLE_WINDOW: usize = 6;
+
+/// Polish may lengthen a little (punctuation spelled out, contractions expanded) but not
+/// write paragraphs of its own.
+const MAX_GROWTH: f64 = 1.5;
+const GROWTH_ALLOWANCE: usize = 4;
+/// Fillers and false starts rarely make up more than this much of what was said.
+const MIN_KEPT: f64 = 0.3;
+/// Share of the raw words that must survive polish; fewer means the content was replaced.
+const MIN_OVERLAP: f64 = 0.5;
+/// Polished words unlike anything said count as new content beyond this share.
+const MAX_NEW: f64 = 0.25;
+const MIN_NEW_ALLOWED: usize = 2;
+/// Spelling and grammar fixes ("buyed" → "bought", "gonna" → "going") stay above this.
+const SIMILAR: f64 = 0.6;
+/// Length checks need enough words to mean anything.
+const MIN_WORDS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    /// Opens like a chat reply ("Sure! Here's…").
+    Preamble(String),
+    /// The transcript asked a question, and polish dropped it for words of its own: it answered.
+    Answered,
+    TooLong { ratio: f64 },
+    TooShort { ratio: f64 },
+    LowOverlap { overlap: f64 },
+    /// Words that appear nowhere in the transcript.
+    NewContent(Vec<String>),
+}
+
+impl Rejection {
+    /// Stable name for logs and counts.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Rejection::Preamble(_) => "preamble",
+            Rejection::Answered => "answered",
+            Rejection::TooLong { .. } => "too_long",
+            Rejection::TooShort { .. } => "too_short",
+            Rejection::LowOverlap { .. } => "low_overlap",
+            Rejection::NewContent(_) => "new_content",
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Preamble(p) => write!(f, "starts with assistant preamble {:?}", p),
+            Rejection::Answered => write!(f, "answered the dictated question"),
+            Rejection::TooLong { ratio } => write!(f, "{:.1}x the words of the transcript", ratio),
+            Rejection::TooShort { ratio } => write!(f, "only {:.0}% of the transcript's words", ratio * 100.0),
+            Rejection::LowOverlap { overlap } => write!(f, "kept only {:.0}% of the transcript's words", overlap * 100.0),
+            Rejection::NewContent(words) => write!(f, "added words not in the transcript: {}", words.join(", ")),
+        }
+    }
+}
+
+/// Polish output refused, and why.
+#[derive(Debug, Clone)]
+pub struct Rejected {
+    pub rejection: Rejection,
+    pub polished: String,
+}
+
+impl Rejected {
+    pub fn new(rejection: Rejection, polished: &str) -> Self {
+        Self { rejection, polished: polished.to_string() }
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|w| w.chars().filter(|c| c.is_alphanumeric() || *c == '\'').flat_map(char::to_lowercase).collect::<String>())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+fn is_said(word: &str, said: &HashSet<String>) -> bool {
+    said.contains(word) || said.iter().any(|s| normalized_levenshtein(s, word) >= SIMILAR)
+}
+
+fn check_preamble(raw: &[String], polished: &[String]) -> Result<(), Rejection> {
+    let opening = polished.iter().take(4).cloned().collect::<Vec<_>>().join(" ");
+    let said = raw.iter()
+        .filter(|w| !FILLERS.contains(&w.as_str()))
+        .take(PREAMBLE_WINDOW)
+        .fold(String::from(" "), |said, w| said + w + " ");
+    // Whole words only, so "surely" isn't "sure"
+    let opens_with = |p: &str| opening == p || opening.starts_with(&format!("{} ", p));
+    match PREAMBLES.iter().find(|p| opens_with(p) && !said.contains(&format!(" {} ", p))) {
+        Some(p) => Err(Rejection::Preamble(p.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Polished words unlike anything in the transcript. Short words are articles and
+/// auxiliaries that grammar fixes add freely, so they don't count.
+fn new_words(raw: &[String], polished: &[String]) -> Vec<String> {
+    let said: HashSet<String> = raw.iter().cloned().collect();
+    polished.iter()
+        .filter(|w| w.chars().count() > 3 && !is_said(w, &said))
+        .cloned()
+        .collect()
+}
+
+/// Whisper's question mark alone isn't enough, polish may fairly turn "can you send it?"
+/// into a request; an answer also says something that wasn't said.
+fn check_answered(raw: &str, polished: &str, new: &[String]) -> Result<(), Rejection> {
+    let asked = raw.contains('?') || raw.contains('？');
+    let still_asks = polished.contains('?') || polished.contains('？');
+    if asked && !still_asks && !new.is_empty() {
+        return Err(Rejection::Answered);
+    }
+    Ok(())
+}
+
+fn check_new_content(polished: &[String], new: Vec<String>) -> Result<(), Rejection> {
+    let allowed = MIN_NEW_ALLOWED.max((polished.len() as f64 * MAX_NEW) as usize);
+    if new.len() > allowed {
+        return Err(Rejection::NewContent(new));
+    }
+    Ok(())
+}
+
+/// Checks that hold for any prefix of the output, so streamed text can be checked before it
+/// is injected. With `translating`, only the preamble is checked.
+pub fn check_partial(raw: &str, polished: &str, translating: bool) -> Result<(), Rejection> {
+    let (raw_words, polished_words) = (words(raw), words(polished));
+    check_preamble(&raw_words, &polished_words)?;
+    if !translating {
+        check_new_content(&polished_words, new_words(&raw_words, &polished_words))?;
+    }
+    Ok(())
+}
+
+/// Full check of finished polish output against the transcript it was given. With
+/// `translating`, word-level comparisons don't apply and only the shape is checked.
+pub fn check(raw: &str, polished: &str, translating: bool) -> Result<(), Rejection> {
+    let (raw_words, polished_words) = (words(raw), words(polished));
+    check_preamble(&raw_words, &polished_words)?;
+
+    let (n_raw, n_polished) = (raw_words.len(), polished_words.len());
+    let ratio = n_polished as f64 / n_raw.max(1) as f64;
+    if n_polished > (n_raw as f64 * MAX_GROWTH) as usize + GROWTH_ALLOWANCE {
+        return Err(Rejection::TooLong { ratio });
+    }
+    if translating {
+        return Ok(());
+    }
+    let new = new_words(&raw_words, &polished_words);
+    check_answered(raw, polished, &new)?;
+    if n_raw >= MIN_WORDS && ratio < MIN_KEPT {
+        return Err(Rejection::TooShort { ratio });
+    }
+
+    if n_raw >= MIN_WORDS {
+        let kept: HashSet<String> = polished_words.iter().cloned().collect();
+        let content: Vec<&String> = raw_words.iter().filter(|w| w.chars().count() > 3).collect();
+        if !content.is_empty() {
+            let overlap = content.iter().filter(|w| is_said(w, &kept)).count() as f64 / content.len() as f64;
+            if overlap < MIN_OVERLAP {
+                return Err(Rejection::LowOverlap { overlap });
+            }
+        }
+    }
+    check_new_content(&polished_words, new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_ordinary_cleanup() {
+        let raw = "um so i went to the store yesterday and uh buyed some milk";
+        assert_eq!(check(raw, "I went to the store yesterday and bought some milk.", false), Ok(()));
+        assert_eq!(check("gonna ship it friday", "Going to ship it Friday.", false), Ok(()));
+        assert_eq!(check("what time is the standup tomorrow", "What time is the standup tomorrow?", false), Ok(()));
+        assert_eq!(check("sure i can do that", "Sure, I can do that.", false), Ok(()));
+        assert_eq!(check("um so here's the plan for friday", "Here's the plan for Friday.", false), Ok(()));
+        assert_eq!(check("Can you send me the report?", "Can you send me the report.", false), Ok(()));
+        assert_eq!(check("surely we can ship friday", "Surely we can ship Friday.", false), Ok(()));
+    }
+
+    #[test]
+    fn rejects_preamble() {
+        let err = check("deploy the new version", "Sure! Here's the polished text: Deploy the new version.", false).unwrap_err();
+        assert_eq!(err.reason(), "preamble");
+        assert!(check_partial("deploy the new version", "Here is the cleaned", false).is_err());
+    }
+
+    #[test]
+    fn rejects_answer_to_question() {
+        let raw = "What is the capital of France?";
+        assert_eq!(check(raw, "The capital of France is Paris.", false), Err(Rejection::Answered));
+        let raw = "how do i reverse a list in python";
+        let answer = "You can reverse a list in Python using the reverse method or slicing with a negative step, which returns a new reversed copy of the list.";
+        assert_eq!(check(raw, answer, false).unwrap_err().reason(), "too_long");
+    }
+
+    #[test]
+    fn rejects_replaced_content() {
+        let raw = "remind me to email the contractor about the kitchen tiles tomorrow";
+        assert_eq!(check(raw, "Okay.", false).unwrap_err().reason(), "too_short");
+        let err = check(raw, "Reminder set for tomorrow morning regarding renovation plans.", false).unwrap_err();
+        assert_eq!(err.reason(), "low_overlap");
+        let err = check_partial("ship the release", "Ship the release after updating changelog documentation thoroughly", false).unwrap_err();
+        assert_eq!(err.reason(), "new_content");
+    }
+
+    #[test]
+    fn translation_checks_only_shape() {
+        assert_eq!(check("namaste sab log", "Hallo zusammen.", true), Ok(()));
+        assert_eq!(check_partial("namaste sab log", "Hallo zusammen.", true), Ok(()));
+        assert!(check("namaste sab log", "Sure! Hallo zusammen.", true).is_err());
+    }
+}